@@ -0,0 +1,62 @@
+use {
+  bitcoin::Network,
+  bitomc::{settings::Settings, Index, Options},
+  clap::Parser,
+  criterion::{criterion_group, criterion_main, Criterion},
+  std::fs,
+  tempfile::TempDir,
+};
+
+const BLOCKS: u32 = 5000;
+
+/// Build an index from scratch over a regtest chain which is entirely below
+/// the first rune height, fetching only headers unless `full_blocks` is set
+fn sync(core: &mockcore::Handle, full_blocks: bool) {
+  let tempdir = TempDir::new().unwrap();
+  let cookie_file = tempdir.path().join("cookie");
+  fs::write(&cookie_file, "username:password").unwrap();
+
+  let options = Options::try_parse_from([
+    "bitomc",
+    "--bitcoin-rpc-url",
+    &core.url(),
+    "--datadir",
+    tempdir.path().to_str().unwrap(),
+    "--cookie-file",
+    cookie_file.to_str().unwrap(),
+    "--chain",
+    "regtest",
+    "--first-rune-height",
+    &(BLOCKS + 1).to_string(),
+  ])
+  .unwrap();
+
+  let mut index = Index::open(&Settings::from_options(options).or_defaults().unwrap()).unwrap();
+
+  if full_blocks {
+    index.fetch_full_blocks();
+  }
+
+  index.update().unwrap();
+
+  assert_eq!(index.block_count().unwrap(), BLOCKS + 1);
+}
+
+fn initial_sync(c: &mut Criterion) {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(BLOCKS.into());
+
+  let mut group = c.benchmark_group("initial_sync_below_first_rune_height");
+
+  group.sample_size(10);
+
+  group.bench_function("headers", |b| b.iter(|| sync(&core, false)));
+
+  group.bench_function("full_blocks", |b| b.iter(|| sync(&core, true)));
+
+  group.finish();
+}
+
+criterion_group!(benches, initial_sync);
+criterion_main!(benches);
@@ -248,6 +248,12 @@ impl Handle {
     u64::try_from(self.state().blocks.len()).unwrap() - 1
   }
 
+  /// Prune blocks below `height`, after which only their headers are
+  /// available
+  pub fn prune(&self, height: u32) {
+    self.state().prune_height = Some(height);
+  }
+
   pub fn invalidate_tip(&self) -> BlockHash {
     self.state().pop_block()
   }
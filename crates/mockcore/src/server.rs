@@ -23,6 +23,14 @@ impl Server {
   fn not_found() -> jsonrpc_core::Error {
     jsonrpc_core::Error::new(jsonrpc_core::types::error::ErrorCode::ServerError(-8))
   }
+
+  fn pruned() -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+      code: jsonrpc_core::types::error::ErrorCode::ServerError(-1),
+      message: "Block not available (pruned data)".into(),
+      data: None,
+    }
+  }
 }
 
 impl Api for Server {
@@ -49,6 +57,8 @@ impl Api for Server {
   }
 
   fn get_blockchain_info(&self) -> Result<GetBlockchainInfoResult, jsonrpc_core::Error> {
+    let state = self.state();
+
     Ok(GetBlockchainInfoResult {
       chain: String::from(match self.network {
         Network::Bitcoin => "main",
@@ -59,15 +69,15 @@ impl Api for Server {
       }),
       blocks: 0,
       headers: 0,
-      best_block_hash: self.state().hashes[0],
+      best_block_hash: state.hashes[0],
       difficulty: 0.0,
       median_time: 0,
       verification_progress: 0.0,
       initial_block_download: false,
       chain_work: Vec::new(),
       size_on_disk: 0,
-      pruned: false,
-      prune_height: None,
+      pruned: state.prune_height.is_some(),
+      prune_height: state.prune_height.map(u64::from),
       automatic_pruning: None,
       prune_target_size: None,
       softforks: HashMap::new(),
@@ -229,8 +239,22 @@ impl Api for Server {
     verbosity: u64,
   ) -> Result<String, jsonrpc_core::Error> {
     assert_eq!(verbosity, 0, "Verbosity level {verbosity} is unsupported");
-    match self.state().blocks.get(&block_hash) {
-      Some(block) => Ok(hex::encode(serialize(block))),
+    let state = self.state();
+    match state.blocks.get(&block_hash) {
+      Some(block) => {
+        if let Some(prune_height) = state.prune_height {
+          if state
+            .hashes
+            .iter()
+            .take(prune_height.try_into().unwrap())
+            .any(|hash| *hash == block_hash)
+          {
+            return Err(Self::pruned());
+          }
+        }
+
+        Ok(hex::encode(serialize(block)))
+      }
       None => Err(Self::not_found()),
     }
   }
@@ -275,7 +299,15 @@ impl Api for Server {
       confirmations: confirmations.unwrap().try_into().unwrap(),
       script_pub_key: GetRawTransactionResultVoutScriptPubKey {
         asm: String::new(),
-        hex: Vec::new(),
+        hex: state
+          .transactions
+          .get(&txid)
+          .map(|tx| {
+            tx.output[usize::try_from(vout).unwrap()]
+              .script_pubkey
+              .to_bytes()
+          })
+          .unwrap_or_default(),
         req_sigs: None,
         type_: None,
         addresses: Vec::new(),
@@ -675,8 +707,13 @@ impl Api for Server {
 
     let blockhash = tx_height.map(|tx_height| state.hashes[usize::try_from(*tx_height).unwrap()]);
 
+    let transaction = state
+      .transactions
+      .get(&txid)
+      .or_else(|| state.mempool.iter().find(|tx| tx.txid() == txid));
+
     if verbose.unwrap_or(false) {
-      match state.transactions.get(&txid) {
+      match transaction {
         Some(transaction) => Ok(
           serde_json::to_value(GetRawTransactionResult {
             in_active_chain: Some(true),
@@ -715,7 +752,7 @@ impl Api for Server {
         None => Err(Self::not_found()),
       }
     } else {
-      match state.transactions.get(&txid) {
+      match transaction {
         Some(tx) => Ok(Value::String(hex::encode(serialize(tx)))),
         None => Err(Self::not_found()),
       }
@@ -18,6 +18,7 @@ pub struct State {
   pub mempool: Vec<Transaction>,
   pub network: Network,
   pub nonce: u32,
+  pub prune_height: Option<u32>,
   pub transactions: BTreeMap<Txid, Transaction>,
   pub txid_to_block_height: BTreeMap<Txid, u32>,
   pub utxos: BTreeMap<OutPoint, Amount>,
@@ -48,6 +49,7 @@ impl State {
       mempool: Vec::new(),
       network,
       nonce: 0,
+      prune_height: None,
       receive_addresses: Vec::new(),
       transactions: BTreeMap::new(),
       txid_to_block_height: BTreeMap::new(),
@@ -1,6 +1,7 @@
 use super::*;
 
-#[derive(Serialize, Eq, PartialEq, Deserialize, Debug)]
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Artifact {
   Cenotaph(Cenotaph),
   Runestone(Runestone),
@@ -1,6 +1,7 @@
 use super::*;
 
-#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Default)]
+#[derive(Eq, PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Cenotaph {
   pub flaw: Option<Flaw>,
 }
@@ -0,0 +1,241 @@
+use super::*;
+
+/// Everything decoded from a runestone output, including the parts of a
+/// cenotaph that were decoded before its flaw was encountered.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Diagnostics {
+  /// Index of the output containing the runestone
+  pub output: u32,
+  pub flaw: Option<Flaw>,
+  /// Byte offset into the runestone output script of the invalid opcode,
+  /// script, or varint
+  pub offset: Option<usize>,
+  /// Integers decoded before the flaw
+  pub integers: Vec<u128>,
+  /// Edicts that would have applied had the runestone not been a cenotaph
+  pub edicts: Vec<Edict>,
+  pub pointer: Option<u32>,
+}
+
+impl From<Diagnostics> for Artifact {
+  fn from(diagnostics: Diagnostics) -> Self {
+    match diagnostics.flaw {
+      Some(flaw) => Artifact::Cenotaph(Cenotaph { flaw: Some(flaw) }),
+      None => Artifact::Runestone(Runestone {
+        edicts: diagnostics.edicts,
+        pointer: diagnostics.pointer,
+      }),
+    }
+  }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+  use {
+    super::*,
+    bitcoin::{
+      locktime::absolute::LockTime,
+      script::{self, PushBytesBuf},
+      TxOut,
+    },
+  };
+
+  fn transaction(script_pubkey: ScriptBuf) -> Transaction {
+    Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: Vec::new(),
+      output: vec![
+        TxOut {
+          script_pubkey: ScriptBuf::new(),
+          value: 0,
+        },
+        TxOut {
+          script_pubkey,
+          value: 0,
+        },
+      ],
+    }
+  }
+
+  fn payload(bytes: &[u8]) -> ScriptBuf {
+    script::Builder::new()
+      .push_opcode(opcodes::all::OP_RETURN)
+      .push_opcode(Runestone::MAGIC_NUMBER)
+      .push_slice(PushBytesBuf::try_from(bytes.to_vec()).unwrap())
+      .into_script()
+  }
+
+  #[test]
+  fn runestone_diagnostics_match_decipher() {
+    let runestone = Runestone {
+      edicts: vec![Edict {
+        id: ID1,
+        amount: 5,
+        output: 0,
+      }],
+      pointer: Some(0),
+    };
+
+    let tx = transaction(runestone.encipher());
+
+    let diagnostics = Runestone::diagnose(&tx).unwrap();
+
+    assert_eq!(
+      diagnostics,
+      Diagnostics {
+        output: 1,
+        flaw: None,
+        offset: None,
+        integers: vec![0, 11, 0],
+        edicts: runestone.edicts.clone(),
+        pointer: Some(0),
+      }
+    );
+
+    assert_eq!(
+      Artifact::from(diagnostics),
+      Artifact::Runestone(runestone.clone())
+    );
+    assert_eq!(
+      Runestone::decipher(&tx),
+      Some(Artifact::Runestone(runestone))
+    );
+  }
+
+  #[test]
+  fn no_runestone() {
+    assert_eq!(Runestone::diagnose(&transaction(ScriptBuf::new())), None);
+  }
+
+  #[test]
+  fn opcode_offset() {
+    let tx = transaction(
+      script::Builder::new()
+        .push_opcode(opcodes::all::OP_RETURN)
+        .push_opcode(Runestone::MAGIC_NUMBER)
+        .push_slice([0, 1])
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .into_script(),
+    );
+
+    assert_eq!(
+      Runestone::diagnose(&tx).unwrap(),
+      Diagnostics {
+        output: 1,
+        flaw: Some(Flaw::Opcode),
+        offset: Some(5),
+        integers: vec![0, 1],
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn invalid_script_offset() {
+    let mut script = payload(&[2, 0]).into_bytes();
+    script.push(opcodes::all::OP_PUSHBYTES_4.to_u8());
+
+    let tx = transaction(ScriptBuf::from_bytes(script));
+
+    assert_eq!(
+      Runestone::diagnose(&tx).unwrap(),
+      Diagnostics {
+        output: 1,
+        flaw: Some(Flaw::InvalidScript),
+        offset: Some(5),
+        integers: vec![2, 0],
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn opcode_keeps_integers_from_earlier_pushes_only() {
+    let tx = transaction(
+      script::Builder::new()
+        .push_opcode(opcodes::all::OP_RETURN)
+        .push_opcode(Runestone::MAGIC_NUMBER)
+        .push_slice([10, 0x80])
+        .push_slice([1])
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .push_slice([3])
+        .into_script(),
+    );
+
+    assert_eq!(
+      Runestone::diagnose(&tx).unwrap(),
+      Diagnostics {
+        output: 1,
+        flaw: Some(Flaw::Opcode),
+        offset: Some(7),
+        integers: vec![10, 128],
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn varint_offset_and_edicts_before_flaw() {
+    let tx = transaction(payload(&[10, 0, 0x80]));
+
+    let diagnostics = Runestone::diagnose(&tx).unwrap();
+
+    assert_eq!(
+      diagnostics,
+      Diagnostics {
+        output: 1,
+        flaw: Some(Flaw::Varint),
+        offset: Some(5),
+        integers: vec![10, 0],
+        edicts: vec![Edict {
+          id: ID0,
+          amount: 5,
+          output: 0,
+        }],
+        pointer: None,
+      }
+    );
+
+    assert_eq!(
+      Artifact::from(diagnostics),
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::Varint),
+      }),
+    );
+  }
+
+  #[test]
+  fn varint_offset_after_pushdata1() {
+    let mut bytes = vec![0; 76];
+    bytes.push(0x80);
+
+    let diagnostics = Runestone::diagnose(&transaction(payload(&bytes))).unwrap();
+
+    assert_eq!(diagnostics.flaw, Some(Flaw::Varint));
+    assert_eq!(diagnostics.offset, Some(80));
+    assert_eq!(diagnostics.integers.len(), 76);
+  }
+
+  #[test]
+  fn edict_output_keeps_earlier_edicts() {
+    let tx = transaction(payload(&[10, 0, 7, 9]));
+
+    assert_eq!(
+      Runestone::diagnose(&tx).unwrap(),
+      Diagnostics {
+        output: 1,
+        flaw: Some(Flaw::EdictOutput),
+        offset: None,
+        integers: vec![10, 0, 7, 9],
+        edicts: vec![Edict {
+          id: ID0,
+          amount: 5,
+          output: 0,
+        }],
+        pointer: None,
+      }
+    );
+  }
+}
@@ -1,6 +1,7 @@
 use super::*;
 
-#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Copy, Clone, Eq)]
+#[derive(Default, Debug, PartialEq, Copy, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Edict {
   pub id: RuneId,
   pub amount: u128,
@@ -8,14 +9,25 @@ pub struct Edict {
 }
 
 impl Edict {
+  #[cfg(feature = "std")]
   pub fn from_integers(tx: &Transaction, id: RuneId, amount: u128, output: u128) -> Option<Self> {
+    Self::from_integers_with_outputs(tx.output.len(), id, amount, output)
+  }
+
+  /// Like `from_integers`, for a transaction with `outputs` outputs
+  pub fn from_integers_with_outputs(
+    outputs: usize,
+    id: RuneId,
+    amount: u128,
+    output: u128,
+  ) -> Option<Self> {
     let Ok(output) = u32::try_from(output) else {
       return None;
     };
 
-    // note that this allows `output == tx.output.len()`, which means to divide
+    // note that this allows `output == outputs`, which means to divide
     // amount between all non-OP_RETURN outputs
-    if output > u32::try_from(tx.output.len()).unwrap() {
+    if u64::from(output) > u64::try_from(outputs).unwrap() {
       return None;
     }
 
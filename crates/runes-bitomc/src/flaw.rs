@@ -1,7 +1,8 @@
 use super::*;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum Flaw {
   EdictOutput,
   EdictRuneId,
@@ -12,6 +13,7 @@ pub enum Flaw {
   TruncatedField,
   UnrecognizedEvenTag,
   UnrecognizedFlag,
+  UnrecognizedVersion,
   Varint,
 }
 
@@ -27,6 +29,7 @@ impl Display for Flaw {
       Self::TruncatedField => write!(f, "field with missing value"),
       Self::UnrecognizedEvenTag => write!(f, "unrecognized even tag"),
       Self::UnrecognizedFlag => write!(f, "unrecognized field"),
+      Self::UnrecognizedVersion => write!(f, "unrecognized payload version"),
       Self::Varint => write!(f, "invalid varint"),
     }
   }
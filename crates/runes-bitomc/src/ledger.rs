@@ -0,0 +1,1297 @@
+use super::*;
+
+/// The BitOMC ledger rules, free of any storage.
+///
+/// A `Ledger` holds the supply state of Tighten and Ease and the tips of the
+/// mint and conversion chains. Transactions are applied one at a time, in
+/// block order, and the resulting allocations are returned to the caller,
+/// which is responsible for persisting them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ledger {
+  pub rules: Rules,
+  pub state: SupplyState,
+  pub mint_outpoint: OutPoint,
+  pub mint_value: u64,
+  pub conversion_outpoint: OutPoint,
+  pub conversion_value: u64,
+  /// Portion of the burned balances issued as block rewards and not yet
+  /// claimed by a mint, or `None` if it was not tracked since the last mint.
+  /// The remainder was burned by transactions.
+  pub unclaimed_reward: Option<(u128, u128)>,
+  require_conversion_outpoint: bool,
+  mint_script_pubkey: ScriptBuf,
+  conversion_script_pubkey: ScriptBuf,
+}
+
+/// The effect of applying a single transaction to the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Outcome {
+  pub artifact: Option<Artifact>,
+  /// Balances assigned to each transaction output. OP_RETURN outputs never
+  /// receive balances, since anything sent to them is burned.
+  pub allocations: Vec<BTreeMap<RuneId, u128>>,
+  pub burned: BTreeMap<RuneId, u128>,
+  pub minted: Option<(u128, u128)>,
+  /// Portion of `minted` recycled from burned runes rather than issued as
+  /// block rewards, or `None` if the unclaimed reward was not tracked
+  pub recycled: Option<(u128, u128)>,
+  /// Output assigned the runes left unallocated by edicts, including any
+  /// minted runes, or `None` if there was no mint or they were burned
+  pub mint_recipient: Option<u32>,
+  pub conversion: Option<Conversion>,
+  pub failed_conversion: Option<FailedConversion>,
+}
+
+/// A successful conversion between Tighten and Ease.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Conversion {
+  pub input_id: RuneId,
+  pub output_id: RuneId,
+  pub exact_input: bool,
+  pub input: u128,
+  pub output: u128,
+  /// For exact input conversions, the output received above the minimum.
+  /// For exact output conversions, the input returned below the maximum.
+  pub residual: u128,
+}
+
+/// Why an attempted conversion was not applied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConversionFailure {
+  #[error("output below minimum or input above maximum")]
+  Slippage,
+  #[error("conversion exceeds supply")]
+  InsufficientSupply,
+  #[error("transaction does not signal RBF")]
+  MissingRbf,
+  #[error("transaction does not extend the conversion chain")]
+  BrokenChain,
+}
+
+/// A conversion that was attempted but not applied. The input is returned
+/// as if no conversion had been requested.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FailedConversion {
+  pub input_id: RuneId,
+  pub output_id: RuneId,
+  pub exact_input: bool,
+  /// Input offered, or maximum input for exact output conversions
+  pub input: u128,
+  /// Output requested, or minimum output for exact input conversions
+  pub output: u128,
+  pub failure: ConversionFailure,
+  /// Output that received the input, or `None` if it was burned
+  pub refund: Option<u32>,
+}
+
+impl Ledger {
+  /// Creates a ledger at the start of a block. Conversions must extend the
+  /// conversion chain until the chain is broken and recreated in this block.
+  pub fn new(
+    rules: Rules,
+    state: SupplyState,
+    mint: (OutPoint, u64),
+    conversion: (OutPoint, u64),
+  ) -> Self {
+    Self {
+      rules,
+      state,
+      mint_outpoint: mint.0,
+      mint_value: mint.1,
+      conversion_outpoint: conversion.0,
+      conversion_value: conversion.1,
+      unclaimed_reward: Some((0, 0)),
+      require_conversion_outpoint: true,
+      mint_script_pubkey: Self::mint_script_pubkey(),
+      conversion_script_pubkey: Self::conversion_script_pubkey(),
+    }
+  }
+
+  /// Witness script of mint outputs: 1 CHECKSEQUENCEVERIFY (anyone can spend
+  /// after 1 block)
+  pub fn mint_script() -> ScriptBuf {
+    ScriptBuf::from_bytes(Vec::from(&[0x51, 0xb2]))
+  }
+
+  pub fn mint_script_pubkey() -> ScriptBuf {
+    ScriptBuf::new_v0_p2wsh(&Self::mint_script().wscript_hash())
+  }
+
+  /// Conversion outputs are p2wpkh for the address with private key
+  /// 0101...0101
+  pub fn conversion_script_pubkey() -> ScriptBuf {
+    let secp = Secp256k1::new();
+    let private_key = PrivateKey::from_slice(&[1; 32], Network::Bitcoin).unwrap();
+    let pub_key = private_key.public_key(&secp);
+    ScriptBuf::new_v0_p2wpkh(&pub_key.wpubkey_hash().unwrap())
+  }
+
+  /// Issues the block reward, split between the runes such that the
+  /// converted supply increases by `reward`. Issued runes are held as burned
+  /// until claimed by a mint.
+  pub fn issue(&mut self, reward: u128) -> (u128, u128) {
+    let SupplyState {
+      supply0, supply1, ..
+    } = self.state;
+
+    let sum_of_sq = supply0 * supply0 + supply1 * supply1;
+    let (amount0, amount1) = if sum_of_sq == 0 {
+      // Assign entire reward to amount0
+      (reward, 0)
+    } else {
+      let k = sum_of_sq.sqrt();
+      (supply0 * reward / k, supply1 * reward / k)
+    };
+
+    self.state.supply0 += amount0;
+    self.state.supply1 += amount1;
+    self.state.burned0 += amount0;
+    self.state.burned1 += amount1;
+    if let Some((reward0, reward1)) = &mut self.unclaimed_reward {
+      *reward0 += amount0;
+      *reward1 += amount1;
+    }
+
+    (amount0, amount1)
+  }
+
+  /// Applies a transaction whose inputs held `balances`.
+  pub fn apply(
+    &mut self,
+    tx: &Transaction,
+    txid: Txid,
+    balances: BTreeMap<RuneId, u128>,
+  ) -> Outcome {
+    let artifact = Runestone::decipher_with(tx, self.rules.version);
+
+    let mut unallocated = balances
+      .into_iter()
+      .map(|(id, balance)| (id, Lot(balance)))
+      .collect::<BTreeMap<RuneId, Lot>>();
+
+    let mut allocated: Vec<BTreeMap<RuneId, Lot>> = vec![BTreeMap::new(); tx.output.len()];
+
+    let mut last_id: Option<RuneId> = None;
+    let mut converted: BTreeMap<RuneId, Lot> = BTreeMap::new();
+    let mut allocated_conversion: Vec<BTreeMap<RuneId, Lot>> =
+      vec![BTreeMap::new(); tx.output.len()];
+
+    let mut burned: BTreeMap<RuneId, Lot> = BTreeMap::new();
+    let mut edicts: Vec<Edict> = Vec::new();
+
+    let mut minted = None;
+    let mut recycled = None;
+    let mut mint_recipient = None;
+    let mut conversion = None;
+    let mut failed_conversion = None;
+
+    // non-OP_RETURN, non-mint, and non-conversion outputs
+    let destinations = tx
+      .output
+      .iter()
+      .enumerate()
+      .filter_map(|(output, tx_out)| {
+        self
+          .is_valid_destination(&tx_out.script_pubkey)
+          .then_some(output)
+      })
+      .collect::<Vec<usize>>();
+
+    self.update_outpoints(tx, txid);
+
+    if let Some(artifact) = &artifact {
+      let unclaimed_reward = self.unclaimed_reward;
+      if let Some((amount0, amount1)) = self.mint(tx, txid) {
+        *unallocated.entry(ID0).or_default() += amount0;
+        *unallocated.entry(ID1).or_default() += amount1;
+        minted = Some((amount0.n(), amount1.n()));
+        recycled = unclaimed_reward.map(|(reward0, reward1)| {
+          (
+            amount0.n().saturating_sub(reward0),
+            amount1.n().saturating_sub(reward1),
+          )
+        });
+      }
+
+      if let Artifact::Runestone(runestone) = artifact {
+        edicts.clone_from(&runestone.edicts);
+        for Edict { id, amount, output } in runestone.edicts.iter().copied() {
+          let amount = Lot(amount);
+          last_id = Some(id);
+
+          // edicts with output values greater than the number of outputs
+          // should never be produced by the edict parser
+          let output = usize::try_from(output).unwrap();
+          assert!(output <= tx.output.len());
+
+          let Some(balance) = unallocated.get_mut(&id) else {
+            if amount > 0 {
+              if output < tx.output.len() {
+                *allocated_conversion[output].entry(id).or_default() += amount;
+                *converted.entry(id).or_default() += amount;
+              } else if !destinations.is_empty() {
+                for output in &destinations {
+                  *allocated_conversion[*output].entry(id).or_default() += amount;
+                }
+                *converted.entry(id).or_default() +=
+                  amount * destinations.len().try_into().unwrap();
+              }
+            }
+            continue;
+          };
+
+          let mut allocate = |balance: &mut Lot, amount: Lot, output: usize| {
+            if amount > 0 {
+              *balance -= amount;
+              *allocated[output].entry(id).or_default() += amount;
+            }
+          };
+
+          if output == tx.output.len() {
+            if !destinations.is_empty() {
+              if amount == 0 {
+                // if amount is zero, divide balance between eligible outputs
+                let amount = *balance / destinations.len() as u128;
+                let remainder = usize::try_from(*balance % destinations.len() as u128).unwrap();
+
+                for (i, output) in destinations.iter().enumerate() {
+                  allocate(
+                    balance,
+                    if i < remainder { amount + 1 } else { amount },
+                    *output,
+                  );
+                }
+              } else {
+                // if amount is non-zero, distribute amount to eligible outputs
+                for output in destinations.iter().copied() {
+                  if amount > *balance {
+                    // if amount exceeds balance, add remaining amount to (potential) conversion output amount
+                    *converted.entry(id).or_default() += amount - *balance;
+                    *allocated_conversion[output].entry(id).or_default() += amount - *balance;
+                  }
+                  allocate(balance, amount.min(*balance), output);
+                }
+              }
+            }
+          } else {
+            // if amount exceeds balance, add remaining amount to (potential) conversion output amount
+            if amount > *balance {
+              *converted.entry(id).or_default() += amount - *balance;
+              *allocated_conversion[output].entry(id).or_default() += amount - *balance;
+            }
+
+            // Get the allocatable amount
+            let amount = if amount == 0 {
+              *balance
+            } else {
+              amount.min(*balance)
+            };
+
+            allocate(balance, amount, output);
+          }
+        }
+      }
+    }
+
+    if let Some(Artifact::Cenotaph(_)) = artifact {
+      for (id, balance) in unallocated {
+        *burned.entry(id).or_default() += balance;
+      }
+    } else {
+      let pointer = match &artifact {
+        Some(Artifact::Runestone(runestone)) => runestone.pointer,
+        _ => None,
+      };
+
+      // assign all un-allocated runes to the default output, or the first valid
+      // destination if there is no default
+      if let Some(vout) = pointer
+        .map(|pointer| {
+          let pointer = usize::try_from(pointer).unwrap();
+          assert!(pointer < allocated.len());
+          pointer
+        })
+        .or_else(|| destinations.first().copied())
+      {
+        if minted.is_some() && !tx.output[vout].script_pubkey.is_op_return() {
+          mint_recipient = Some(u32::try_from(vout).unwrap());
+        }
+
+        for (id, balance) in unallocated {
+          if balance > 0 {
+            *allocated[vout].entry(id).or_default() += balance;
+          }
+        }
+      } else {
+        for (id, balance) in unallocated {
+          if balance > 0 {
+            *burned.entry(id).or_default() += balance;
+          }
+        }
+      }
+    }
+
+    // increment burned balances
+    for (vout, balances) in allocated.iter_mut().enumerate() {
+      if !balances.is_empty() && tx.output[vout].script_pubkey.is_op_return() {
+        for (id, balance) in balances.iter_mut() {
+          *burned.entry(*id).or_default() += *balance;
+          // zero out allocation so that burned balance does not increment a second time
+          *balance = Lot(0);
+        }
+      }
+    }
+
+    // check if this transaction contains a conversion
+    let conversion_ids = if burned.get(&ID0).copied().unwrap_or_default() > 0
+      && converted.get(&ID1).copied().unwrap_or_default() > 0
+    {
+      Some((ID0, ID1))
+    } else if burned.get(&ID1).copied().unwrap_or_default() > 0
+      && converted.get(&ID0).copied().unwrap_or_default() > 0
+    {
+      Some((ID1, ID0))
+    } else {
+      None
+    };
+
+    if let (Some((input_id, output_id)), Some(residual_id)) = (conversion_ids, last_id) {
+      let mut failure = None;
+
+      if residual_id == output_id {
+        // convert exact input
+        let input_amt = burned.get(&input_id).copied().unwrap_or_default();
+        let min_output_amt = converted.get(&output_id).copied().unwrap_or_default();
+        match self.convert_exact_input(tx, txid, input_id, output_id, input_amt, min_output_amt) {
+          Ok(output_amt) => {
+            // undo burned entry if conversion successful
+            burned.insert(input_id, Lot(0));
+
+            // allocate conversion outputs and assign residual output
+            let mut residual_vout: Option<usize> = None;
+            for (vout, balances) in allocated_conversion.iter().enumerate() {
+              let Some(balance) = balances.get(&output_id) else {
+                continue;
+              };
+
+              // conversion output values greater than or equal to the number of outputs
+              // should never be produced by the initial edict scan
+              assert!(vout < tx.output.len());
+
+              *allocated[vout].entry(output_id).or_default() += *balance;
+
+              // residual output is first conversion output
+              if residual_vout.is_none() {
+                residual_vout = Some(vout);
+              }
+            }
+
+            // add residual amount to residual vout
+            if let Some(residual_vout) = residual_vout {
+              *allocated[residual_vout].entry(output_id).or_default() +=
+                output_amt - min_output_amt;
+            } else {
+              *burned.entry(output_id).or_default() += output_amt - min_output_amt;
+            }
+
+            conversion = Some(Conversion {
+              input_id,
+              output_id,
+              exact_input: true,
+              input: input_amt.n(),
+              output: output_amt.n(),
+              residual: (output_amt - min_output_amt).n(),
+            });
+          }
+          Err(err) => failure = Some((true, input_amt, min_output_amt, err)),
+        }
+      } else {
+        // convert exact output
+        let max_input_amt = burned.get(&input_id).copied().unwrap_or_default();
+        let output_amt = converted.get(&output_id).copied().unwrap_or_default();
+        match self.convert_exact_output(tx, txid, input_id, output_id, output_amt, max_input_amt) {
+          Ok(input_amt) => {
+            // allocate conversion outputs
+            for (vout, balances) in allocated_conversion.iter().enumerate() {
+              let Some(balance) = balances.get(&output_id) else {
+                continue;
+              };
+
+              // conversion output values greater than or equal to the number of outputs
+              // should never be produced by the initial edict scan
+              assert!(vout < tx.output.len());
+
+              *allocated[vout].entry(output_id).or_default() += *balance;
+            }
+
+            // assign residual to input balance by adding it to burned entry
+            burned.insert(input_id, max_input_amt - input_amt);
+
+            conversion = Some(Conversion {
+              input_id,
+              output_id,
+              exact_input: false,
+              input: input_amt.n(),
+              output: output_amt.n(),
+              residual: (max_input_amt - input_amt).n(),
+            });
+          }
+          Err(err) => failure = Some((false, max_input_amt, output_amt, err)),
+        }
+      }
+
+      // add burned entry back to input balance
+      let mut residual = burned.get(&input_id).copied().unwrap_or_default();
+      let mut refund = None;
+      if residual > 0 {
+        // allocate input amount to output of last edict of input_id with valid output
+        if let Some(output) = edicts
+          .iter()
+          .rev()
+          .filter(|edict| edict.id == input_id)
+          .map(|edict| usize::try_from(edict.output).unwrap())
+          .find(|output| *output < tx.output.len())
+        {
+          *allocated[output].entry(input_id).or_default() += residual;
+          residual = Lot(0);
+          refund = Some(output);
+        }
+
+        // if unallocated, allocate to first output with non-zero balance for input_id
+        if residual > 0 {
+          if let Some((output, balances)) = allocated
+            .iter_mut()
+            .enumerate()
+            .find(|(_, balances)| balances.get(&input_id).copied().unwrap_or_default() > 0)
+          {
+            *balances.entry(input_id).or_default() += residual;
+            residual = Lot(0);
+            refund = Some(output);
+          }
+        }
+
+        // if unallocated, allocate input amount to output of first edict
+        if residual > 0 {
+          let mut output = usize::try_from(edicts[0].output).unwrap();
+          if output == tx.output.len() {
+            if let Some(destination) = destinations.first() {
+              output = *destination;
+            }
+          }
+
+          if output < tx.output.len() {
+            *allocated[output].entry(input_id).or_default() += residual;
+            residual = Lot(0);
+            refund = Some(output);
+          }
+        }
+
+        burned.insert(input_id, residual);
+      }
+
+      if let Some((exact_input, input, output, failure)) = failure {
+        failed_conversion = Some(FailedConversion {
+          input_id,
+          output_id,
+          exact_input,
+          input: input.n(),
+          output: output.n(),
+          failure,
+          // input sent to an OP_RETURN output is burned below
+          refund: refund
+            .filter(|output| !tx.output[*output].script_pubkey.is_op_return())
+            .map(|output| u32::try_from(output).unwrap()),
+        });
+      }
+    }
+
+    // increment burned balances created by conversion
+    for (vout, balances) in allocated.iter_mut().enumerate() {
+      if tx.output[vout].script_pubkey.is_op_return() {
+        for (id, balance) in std::mem::take(balances) {
+          *burned.entry(id).or_default() += balance;
+        }
+      }
+    }
+
+    let burned = burned
+      .into_iter()
+      .filter(|(_, amount)| *amount > 0)
+      .map(|(id, amount)| (id, amount.n()))
+      .collect::<BTreeMap<RuneId, u128>>();
+
+    self.state.burned0 += burned.get(&ID0).copied().unwrap_or_default();
+    self.state.burned1 += burned.get(&ID1).copied().unwrap_or_default();
+
+    Outcome {
+      artifact,
+      allocations: allocated
+        .into_iter()
+        .map(|balances| {
+          balances
+            .into_iter()
+            .map(|(id, balance)| (id, balance.n()))
+            .collect()
+        })
+        .collect(),
+      burned,
+      minted,
+      recycled,
+      mint_recipient,
+      conversion,
+      failed_conversion,
+    }
+  }
+
+  fn update_outpoints(&mut self, tx: &Transaction, txid: Txid) {
+    let last_mint_outpoint = self.mint_outpoint;
+    let last_conversion_outpoint = self.conversion_outpoint;
+
+    if last_mint_outpoint == OutPoint::null() && last_conversion_outpoint == OutPoint::null() {
+      return;
+    }
+
+    if tx.is_coin_base() {
+      return;
+    }
+
+    for input in &tx.input {
+      if input.previous_output == last_mint_outpoint {
+        (self.mint_outpoint, self.mint_value) =
+          Self::find_output(tx, txid, &self.mint_script_pubkey).unwrap_or((OutPoint::null(), 0));
+      }
+
+      if input.previous_output == last_conversion_outpoint {
+        (self.conversion_outpoint, self.conversion_value) =
+          Self::find_output(tx, txid, &self.conversion_script_pubkey)
+            .unwrap_or((OutPoint::null(), 0));
+      }
+    }
+  }
+
+  fn find_output(
+    tx: &Transaction,
+    txid: Txid,
+    script_pubkey: &ScriptBuf,
+  ) -> Option<(OutPoint, u64)> {
+    let vout = tx
+      .output
+      .iter()
+      .position(|tx_out| tx_out.script_pubkey == *script_pubkey)?;
+
+    Some((
+      OutPoint {
+        txid,
+        vout: u32::try_from(vout).unwrap(),
+      },
+      tx.output[vout].value,
+    ))
+  }
+
+  fn is_valid_destination(&self, script_pubkey: &ScriptBuf) -> bool {
+    !(script_pubkey.is_op_return()
+      || *script_pubkey == self.mint_script_pubkey
+      || *script_pubkey == self.conversion_script_pubkey)
+  }
+
+  fn mint(&mut self, tx: &Transaction, txid: Txid) -> Option<(Lot, Lot)> {
+    // Transaction must signal RBF
+    if !tx.is_explicitly_rbf() {
+      return None;
+    }
+
+    if self.mint_outpoint == OutPoint::null() {
+      // If no saved outpoint, this transaction must create one with a mint script
+      (self.mint_outpoint, self.mint_value) =
+        Self::find_output(tx, txid, &self.mint_script_pubkey)?;
+    } else if self.mint_outpoint.txid != txid {
+      // Saved outpoint must point to this transaction
+      return None;
+    }
+
+    let amounts = (Lot(self.state.burned0), Lot(self.state.burned1));
+
+    self.state.burned0 = 0;
+    self.state.burned1 = 0;
+    self.unclaimed_reward = Some((0, 0));
+
+    Some(amounts)
+  }
+
+  fn validate_rbf_and_conversion_outpoint(
+    &mut self,
+    tx: &Transaction,
+    txid: Txid,
+  ) -> Result<(), ConversionFailure> {
+    // Transaction must signal RBF
+    if !tx.is_explicitly_rbf() {
+      return Err(ConversionFailure::MissingRbf);
+    }
+
+    if !self.require_conversion_outpoint {
+      return Ok(());
+    }
+
+    let last_conversion_outpoint = self.conversion_outpoint;
+
+    if last_conversion_outpoint == OutPoint::null() {
+      // If no saved outpoint, this transaction must create one with a conversion script
+      let Some((outpoint, value)) = Self::find_output(tx, txid, &self.conversion_script_pubkey)
+      else {
+        return Err(ConversionFailure::BrokenChain);
+      };
+
+      self.conversion_outpoint = outpoint;
+      self.conversion_value = value;
+
+      // Allow unconnected conversions once conversion chain has been broken (only for 1 block)
+      self.require_conversion_outpoint = false;
+    }
+
+    // Saved outpoint must point to this transaction
+    if last_conversion_outpoint.txid == txid || last_conversion_outpoint == OutPoint::null() {
+      Ok(())
+    } else {
+      Err(ConversionFailure::BrokenChain)
+    }
+  }
+
+  fn supply(&self, id: RuneId) -> u128 {
+    if id == ID0 {
+      self.state.supply0
+    } else {
+      self.state.supply1
+    }
+  }
+
+  fn set_supply(&mut self, id: RuneId, supply: u128) {
+    if id == ID0 {
+      self.state.supply0 = supply;
+    } else {
+      self.state.supply1 = supply;
+    }
+  }
+
+  fn convert_exact_input(
+    &mut self,
+    tx: &Transaction,
+    txid: Txid,
+    input_id: RuneId,
+    output_id: RuneId,
+    input_amt: Lot,
+    min_output_amt: Lot,
+  ) -> Result<Lot, ConversionFailure> {
+    let supply_in = self.supply(input_id);
+    let supply_out = self.supply(output_id);
+
+    if input_amt.0 > supply_in {
+      return Err(ConversionFailure::InsufficientSupply);
+    }
+
+    let invariant = supply_in * supply_in + supply_out * supply_out;
+    let new_input_sq = (supply_in - input_amt.0) * (supply_in - input_amt.0);
+    let output_amt = (invariant - new_input_sq).sqrt() - supply_out;
+
+    if output_amt < min_output_amt.0 {
+      return Err(ConversionFailure::Slippage);
+    }
+
+    self.validate_rbf_and_conversion_outpoint(tx, txid)?;
+
+    self.set_supply(input_id, supply_in - input_amt.0);
+    self.set_supply(output_id, supply_out + output_amt);
+
+    Ok(Lot(output_amt))
+  }
+
+  fn convert_exact_output(
+    &mut self,
+    tx: &Transaction,
+    txid: Txid,
+    input_id: RuneId,
+    output_id: RuneId,
+    output_amt: Lot,
+    max_input_amt: Lot,
+  ) -> Result<Lot, ConversionFailure> {
+    let supply_in = self.supply(input_id);
+    let supply_out = self.supply(output_id);
+
+    let invariant = supply_in * supply_in + supply_out * supply_out;
+    let new_output_sq = (supply_out + output_amt.0) * (supply_out + output_amt.0);
+
+    if new_output_sq > invariant {
+      return Err(ConversionFailure::InsufficientSupply);
+    }
+
+    let input_amt = supply_in - (invariant - new_output_sq).sqrt();
+
+    if input_amt > max_input_amt.0 {
+      return Err(ConversionFailure::Slippage);
+    }
+
+    self.validate_rbf_and_conversion_outpoint(tx, txid)?;
+
+    self.set_supply(input_id, supply_in - input_amt);
+    self.set_supply(output_id, supply_out + output_amt.0);
+
+    Ok(Lot(input_amt))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    bitcoin::{hashes::Hash, locktime::absolute::LockTime, script, Sequence, TxIn, TxOut, Witness},
+    pretty_assertions::assert_eq,
+  };
+
+  fn ledger(supply0: u128, supply1: u128) -> Ledger {
+    Ledger::new(
+      Rules::default(),
+      SupplyState {
+        supply0,
+        supply1,
+        ..Default::default()
+      },
+      (OutPoint::null(), 0),
+      (OutPoint::null(), 0),
+    )
+  }
+
+  fn transaction(rbf: bool, runestone: Option<Runestone>, outputs: &[ScriptBuf]) -> Transaction {
+    Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: vec![TxIn {
+        previous_output: OutPoint {
+          txid: Txid::from_byte_array([1; 32]),
+          vout: 0,
+        },
+        script_sig: ScriptBuf::new(),
+        sequence: if rbf {
+          Sequence::ENABLE_RBF_NO_LOCKTIME
+        } else {
+          Sequence::MAX
+        },
+        witness: Witness::new(),
+      }],
+      output: outputs
+        .iter()
+        .cloned()
+        .chain(runestone.map(|runestone| runestone.encipher()))
+        .map(|script_pubkey| TxOut {
+          script_pubkey,
+          value: 0,
+        })
+        .collect(),
+    }
+  }
+
+  fn destination() -> ScriptBuf {
+    ScriptBuf::from_bytes(vec![0x51])
+  }
+
+  fn balances(balances: &[(RuneId, u128)]) -> BTreeMap<RuneId, u128> {
+    balances.iter().copied().collect()
+  }
+
+  #[test]
+  fn issue_assigns_first_reward_to_tighten() {
+    let mut ledger = ledger(0, 0);
+    assert_eq!(ledger.issue(50), (50, 0));
+    assert_eq!(
+      ledger.state,
+      SupplyState {
+        supply0: 50,
+        supply1: 0,
+        burned0: 50,
+        burned1: 0,
+      }
+    );
+  }
+
+  #[test]
+  fn issue_splits_reward_by_supply() {
+    let mut ledger = ledger(30, 40);
+    assert_eq!(ledger.issue(50), (30, 40));
+    assert_eq!(
+      ledger.state,
+      SupplyState {
+        supply0: 60,
+        supply1: 80,
+        burned0: 30,
+        burned1: 40,
+      }
+    );
+    assert_eq!(ledger.unclaimed_reward, Some((30, 40)));
+  }
+
+  #[test]
+  fn unallocated_balances_skip_mint_and_conversion_outputs() {
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(
+      false,
+      None,
+      &[
+        Ledger::mint_script_pubkey(),
+        Ledger::conversion_script_pubkey(),
+        destination(),
+      ],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10), (ID1, 20)]));
+
+    assert_eq!(
+      outcome.allocations,
+      [
+        BTreeMap::new(),
+        BTreeMap::new(),
+        balances(&[(ID0, 10), (ID1, 20)]),
+      ]
+    );
+    assert!(outcome.burned.is_empty());
+    assert_eq!(outcome.artifact, None);
+  }
+
+  #[test]
+  fn edict_to_op_return_burns() {
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(
+      false,
+      Some(Runestone {
+        edicts: vec![Edict {
+          id: ID0,
+          amount: 4,
+          output: 1,
+        }],
+        pointer: None,
+      }),
+      &[destination()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10)]));
+
+    assert_eq!(
+      outcome.allocations,
+      [balances(&[(ID0, 6)]), BTreeMap::new()]
+    );
+    assert_eq!(outcome.burned, balances(&[(ID0, 4)]));
+    assert_eq!(ledger.state.burned0, 4);
+  }
+
+  #[test]
+  fn cenotaph_burns_all_balances() {
+    let mut ledger = ledger(30, 40);
+    let mut tx = transaction(false, None, &[destination()]);
+    tx.output.push(TxOut {
+      script_pubkey: script::Builder::new()
+        .push_opcode(opcodes::all::OP_RETURN)
+        .push_opcode(Runestone::MAGIC_NUMBER)
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .into_script(),
+      value: 0,
+    });
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10), (ID1, 20)]));
+
+    assert!(matches!(outcome.artifact, Some(Artifact::Cenotaph(_))));
+    assert_eq!(outcome.allocations, [BTreeMap::new(), BTreeMap::new()]);
+    assert_eq!(outcome.burned, balances(&[(ID0, 10), (ID1, 20)]));
+  }
+
+  #[test]
+  fn runestone_is_deciphered_under_ledger_rules() {
+    let runestone = Runestone {
+      edicts: Vec::new(),
+      pointer: Some(0),
+    };
+
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(false, Some(runestone.clone()), &[destination()]);
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10)]));
+    assert_eq!(
+      outcome.artifact,
+      Some(Artifact::Runestone(runestone.clone()))
+    );
+    assert_eq!(outcome.allocations[0], balances(&[(ID0, 10)]));
+
+    ledger.rules.version = Version::V1;
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10)]));
+    assert_eq!(
+      outcome.artifact,
+      Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::UnrecognizedVersion),
+      }))
+    );
+    assert_eq!(outcome.burned, balances(&[(ID0, 10)]));
+
+    let mut tx = transaction(false, None, &[destination()]);
+    tx.output.push(TxOut {
+      script_pubkey: runestone.encipher_with(Version::V1),
+      value: 0,
+    });
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 10)]));
+    assert_eq!(outcome.artifact, Some(Artifact::Runestone(runestone)));
+    assert_eq!(outcome.allocations[0], balances(&[(ID0, 10)]));
+  }
+
+  #[test]
+  fn mint_claims_burned_balances() {
+    let mut ledger = ledger(0, 0);
+    ledger.issue(50);
+
+    let tx = transaction(
+      true,
+      Some(Runestone::default()),
+      &[Ledger::mint_script_pubkey(), destination()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), BTreeMap::new());
+
+    assert_eq!(outcome.minted, Some((50, 0)));
+    assert_eq!(outcome.recycled, Some((0, 0)));
+    assert_eq!(
+      outcome.allocations,
+      [BTreeMap::new(), balances(&[(ID0, 50)]), BTreeMap::new()]
+    );
+    assert_eq!(ledger.state.burned0, 0);
+    assert_eq!(ledger.unclaimed_reward, Some((0, 0)));
+    assert_eq!(
+      ledger.mint_outpoint,
+      OutPoint {
+        txid: tx.txid(),
+        vout: 0
+      }
+    );
+  }
+
+  #[test]
+  fn mint_splits_recycled_burns_from_reward() {
+    let mut ledger = ledger(0, 0);
+    ledger.issue(50);
+
+    let burn = transaction(
+      false,
+      Some(Runestone {
+        edicts: vec![Edict {
+          id: ID0,
+          amount: 4,
+          output: 1,
+        }],
+        pointer: None,
+      }),
+      &[destination()],
+    );
+
+    ledger.apply(&burn, burn.txid(), balances(&[(ID0, 4)]));
+
+    assert_eq!(ledger.state.burned0, 54);
+    assert_eq!(ledger.unclaimed_reward, Some((50, 0)));
+
+    let mint = transaction(
+      true,
+      Some(Runestone::default()),
+      &[Ledger::mint_script_pubkey(), destination()],
+    );
+
+    let outcome = ledger.apply(&mint, mint.txid(), BTreeMap::new());
+
+    assert_eq!(outcome.minted, Some((54, 0)));
+    assert_eq!(outcome.recycled, Some((4, 0)));
+  }
+
+  #[test]
+  fn mint_recipient_is_output_assigned_unallocated_runes() {
+    let mut ledger = ledger(0, 0);
+    ledger.issue(50);
+
+    let mint = transaction(
+      true,
+      Some(Runestone {
+        edicts: vec![Edict {
+          id: ID1,
+          amount: 0,
+          output: 1,
+        }],
+        pointer: Some(2),
+      }),
+      &[Ledger::mint_script_pubkey(), destination(), destination()],
+    );
+
+    let outcome = ledger.apply(&mint, mint.txid(), balances(&[(ID1, 7)]));
+
+    assert_eq!(outcome.minted, Some((50, 0)));
+    assert_eq!(outcome.mint_recipient, Some(2));
+    assert_eq!(
+      outcome.allocations[..3],
+      [
+        BTreeMap::new(),
+        balances(&[(ID1, 7)]),
+        balances(&[(ID0, 50)])
+      ]
+    );
+  }
+
+  #[test]
+  fn mint_with_untracked_reward_has_unknown_recycled_amount() {
+    let mut ledger = ledger(0, 0);
+    ledger.issue(50);
+    ledger.unclaimed_reward = None;
+    ledger.issue(10);
+
+    assert_eq!(ledger.unclaimed_reward, None);
+
+    let mint = transaction(
+      true,
+      Some(Runestone::default()),
+      &[Ledger::mint_script_pubkey(), destination()],
+    );
+
+    let outcome = ledger.apply(&mint, mint.txid(), BTreeMap::new());
+
+    assert_eq!(outcome.minted, Some((60, 0)));
+    assert_eq!(outcome.recycled, None);
+    assert_eq!(ledger.unclaimed_reward, Some((0, 0)));
+  }
+
+  #[test]
+  fn mint_requires_rbf() {
+    let mut ledger = ledger(0, 0);
+    ledger.issue(50);
+
+    let tx = transaction(
+      false,
+      Some(Runestone::default()),
+      &[Ledger::mint_script_pubkey(), destination()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), BTreeMap::new());
+
+    assert_eq!(outcome.minted, None);
+    assert_eq!(ledger.state.burned0, 50);
+    assert_eq!(ledger.mint_outpoint, OutPoint::null());
+  }
+
+  #[test]
+  fn convert_exact_input() {
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(
+      true,
+      Some(Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 14,
+            output: 2,
+          },
+          Edict {
+            id: ID1,
+            amount: 5,
+            output: 0,
+          },
+        ],
+        pointer: None,
+      }),
+      &[destination(), Ledger::conversion_script_pubkey()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 14)]));
+
+    assert_eq!(
+      outcome.conversion,
+      Some(Conversion {
+        input_id: ID0,
+        output_id: ID1,
+        exact_input: true,
+        input: 14,
+        output: 7,
+        residual: 2,
+      })
+    );
+    assert_eq!(
+      outcome.allocations,
+      [balances(&[(ID1, 7)]), BTreeMap::new(), BTreeMap::new()]
+    );
+    assert!(outcome.burned.is_empty());
+    assert_eq!((ledger.state.supply0, ledger.state.supply1), (16, 47));
+    assert_eq!(
+      ledger.conversion_outpoint,
+      OutPoint {
+        txid: tx.txid(),
+        vout: 1
+      }
+    );
+  }
+
+  #[test]
+  fn convert_exact_output() {
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(
+      true,
+      Some(Runestone {
+        edicts: vec![
+          Edict {
+            id: ID1,
+            amount: 7,
+            output: 0,
+          },
+          Edict {
+            id: ID0,
+            amount: 13,
+            output: 2,
+          },
+        ],
+        pointer: None,
+      }),
+      &[destination(), Ledger::conversion_script_pubkey()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 14)]));
+
+    assert_eq!(
+      outcome.conversion,
+      Some(Conversion {
+        input_id: ID0,
+        output_id: ID1,
+        exact_input: false,
+        input: 13,
+        output: 7,
+        residual: 0,
+      })
+    );
+    assert_eq!(
+      outcome.allocations,
+      [
+        balances(&[(ID0, 1), (ID1, 7)]),
+        BTreeMap::new(),
+        BTreeMap::new()
+      ]
+    );
+    assert!(outcome.burned.is_empty());
+    assert_eq!((ledger.state.supply0, ledger.state.supply1), (17, 47));
+  }
+
+  #[test]
+  fn conversion_requires_rbf() {
+    let mut ledger = ledger(30, 40);
+    let tx = transaction(
+      false,
+      Some(Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 14,
+            output: 2,
+          },
+          Edict {
+            id: ID1,
+            amount: 5,
+            output: 0,
+          },
+        ],
+        pointer: None,
+      }),
+      &[destination(), Ledger::conversion_script_pubkey()],
+    );
+
+    let outcome = ledger.apply(&tx, tx.txid(), balances(&[(ID0, 14)]));
+
+    assert_eq!(outcome.conversion, None);
+    assert_eq!(
+      outcome.failed_conversion,
+      Some(FailedConversion {
+        input_id: ID0,
+        output_id: ID1,
+        exact_input: true,
+        input: 14,
+        output: 5,
+        failure: ConversionFailure::MissingRbf,
+        refund: None,
+      })
+    );
+    assert_eq!(outcome.burned, balances(&[(ID0, 14)]));
+    assert_eq!(
+      outcome.allocations,
+      [BTreeMap::new(), BTreeMap::new(), BTreeMap::new()]
+    );
+    assert_eq!((ledger.state.supply0, ledger.state.supply1), (30, 40));
+  }
+
+  #[test]
+  fn failed_conversions_are_classified() {
+    // burn 14 TIGHTEN for at least `min_output` EASE, refunding to output 0
+    fn attempt(ledger: &mut Ledger, min_output: u128) -> Outcome {
+      let tx = transaction(
+        true,
+        Some(Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 14,
+              output: 2,
+            },
+            Edict {
+              id: ID0,
+              amount: 0,
+              output: 0,
+            },
+            Edict {
+              id: ID1,
+              amount: min_output,
+              output: 0,
+            },
+          ],
+          pointer: None,
+        }),
+        &[destination(), Ledger::conversion_script_pubkey()],
+      );
+
+      ledger.apply(&tx, tx.txid(), balances(&[(ID0, 14)]))
+    }
+
+    let failure = |outcome: &Outcome| {
+      assert_eq!(outcome.conversion, None);
+      assert!(outcome.burned.is_empty());
+      assert_eq!(outcome.allocations[0], balances(&[(ID0, 14)]));
+      let failed = outcome.failed_conversion.unwrap();
+      assert_eq!(failed.refund, Some(0));
+      failed.failure
+    };
+
+    assert_eq!(
+      failure(&attempt(&mut ledger(30, 40), 8)),
+      ConversionFailure::Slippage
+    );
+
+    assert_eq!(
+      failure(&attempt(&mut ledger(10, 40), 1)),
+      ConversionFailure::InsufficientSupply
+    );
+
+    let mut unchained = Ledger::new(
+      Rules::default(),
+      SupplyState {
+        supply0: 30,
+        supply1: 40,
+        ..Default::default()
+      },
+      (OutPoint::null(), 0),
+      (
+        OutPoint {
+          txid: Txid::from_byte_array([2; 32]),
+          vout: 0,
+        },
+        0,
+      ),
+    );
+
+    assert_eq!(
+      failure(&attempt(&mut unchained, 1)),
+      ConversionFailure::BrokenChain
+    );
+
+    assert_eq!(attempt(&mut ledger(30, 40), 7).failed_conversion, None);
+  }
+}
@@ -1,55 +1,128 @@
 //! Types for interoperating with ordinals, inscriptions, and runes.
+//!
+//! With default features disabled, the crate is `no_std` and requires only
+//! `alloc`. `Runestone`, `Edict`, `RuneId`, `varint` and `Flaw` remain
+//! available, deciphering from raw output scripts, and serde support can be
+//! enabled with the `serde` feature. The `std` feature, enabled by default,
+//! adds `bitcoin` types and the rest of the crate.
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 #![allow(clippy::large_enum_variant)]
 
+#[macro_use]
+extern crate alloc;
+
+use {
+  alloc::{string::String, vec::Vec},
+  core::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    str::FromStr,
+  },
+};
+
+#[cfg(feature = "serde")]
+use {
+  serde::{Deserialize, Serialize},
+  serde_with::{DeserializeFromStr, SerializeDisplay},
+};
+
+#[cfg(feature = "std")]
 use {
   bitcoin::{
     consensus::{Decodable, Encodable},
-    constants::{
-      COIN_VALUE, DIFFCHANGE_INTERVAL, MAX_SCRIPT_ELEMENT_SIZE, SUBSIDY_HALVING_INTERVAL,
-    },
-    opcodes,
-    script::{self, Instruction},
-    Network, OutPoint, ScriptBuf, Transaction,
+    constants::{COIN_VALUE, DIFFCHANGE_INTERVAL, SUBSIDY_HALVING_INTERVAL},
+    key::Secp256k1,
+    opcodes, Network, OutPoint, PrivateKey, ScriptBuf, Transaction, TxOut, Txid,
   },
   derive_more::{Display, FromStr},
-  serde::{Deserialize, Serialize},
-  serde_with::{DeserializeFromStr, SerializeDisplay},
+  lot::Lot,
+  num_integer::Roots,
   std::{
     cmp,
-    fmt::{self, Display, Formatter},
+    collections::BTreeMap,
     io,
-    num::ParseIntError,
     ops::{Add, AddAssign, Sub},
-    str::FromStr,
   },
   thiserror::Error,
 };
 
 pub use {
-  artifact::Artifact, cenotaph::Cenotaph, charm::Charm, decimal_sat::DecimalSat, degree::Degree,
-  edict::Edict, epoch::Epoch, flaw::Flaw, height::Height, pile::Pile, rarity::Rarity, rune::Rune,
-  rune_id::RuneId, runestone::Runestone, sat::Sat, sat_point::SatPoint, spaced_rune::SpacedRune,
+  artifact::Artifact,
+  cenotaph::Cenotaph,
+  diagnostics::Diagnostics,
+  edict::Edict,
+  flaw::Flaw,
+  rules::{Rules, Version},
+  rune_id::RuneId,
+  runestone::Runestone,
+};
+
+#[cfg(feature = "std")]
+pub use {
+  charm::Charm,
+  decimal_sat::DecimalSat,
+  degree::Degree,
+  epoch::Epoch,
+  height::Height,
+  ledger::{Conversion, ConversionFailure, FailedConversion, Ledger, Outcome},
+  pile::Pile,
+  rarity::Rarity,
+  rune::Rune,
+  runestone_builder::{EncipherError, RunestoneBuilder},
+  sat::Sat,
+  sat_point::SatPoint,
+  spaced_rune::SpacedRune,
+  supply_state::SupplyState,
   terms::Terms,
 };
 
+#[cfg(feature = "std")]
 pub const CYCLE_EPOCHS: u32 = 6;
 
+/// Tighten, the first of the two runes etched at genesis
+pub const ID0: RuneId = RuneId { block: 1, tx: 0 };
+
+/// Ease, the second of the two runes etched at genesis
+pub const ID1: RuneId = RuneId { block: 1, tx: 1 };
+
 mod artifact;
 mod cenotaph;
+#[cfg(feature = "std")]
 mod charm;
+#[cfg(feature = "std")]
 mod decimal_sat;
+#[cfg(feature = "std")]
 mod degree;
+mod diagnostics;
 mod edict;
+#[cfg(feature = "std")]
 mod epoch;
 mod flaw;
+#[cfg(feature = "std")]
 mod height;
+#[cfg(feature = "std")]
+mod ledger;
+#[cfg(feature = "std")]
+mod lot;
+#[cfg(feature = "std")]
 mod pile;
+#[cfg(feature = "std")]
 mod rarity;
+mod rules;
+#[cfg(feature = "std")]
 mod rune;
 mod rune_id;
 mod runestone;
+#[cfg(feature = "std")]
+mod runestone_builder;
+#[cfg(feature = "std")]
 mod sat;
+#[cfg(feature = "std")]
 mod sat_point;
+#[cfg(feature = "std")]
 mod spaced_rune;
+#[cfg(feature = "std")]
+mod supply_state;
+#[cfg(feature = "std")]
 mod terms;
 pub mod varint;
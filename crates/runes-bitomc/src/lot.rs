@@ -7,13 +7,13 @@ use {
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Default, Serialize, Deserialize)]
-pub struct Lot(pub u128);
+pub(crate) struct Lot(pub(crate) u128);
 
 impl Lot {
   #[cfg(test)]
   const MAX: Self = Self(u128::MAX);
 
-  pub(super) fn n(self) -> u128 {
+  pub(crate) fn n(self) -> u128 {
     self.0
   }
 
@@ -0,0 +1,222 @@
+use super::*;
+
+/// Consensus rules in effect at a given block height.
+///
+/// Rule changes are introduced by adding a field here, defaulting to the
+/// current behavior, and scheduling the new value at an activation height.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rules {
+  /// Runestone payload encoding
+  pub version: Version,
+}
+
+/// Runestone payload encoding.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Version {
+  /// Original encoding, without a version field
+  #[default]
+  Unversioned,
+  /// Payload begins with the integer 1, followed by the original encoding
+  V1,
+}
+
+impl Version {
+  /// Leading integer identifying the version, if any
+  pub fn tag(self) -> Option<u128> {
+    match self {
+      Self::Unversioned => None,
+      Self::V1 => Some(1),
+    }
+  }
+}
+
+impl Display for Version {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::Unversioned => write!(f, "unversioned"),
+      Self::V1 => write!(f, "v1"),
+    }
+  }
+}
+
+impl FromStr for Version {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "unversioned" => Ok(Self::Unversioned),
+      "v1" => Ok(Self::V1),
+      _ => Err(Error::Version(s.into())),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+  Version(String),
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::Version(version) => write!(f, "invalid payload version `{version}`"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+  use {
+    super::*,
+    bitcoin::{
+      locktime::absolute::LockTime,
+      script::{self, PushBytesBuf},
+      TxOut,
+    },
+  };
+
+  fn transaction(script_pubkey: ScriptBuf) -> Transaction {
+    Transaction {
+      version: 2,
+      lock_time: LockTime::ZERO,
+      input: Vec::new(),
+      output: vec![
+        TxOut {
+          script_pubkey: ScriptBuf::new(),
+          value: 0,
+        },
+        TxOut {
+          script_pubkey,
+          value: 0,
+        },
+      ],
+    }
+  }
+
+  fn payload(integers: &[u128]) -> ScriptBuf {
+    let mut bytes = Vec::new();
+    for integer in integers {
+      varint::encode_to_vec(*integer, &mut bytes);
+    }
+
+    script::Builder::new()
+      .push_opcode(opcodes::all::OP_RETURN)
+      .push_opcode(Runestone::MAGIC_NUMBER)
+      .push_slice(PushBytesBuf::try_from(bytes).unwrap())
+      .into_script()
+  }
+
+  fn runestone() -> Runestone {
+    Runestone {
+      edicts: vec![Edict {
+        id: ID1,
+        amount: 5,
+        output: 0,
+      }],
+      pointer: Some(0),
+    }
+  }
+
+  #[test]
+  fn default_rules_are_unversioned() {
+    assert_eq!(Rules::default().version, Version::Unversioned);
+    assert_eq!(Version::Unversioned.tag(), None);
+    assert_eq!(Version::V1.tag(), Some(1));
+  }
+
+  #[test]
+  fn version_round_trips_through_string() {
+    for version in [Version::Unversioned, Version::V1] {
+      assert_eq!(version.to_string().parse::<Version>().unwrap(), version);
+    }
+
+    assert_eq!("v2".parse::<Version>(), Err(Error::Version("v2".into())),);
+  }
+
+  #[test]
+  fn unversioned_encipher_is_unchanged() {
+    assert_eq!(
+      runestone().encipher_with(Version::Unversioned),
+      runestone().encipher(),
+    );
+  }
+
+  #[test]
+  fn v1_round_trips() {
+    let tx = transaction(runestone().encipher_with(Version::V1));
+
+    assert_eq!(tx.output[1].script_pubkey, payload(&[1, 0, 2 * 5 + 1, 0]),);
+
+    assert_eq!(
+      Runestone::decipher_with(&tx, Version::V1),
+      Some(Artifact::Runestone(runestone())),
+    );
+
+    assert_eq!(
+      Runestone::diagnose_with(&tx, Version::V1).unwrap().integers,
+      [1, 0, 11, 0],
+    );
+  }
+
+  #[test]
+  fn v1_payload_without_version_is_cenotaph() {
+    let tx = transaction(runestone().encipher());
+
+    assert_eq!(
+      Runestone::decipher_with(&tx, Version::V1),
+      Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::UnrecognizedVersion),
+      })),
+    );
+
+    let tx = transaction(payload(&[]));
+
+    assert_eq!(
+      Runestone::decipher_with(&tx, Version::V1),
+      Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::UnrecognizedVersion),
+      })),
+    );
+  }
+
+  #[test]
+  fn v1_payload_with_unknown_version_is_cenotaph() {
+    let tx = transaction(payload(&[2, 0, 11, 0]));
+
+    let diagnostics = Runestone::diagnose_with(&tx, Version::V1).unwrap();
+
+    assert_eq!(diagnostics.flaw, Some(Flaw::UnrecognizedVersion));
+    assert_eq!(diagnostics.integers, [2, 0, 11, 0]);
+    assert!(diagnostics.edicts.is_empty());
+  }
+
+  #[test]
+  fn unversioned_rules_misread_v1_payload() {
+    let tx = transaction(runestone().encipher_with(Version::V1));
+
+    assert_eq!(
+      Runestone::decipher(&tx),
+      Some(Artifact::Runestone(Runestone {
+        edicts: vec![
+          Edict {
+            id: ID1,
+            amount: 0,
+            output: 0,
+          },
+          Edict {
+            id: ID1,
+            amount: 5,
+            output: 0,
+          },
+        ],
+        pointer: None,
+      })),
+    );
+  }
+}
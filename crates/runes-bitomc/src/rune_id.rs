@@ -1,18 +1,7 @@
 use super::*;
 
-#[derive(
-  Debug,
-  PartialEq,
-  Copy,
-  Clone,
-  Hash,
-  Eq,
-  Ord,
-  PartialOrd,
-  Default,
-  DeserializeFromStr,
-  SerializeDisplay,
-)]
+#[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Ord, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(DeserializeFromStr, SerializeDisplay))]
 pub struct RuneId {
   pub block: u64,
   pub tx: u32,
@@ -89,6 +78,7 @@ impl Display for Error {
   }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 #[cfg(test)]
@@ -160,6 +150,7 @@ mod tests {
   }
 
   #[test]
+  #[cfg(feature = "serde")]
   fn serde() {
     let rune_id = RuneId { block: 1, tx: 2 };
     let json = "\"1:2\"";
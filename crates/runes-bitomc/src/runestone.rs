@@ -1,8 +1,14 @@
-use {super::*, message::Message};
+use {
+  super::*,
+  message::Message,
+  script::{Instruction, Instructions},
+};
 
 mod message;
+mod script;
 
-#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Runestone {
   pub edicts: Vec<Edict>,
   pub pointer: Option<u32>,
@@ -10,59 +16,127 @@ pub struct Runestone {
 
 #[derive(Debug, PartialEq)]
 enum Payload {
-  Valid(Vec<u8>),
-  Invalid(Flaw),
+  /// Concatenated data pushes, and the offset into the script of each byte
+  Valid(Vec<u8>, Vec<usize>),
+  /// Flaw, the offset into the script of the offending instruction, and the
+  /// data pushes before it
+  Invalid(Flaw, usize, Vec<u8>),
 }
 
 impl Runestone {
+  #[cfg(feature = "std")]
   pub const MAGIC_NUMBER: opcodes::All = opcodes::all::OP_PUSHNUM_14;
   pub const COMMIT_CONFIRMATIONS: u16 = 6;
+  /// Largest edict amount that `encipher` encodes without clamping
+  pub const MAX_AMOUNT: u128 = u128::MAX / 2 - 1;
+  /// Standardness limit for OP_RETURN outputs
+  pub const MAX_SCRIPT_SIZE: usize = 82;
 
+  #[cfg(feature = "std")]
   pub fn decipher(transaction: &Transaction) -> Option<Artifact> {
-    let payload = match Runestone::payload(transaction) {
-      Some(Payload::Valid(payload)) => payload,
-      Some(Payload::Invalid(flaw)) => {
-        return Some(Artifact::Cenotaph(Cenotaph { flaw: Some(flaw) }));
-      }
-      None => return None,
+    Runestone::decipher_with(transaction, Version::Unversioned)
+  }
+
+  #[cfg(feature = "std")]
+  pub fn decipher_with(transaction: &Transaction, version: Version) -> Option<Artifact> {
+    Runestone::diagnose_with(transaction, version).map(Artifact::from)
+  }
+
+  /// Deciphers the runestone in `transaction`, keeping everything decoded
+  /// before a flaw was encountered.
+  #[cfg(feature = "std")]
+  pub fn diagnose(transaction: &Transaction) -> Option<Diagnostics> {
+    Runestone::diagnose_with(transaction, Version::Unversioned)
+  }
+
+  #[cfg(feature = "std")]
+  pub fn diagnose_with(transaction: &Transaction, version: Version) -> Option<Diagnostics> {
+    let scripts = transaction
+      .output
+      .iter()
+      .map(|output| output.script_pubkey.as_bytes())
+      .collect::<Vec<&[u8]>>();
+
+    Runestone::diagnose_scripts(&scripts, version)
+  }
+
+  /// Deciphers the runestone in a transaction with output scripts `scripts`
+  pub fn decipher_scripts(scripts: &[&[u8]], version: Version) -> Option<Artifact> {
+    Runestone::diagnose_scripts(scripts, version).map(Artifact::from)
+  }
+
+  /// Diagnoses the runestone in a transaction with output scripts `scripts`
+  pub fn diagnose_scripts(scripts: &[&[u8]], version: Version) -> Option<Diagnostics> {
+    let (output, payload) = Runestone::payload(scripts)?;
+
+    let mut diagnostics = Diagnostics {
+      output,
+      ..Default::default()
     };
 
-    let Ok(integers) = Runestone::integers(&payload) else {
-      return Some(Artifact::Cenotaph(Cenotaph {
-        flaw: Some(Flaw::Varint),
-      }));
+    let (payload, offsets) = match payload {
+      Payload::Valid(payload, offsets) => (payload, offsets),
+      Payload::Invalid(flaw, offset, payload) => {
+        diagnostics.flaw = Some(flaw);
+        diagnostics.offset = Some(offset);
+        diagnostics.integers = Runestone::integers(&payload).0;
+        return Some(diagnostics);
+      }
     };
 
+    let (integers, error) = Runestone::integers(&payload);
+
+    if let Some(i) = error {
+      diagnostics.flaw = Some(Flaw::Varint);
+      diagnostics.offset = Some(offsets[i]);
+    }
+
     let Message {
       flaw,
       edicts,
       mut pointer,
-    } = Message::from_integers(transaction, &integers);
+    } = Message::from_integers(scripts.len(), &integers, version);
 
     if let Some(p) = pointer {
-      if u64::from(p) >= u64::try_from(transaction.output.len()).unwrap() {
+      if u64::from(p) >= u64::try_from(scripts.len()).unwrap() {
         pointer = None;
       }
     }
 
-    if let Some(flaw) = flaw {
-      return Some(Artifact::Cenotaph(Cenotaph { flaw: Some(flaw) }));
-    }
+    diagnostics.flaw = diagnostics.flaw.or(flaw);
+    diagnostics.integers = integers;
+    diagnostics.edicts = edicts;
+    diagnostics.pointer = pointer;
 
-    Some(Artifact::Runestone(Self { edicts, pointer }))
+    Some(diagnostics)
   }
 
+  #[cfg(feature = "std")]
   pub fn encipher(&self) -> ScriptBuf {
+    self.encipher_with(Version::Unversioned)
+  }
+
+  #[cfg(feature = "std")]
+  pub fn encipher_with(&self, version: Version) -> ScriptBuf {
+    ScriptBuf::from_bytes(self.encipher_to_vec(version))
+  }
+
+  /// Enciphers the runestone into the bytes of an OP_RETURN output script
+  pub fn encipher_to_vec(&self, version: Version) -> Vec<u8> {
     let mut payload = Vec::new();
 
+    if let Some(tag) = version.tag() {
+      varint::encode_to_vec(tag, &mut payload);
+    }
+
     if let Some(pointer) = self.pointer {
       varint::encode_to_vec(pointer.into(), &mut payload);
     }
 
     if !self.edicts.is_empty() {
       for mut edict in self.edicts.clone() {
-        if edict.amount >= u128::MAX / 2 {
-          edict.amount = u128::MAX / 2 - 1;
+        if edict.amount > Self::MAX_AMOUNT {
+          edict.amount = Self::MAX_AMOUNT;
         }
         let id0 = RuneId { block: 1, tx: 0 };
         let encoded_id: u128 = if edict.id == id0 { 0 } else { 1 };
@@ -72,67 +146,94 @@ impl Runestone {
       }
     }
 
-    let mut builder = script::Builder::new()
-      .push_opcode(opcodes::all::OP_RETURN)
-      .push_opcode(Runestone::MAGIC_NUMBER);
+    let mut script = vec![script::OP_RETURN, script::MAGIC_NUMBER];
 
-    for chunk in payload.chunks(MAX_SCRIPT_ELEMENT_SIZE) {
-      let push: &script::PushBytes = chunk.try_into().unwrap();
-      builder = builder.push_slice(push);
+    for chunk in payload.chunks(script::MAX_PUSH_SIZE) {
+      script::push_slice(&mut script, chunk);
     }
 
-    builder.into_script()
+    script
+  }
+
+  /// Enciphers the runestone after checking it against the outputs of the
+  /// draft transaction, instead of silently clamping amounts.
+  #[cfg(feature = "std")]
+  pub fn try_encipher(&self, outputs: &[TxOut]) -> Result<ScriptBuf, EncipherError> {
+    self.try_encipher_with(outputs, Version::Unversioned)
+  }
+
+  /// Like `try_encipher`, with the payload encoded for `version`
+  #[cfg(feature = "std")]
+  pub fn try_encipher_with(
+    &self,
+    outputs: &[TxOut],
+    version: Version,
+  ) -> Result<ScriptBuf, EncipherError> {
+    Ok(
+      RunestoneBuilder::from(self.clone())
+        .build(outputs, version)?
+        .encipher_with(version),
+    )
   }
 
-  fn payload(transaction: &Transaction) -> Option<Payload> {
+  fn payload(scripts: &[&[u8]]) -> Option<(u32, Payload)> {
     // search transaction outputs for payload
-    for output in &transaction.output {
-      let mut instructions = output.script_pubkey.instructions();
+    for (vout, script) in scripts.iter().enumerate() {
+      let vout = u32::try_from(vout).unwrap();
+      let mut instructions = Instructions::new(script);
 
       // payload starts with OP_RETURN
-      if instructions.next() != Some(Ok(Instruction::Op(opcodes::all::OP_RETURN))) {
+      if instructions.next() != Some(Ok((0, Instruction::Op(script::OP_RETURN)))) {
         continue;
       }
 
       // followed by the protocol identifier, ignoring errors, since OP_RETURN
       // scripts may be invalid
-      if instructions.next() != Some(Ok(Instruction::Op(Runestone::MAGIC_NUMBER))) {
+      if instructions.next() != Some(Ok((1, Instruction::Op(script::MAGIC_NUMBER)))) {
         continue;
       }
 
       // construct the payload by concatenating remaining data pushes
       let mut payload = Vec::new();
+      let mut offsets = Vec::new();
+      let mut end = 2;
 
       for result in instructions {
         match result {
-          Ok(Instruction::PushBytes(push)) => {
-            payload.extend_from_slice(push.as_bytes());
+          Ok((_, Instruction::PushBytes(range))) => {
+            payload.extend_from_slice(&script[range.clone()]);
+            end = range.end;
+            offsets.extend(range);
           }
-          Ok(Instruction::Op(_)) => {
-            return Some(Payload::Invalid(Flaw::Opcode));
+          Ok((index, Instruction::Op(_))) => {
+            return Some((vout, Payload::Invalid(Flaw::Opcode, index, payload)));
           }
-          Err(_) => {
-            return Some(Payload::Invalid(Flaw::InvalidScript));
+          Err(()) => {
+            return Some((vout, Payload::Invalid(Flaw::InvalidScript, end, payload)));
           }
         }
       }
 
-      return Some(Payload::Valid(payload));
+      return Some((vout, Payload::Valid(payload, offsets)));
     }
 
     None
   }
 
-  fn integers(payload: &[u8]) -> Result<Vec<u128>, varint::Error> {
+  /// Decodes varints until the end of the payload or the first invalid
+  /// varint, returning the offset of the latter
+  fn integers(payload: &[u8]) -> (Vec<u128>, Option<usize>) {
     let mut integers = Vec::new();
     let mut i = 0;
 
     while i < payload.len() {
-      let (integer, length) = varint::decode(&payload[i..])?;
+      let Ok((integer, length)) = varint::decode(&payload[i..]) else {
+        return (integers, Some(i));
+      };
       integers.push(integer);
       i += length;
     }
 
-    Ok(integers)
+    (integers, None)
   }
 }
@@ -7,11 +7,27 @@ pub(super) struct Message {
 }
 
 impl Message {
-  pub(super) fn from_integers(tx: &Transaction, payload: &[u128]) -> Self {
+  pub(super) fn from_integers(outputs: usize, payload: &[u128], version: Version) -> Self {
     let mut edicts = Vec::new();
     let mut pointer = None;
     let mut flaw = None;
 
+    // versioned payloads begin with the version tag, followed by the
+    // unversioned encoding
+    let payload = match version.tag() {
+      None => payload,
+      Some(tag) => match payload.split_first() {
+        Some((&first, rest)) if first == tag => rest,
+        _ => {
+          return Self {
+            flaw: Some(Flaw::UnrecognizedVersion),
+            edicts,
+            pointer,
+          }
+        }
+      },
+    };
+
     let mut offset = 0;
     if payload.len() % 2 == 1 {
       if let Some(&value) = payload.first() {
@@ -32,7 +48,7 @@ impl Message {
       };
       let amount = chunk[0] / 2;
 
-      let Some(edict) = Edict::from_integers(tx, id, amount, chunk[1]) else {
+      let Some(edict) = Edict::from_integers_with_outputs(outputs, id, amount, chunk[1]) else {
         flaw.get_or_insert(Flaw::EdictOutput);
         break;
       };
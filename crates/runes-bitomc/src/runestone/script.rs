@@ -0,0 +1,169 @@
+//! Minimal script parsing and building, so that runestones can be
+//! deciphered without depending on `bitcoin`. Instructions are parsed the
+//! same way as `bitcoin::Script::instruction_indices`, without requiring
+//! minimal pushes.
+
+use {super::*, core::ops::Range};
+
+pub(super) const OP_RETURN: u8 = 0x6a;
+/// OP_PUSHNUM_14
+pub(super) const MAGIC_NUMBER: u8 = 0x5e;
+/// Maximum size of a single data push
+pub(super) const MAX_PUSH_SIZE: usize = 520;
+
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Instruction {
+  Op(u8),
+  /// Range of the pushed data in the script
+  PushBytes(Range<usize>),
+}
+
+/// Iterator over the instructions of a script and their offsets. Yields an
+/// error and stops if a push runs past the end of the script.
+pub(super) struct Instructions<'a> {
+  script: &'a [u8],
+  index: usize,
+}
+
+impl<'a> Instructions<'a> {
+  pub(super) fn new(script: &'a [u8]) -> Self {
+    Self { script, index: 0 }
+  }
+
+  fn end(&mut self) -> Option<Result<(usize, Instruction), ()>> {
+    self.index = self.script.len();
+    Some(Err(()))
+  }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+  type Item = Result<(usize, Instruction), ()>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let index = self.index;
+    let opcode = *self.script.get(index)?;
+
+    let (header, len) = match opcode {
+      0..=0x4b => (1, usize::from(opcode)),
+      OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => {
+        let size = match opcode {
+          OP_PUSHDATA1 => 1,
+          OP_PUSHDATA2 => 2,
+          _ => 4,
+        };
+
+        let Some(bytes) = self.script.get(index + 1..index + 1 + size) else {
+          return self.end();
+        };
+
+        let len = bytes
+          .iter()
+          .rev()
+          .fold(0u64, |len, &byte| len << 8 | u64::from(byte));
+
+        let Ok(len) = usize::try_from(len) else {
+          return self.end();
+        };
+
+        (1 + size, len)
+      }
+      _ => {
+        self.index += 1;
+        return Some(Ok((index, Instruction::Op(opcode))));
+      }
+    };
+
+    let start = index + header;
+
+    let Some(end) = start
+      .checked_add(len)
+      .filter(|&end| end <= self.script.len())
+    else {
+      return self.end();
+    };
+
+    self.index = end;
+
+    Some(Ok((index, Instruction::PushBytes(start..end))))
+  }
+}
+
+/// Appends a push of `data` to `script`, using the smallest push opcode
+pub(super) fn push_slice(script: &mut Vec<u8>, data: &[u8]) {
+  let len = data.len();
+
+  if len < usize::from(OP_PUSHDATA1) {
+    script.push(len as u8);
+  } else if len < 0x100 {
+    script.push(OP_PUSHDATA1);
+    script.push(len as u8);
+  } else if len < 0x10000 {
+    script.push(OP_PUSHDATA2);
+    script.extend_from_slice(&(len as u16).to_le_bytes());
+  } else {
+    script.push(OP_PUSHDATA4);
+    script.extend_from_slice(&u32::try_from(len).unwrap().to_le_bytes());
+  }
+
+  script.extend_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn instructions(script: &[u8]) -> Vec<Result<(usize, Instruction), ()>> {
+    Instructions::new(script).collect()
+  }
+
+  #[test]
+  fn opcodes_and_pushes() {
+    assert_eq!(
+      instructions(&[OP_RETURN, MAGIC_NUMBER, 0, 2, 7, 8, OP_PUSHDATA1, 1, 9]),
+      [
+        Ok((0, Instruction::Op(OP_RETURN))),
+        Ok((1, Instruction::Op(MAGIC_NUMBER))),
+        Ok((2, Instruction::PushBytes(3..3))),
+        Ok((3, Instruction::PushBytes(4..6))),
+        Ok((6, Instruction::PushBytes(8..9))),
+      ]
+    );
+  }
+
+  #[test]
+  fn truncated_pushes_are_errors() {
+    assert_eq!(
+      instructions(&[OP_RETURN, 2, 7]),
+      [Ok((0, Instruction::Op(OP_RETURN))), Err(())]
+    );
+    assert_eq!(instructions(&[OP_PUSHDATA2, 1]), [Err(())]);
+    assert_eq!(instructions(&[OP_PUSHDATA4, 1, 0, 0, 0]), [Err(())]);
+  }
+
+  #[test]
+  fn push_slice_uses_smallest_opcode() {
+    for (len, header) in [
+      (0, vec![0]),
+      (75, vec![75]),
+      (76, vec![OP_PUSHDATA1, 76]),
+      (255, vec![OP_PUSHDATA1, 255]),
+      (256, vec![OP_PUSHDATA2, 0, 1]),
+      (0x10000, vec![OP_PUSHDATA4, 0, 0, 1, 0]),
+    ] {
+      let data = vec![0xff; len];
+      let mut script = Vec::new();
+      push_slice(&mut script, &data);
+      assert_eq!(script[..header.len()], header);
+      assert_eq!(script.len(), header.len() + len);
+
+      assert_eq!(
+        instructions(&script),
+        [Ok((0, Instruction::PushBytes(header.len()..script.len())))]
+      );
+    }
+  }
+}
@@ -0,0 +1,409 @@
+use super::*;
+
+/// Builds a runestone for a draft transaction, checking that it will be
+/// interpreted as intended once the transaction is broadcast.
+///
+/// `outputs` are the outputs of the draft transaction, including the
+/// OP_RETURN output that will hold the runestone.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunestoneBuilder {
+  runestone: Runestone,
+  burn_unallocated: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum EncipherError {
+  #[error("edict {edict} transfers unknown rune {id}")]
+  UnknownRune { edict: usize, id: RuneId },
+  #[error(
+    "edict {edict} amount {amount} exceeds maximum of {}",
+    Runestone::MAX_AMOUNT
+  )]
+  Amount { edict: usize, amount: u128 },
+  #[error("edict {edict} output {output} out of range for transaction with {outputs} outputs")]
+  EdictOutput {
+    edict: usize,
+    output: u32,
+    outputs: usize,
+  },
+  #[error("edict {edict} output {output} is a mint output")]
+  EdictMint { edict: usize, output: u32 },
+  #[error("edict {edict} output {output} is a convert output")]
+  EdictConvert { edict: usize, output: u32 },
+  #[error("pointer {pointer} out of range for transaction with {outputs} outputs")]
+  PointerOutput { pointer: u32, outputs: usize },
+  #[error("pointer {pointer} is an OP_RETURN output")]
+  PointerOpReturn { pointer: u32 },
+  #[error("pointer {pointer} is a mint output")]
+  PointerMint { pointer: u32 },
+  #[error("pointer {pointer} is a convert output")]
+  PointerConvert { pointer: u32 },
+  #[error(
+    "runestone greater than maximum OP_RETURN size: {size} > {}",
+    Runestone::MAX_SCRIPT_SIZE
+  )]
+  Size { size: usize },
+}
+
+impl From<Runestone> for RunestoneBuilder {
+  fn from(runestone: Runestone) -> Self {
+    Self {
+      runestone,
+      burn_unallocated: false,
+    }
+  }
+}
+
+impl RunestoneBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn edict(mut self, id: RuneId, amount: u128, output: u32) -> Self {
+    self.runestone.edicts.push(Edict { id, amount, output });
+    self
+  }
+
+  pub fn pointer(mut self, pointer: u32) -> Self {
+    self.runestone.pointer = Some(pointer);
+    self
+  }
+
+  /// Allow the pointer to reference an OP_RETURN output, burning runes not
+  /// allocated by edicts, as conversions do.
+  pub fn burn_unallocated(mut self) -> Self {
+    self.burn_unallocated = true;
+    self
+  }
+
+  /// Checks the runestone against `outputs`, and that its payload encoded for
+  /// `version` fits in an OP_RETURN output
+  pub fn build(self, outputs: &[TxOut], version: Version) -> Result<Runestone, EncipherError> {
+    let mint_script_pubkey = Ledger::mint_script_pubkey();
+    let conversion_script_pubkey = Ledger::conversion_script_pubkey();
+
+    let script_pubkey = |output: u32| {
+      usize::try_from(output)
+        .ok()
+        .and_then(|output| outputs.get(output))
+        .map(|tx_out| &tx_out.script_pubkey)
+    };
+
+    for (i, edict) in self.runestone.edicts.iter().enumerate() {
+      if edict.id != ID0 && edict.id != ID1 {
+        return Err(EncipherError::UnknownRune {
+          edict: i,
+          id: edict.id,
+        });
+      }
+
+      if edict.amount > Runestone::MAX_AMOUNT {
+        return Err(EncipherError::Amount {
+          edict: i,
+          amount: edict.amount,
+        });
+      }
+
+      // an output equal to the number of outputs splits the amount between
+      // all eligible outputs
+      if u64::from(edict.output) == u64::try_from(outputs.len()).unwrap() {
+        continue;
+      }
+
+      let Some(script_pubkey) = script_pubkey(edict.output) else {
+        return Err(EncipherError::EdictOutput {
+          edict: i,
+          output: edict.output,
+          outputs: outputs.len(),
+        });
+      };
+
+      if *script_pubkey == mint_script_pubkey {
+        return Err(EncipherError::EdictMint {
+          edict: i,
+          output: edict.output,
+        });
+      }
+
+      if *script_pubkey == conversion_script_pubkey {
+        return Err(EncipherError::EdictConvert {
+          edict: i,
+          output: edict.output,
+        });
+      }
+    }
+
+    if let Some(pointer) = self.runestone.pointer {
+      let Some(script_pubkey) = script_pubkey(pointer) else {
+        return Err(EncipherError::PointerOutput {
+          pointer,
+          outputs: outputs.len(),
+        });
+      };
+
+      if script_pubkey.is_op_return() && !self.burn_unallocated {
+        return Err(EncipherError::PointerOpReturn { pointer });
+      }
+
+      if *script_pubkey == mint_script_pubkey {
+        return Err(EncipherError::PointerMint { pointer });
+      }
+
+      if *script_pubkey == conversion_script_pubkey {
+        return Err(EncipherError::PointerConvert { pointer });
+      }
+    }
+
+    let size = self.runestone.encipher_with(version).len();
+
+    if size > Runestone::MAX_SCRIPT_SIZE {
+      return Err(EncipherError::Size { size });
+    }
+
+    Ok(self.runestone)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, bitcoin::script::PushBytesBuf};
+
+  fn outputs() -> Vec<TxOut> {
+    [
+      Runestone::default().encipher(),
+      Ledger::mint_script_pubkey(),
+      Ledger::conversion_script_pubkey(),
+      ScriptBuf::from_bytes(vec![0x51]),
+    ]
+    .into_iter()
+    .map(|script_pubkey| TxOut {
+      script_pubkey,
+      value: 0,
+    })
+    .collect()
+  }
+
+  #[test]
+  fn valid_runestone_is_built() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID0, 10, 3)
+        .edict(ID1, 0, 4)
+        .edict(ID1, 5, 0)
+        .pointer(3)
+        .build(&outputs(), Version::Unversioned)
+        .unwrap(),
+      Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 10,
+            output: 3,
+          },
+          Edict {
+            id: ID1,
+            amount: 0,
+            output: 4,
+          },
+          Edict {
+            id: ID1,
+            amount: 5,
+            output: 0,
+          },
+        ],
+        pointer: Some(3),
+      }
+    );
+  }
+
+  #[test]
+  fn unknown_rune() {
+    let id = RuneId { block: 2, tx: 0 };
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(id, 1, 3)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::UnknownRune { edict: 0, id }),
+    );
+  }
+
+  #[test]
+  fn amount_that_would_be_clamped() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID0, Runestone::MAX_AMOUNT, 3)
+        .build(&outputs(), Version::Unversioned)
+        .unwrap()
+        .edicts[0]
+        .amount,
+      Runestone::MAX_AMOUNT,
+    );
+
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID0, 1, 3)
+        .edict(ID0, u128::MAX / 2, 3)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::Amount {
+        edict: 1,
+        amount: u128::MAX / 2,
+      }),
+    );
+  }
+
+  #[test]
+  fn edict_output_out_of_range() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID0, 1, 5)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::EdictOutput {
+        edict: 0,
+        output: 5,
+        outputs: 4,
+      }),
+    );
+  }
+
+  #[test]
+  fn edict_to_mint_or_convert_output() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID0, 1, 1)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::EdictMint {
+        edict: 0,
+        output: 1
+      }),
+    );
+
+    assert_eq!(
+      RunestoneBuilder::new()
+        .edict(ID1, 1, 2)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::EdictConvert {
+        edict: 0,
+        output: 2
+      }),
+    );
+  }
+
+  #[test]
+  fn pointer_out_of_range() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .pointer(4)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::PointerOutput {
+        pointer: 4,
+        outputs: 4,
+      }),
+    );
+  }
+
+  #[test]
+  fn pointer_to_op_return() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .pointer(0)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::PointerOpReturn { pointer: 0 }),
+    );
+
+    assert_eq!(
+      RunestoneBuilder::new()
+        .pointer(0)
+        .burn_unallocated()
+        .build(&outputs(), Version::Unversioned)
+        .unwrap()
+        .pointer,
+      Some(0),
+    );
+  }
+
+  #[test]
+  fn pointer_to_mint_or_convert_output() {
+    assert_eq!(
+      RunestoneBuilder::new()
+        .pointer(1)
+        .burn_unallocated()
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::PointerMint { pointer: 1 }),
+    );
+
+    assert_eq!(
+      RunestoneBuilder::new()
+        .pointer(2)
+        .build(&outputs(), Version::Unversioned),
+      Err(EncipherError::PointerConvert { pointer: 2 }),
+    );
+  }
+
+  #[test]
+  fn oversize_payload() {
+    let mut builder = RunestoneBuilder::new();
+    for _ in 0..4 {
+      builder = builder.edict(ID0, Runestone::MAX_AMOUNT, 3);
+    }
+
+    let size = builder.clone().runestone.encipher().len();
+    assert!(size > Runestone::MAX_SCRIPT_SIZE);
+
+    assert_eq!(
+      builder.build(&outputs(), Version::Unversioned),
+      Err(EncipherError::Size { size }),
+    );
+  }
+
+  #[test]
+  fn versioned_payload_size_is_checked() {
+    // each edict encodes to two bytes, so the unversioned script is exactly
+    // the maximum size, and the version tag pushes it over
+    let mut builder = RunestoneBuilder::new();
+    for _ in 0..39 {
+      builder = builder.edict(ID0, 1, 3);
+    }
+
+    assert_eq!(
+      builder.runestone.encipher().len(),
+      Runestone::MAX_SCRIPT_SIZE
+    );
+
+    assert!(builder
+      .clone()
+      .build(&outputs(), Version::Unversioned)
+      .is_ok());
+
+    assert_eq!(
+      builder.build(&outputs(), Version::V1),
+      Err(EncipherError::Size {
+        size: Runestone::MAX_SCRIPT_SIZE + 1
+      }),
+    );
+  }
+
+  #[test]
+  fn try_encipher_matches_encipher() {
+    let runestone = Runestone {
+      edicts: vec![Edict {
+        id: ID1,
+        amount: 100,
+        output: 3,
+      }],
+      pointer: Some(3),
+    };
+
+    assert_eq!(
+      runestone.try_encipher(&outputs()).unwrap(),
+      runestone.encipher()
+    );
+
+    let mut outputs = outputs();
+    outputs[3].script_pubkey = ScriptBuf::new_op_return(&PushBytesBuf::new());
+
+    assert_eq!(
+      runestone.try_encipher(&outputs),
+      Err(EncipherError::PointerOpReturn { pointer: 3 }),
+    );
+  }
+}
@@ -0,0 +1,9 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct SupplyState {
+  pub supply0: u128,
+  pub supply1: u128,
+  pub burned0: u128,
+  pub burned1: u128,
+}
@@ -55,6 +55,7 @@ impl Display for Error {
   }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 #[cfg(test)]
@@ -0,0 +1,301 @@
+//! Checks that runestones are deciphered and enciphered identically with and
+//! without the `std` feature. Run with default features and with
+//! `--no-default-features`; `bitcoin` is only used as an oracle here.
+
+use {
+  bitcoin::{
+    blockdata::{opcodes, script},
+    script::Instruction,
+    ScriptBuf,
+  },
+  pretty_assertions::assert_eq,
+  runes_bitomc::{varint, Artifact, Cenotaph, Edict, Flaw, RuneId, Runestone, Version, ID0, ID1},
+};
+
+/// xorshift64, so that the corpus is the same on every run
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  fn below(&mut self, n: u64) -> u64 {
+    self.next() % n
+  }
+
+  fn bytes(&mut self, len: usize) -> Vec<u8> {
+    (0..len).map(|_| self.next() as u8).collect()
+  }
+}
+
+/// Expected diagnostics computed with `bitcoin`'s script parser
+fn oracle(script: &ScriptBuf) -> (Option<Flaw>, Option<usize>, Vec<u128>) {
+  let mut instructions = script.instruction_indices();
+
+  assert_eq!(
+    instructions.next(),
+    Some(Ok((0, Instruction::Op(opcodes::all::OP_RETURN))))
+  );
+  assert_eq!(
+    instructions.next(),
+    Some(Ok((1, Instruction::Op(opcodes::all::OP_PUSHNUM_14))))
+  );
+
+  let mut payload = Vec::new();
+  let mut offsets = Vec::new();
+  let mut end = 2;
+  let mut script_flaw = None;
+
+  for result in instructions {
+    match result {
+      Ok((index, Instruction::PushBytes(push))) => {
+        let start = index
+          + match script.as_bytes()[index] {
+            0x4c => 2,
+            0x4d => 3,
+            0x4e => 5,
+            _ => 1,
+          };
+        payload.extend_from_slice(push.as_bytes());
+        offsets.extend(start..start + push.len());
+        end = start + push.len();
+      }
+      Ok((index, Instruction::Op(_))) => {
+        script_flaw = Some((Flaw::Opcode, index));
+        break;
+      }
+      Err(_) => {
+        script_flaw = Some((Flaw::InvalidScript, end));
+        break;
+      }
+    }
+  }
+
+  let mut integers = Vec::new();
+  let mut i = 0;
+  while i < payload.len() {
+    match varint::decode(&payload[i..]) {
+      Ok((integer, length)) => {
+        integers.push(integer);
+        i += length;
+      }
+      Err(_) if script_flaw.is_some() => break,
+      Err(_) => return (Some(Flaw::Varint), Some(offsets[i]), integers),
+    }
+  }
+
+  match script_flaw {
+    Some((flaw, offset)) => (Some(flaw), Some(offset), integers),
+    None => (None, None, integers),
+  }
+}
+
+/// Encipher using `bitcoin`'s script builder
+fn oracle_encipher(runestone: &Runestone, version: Version) -> ScriptBuf {
+  let mut payload = Vec::new();
+
+  if let Some(tag) = version.tag() {
+    varint::encode_to_vec(tag, &mut payload);
+  }
+
+  if let Some(pointer) = runestone.pointer {
+    varint::encode_to_vec(pointer.into(), &mut payload);
+  }
+
+  for edict in &runestone.edicts {
+    let amount = edict.amount.min(Runestone::MAX_AMOUNT);
+    varint::encode_to_vec(2 * amount + u128::from(edict.id != ID0), &mut payload);
+    varint::encode_to_vec(edict.output.into(), &mut payload);
+  }
+
+  let mut builder = script::Builder::new()
+    .push_opcode(opcodes::all::OP_RETURN)
+    .push_opcode(opcodes::all::OP_PUSHNUM_14);
+
+  for chunk in payload.chunks(520) {
+    let push: &script::PushBytes = chunk.try_into().unwrap();
+    builder = builder.push_slice(push);
+  }
+
+  builder.into_script()
+}
+
+fn push(data: &[u8]) -> Vec<u8> {
+  let mut script = vec![0x6a, 0x5e, u8::try_from(data.len()).unwrap()];
+  script.extend(data);
+  script
+}
+
+#[test]
+fn golden_vectors() {
+  for (script, version, expected) in [
+    (
+      push(&[]),
+      Version::Unversioned,
+      Artifact::Runestone(Runestone::default()),
+    ),
+    (
+      push(&[0x01]),
+      Version::Unversioned,
+      Artifact::Runestone(Runestone {
+        edicts: Vec::new(),
+        pointer: Some(1),
+      }),
+    ),
+    (
+      push(&[0x0a, 0x01]),
+      Version::Unversioned,
+      Artifact::Runestone(Runestone {
+        edicts: vec![Edict {
+          id: ID0,
+          amount: 5,
+          output: 1,
+        }],
+        pointer: None,
+      }),
+    ),
+    (
+      push(&[0x01, 0x0b, 0x00]),
+      Version::V1,
+      Artifact::Runestone(Runestone {
+        edicts: vec![Edict {
+          id: ID1,
+          amount: 5,
+          output: 0,
+        }],
+        pointer: None,
+      }),
+    ),
+    (
+      push(&[0x0b, 0x00]),
+      Version::V1,
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::UnrecognizedVersion),
+      }),
+    ),
+    (
+      push(&[0x0a, 0x04]),
+      Version::Unversioned,
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::EdictOutput),
+      }),
+    ),
+    (
+      push(&[0x80]),
+      Version::Unversioned,
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::Varint),
+      }),
+    ),
+    (
+      vec![0x6a, 0x5e, 0x02, 0x0a],
+      Version::Unversioned,
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::InvalidScript),
+      }),
+    ),
+    (
+      vec![0x6a, 0x5e, 0x51],
+      Version::Unversioned,
+      Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::Opcode),
+      }),
+    ),
+  ] {
+    assert_eq!(
+      Runestone::decipher_scripts(&[&[0x51], &[0x51], &script], version),
+      Some(expected),
+      "{script:02x?}"
+    );
+  }
+
+  assert_eq!(Runestone::decipher_scripts(&[&[0x51]], Version::V1), None);
+}
+
+#[test]
+fn decipher_matches_bitcoin_script_parser() {
+  let mut rng = Rng(0x5eed);
+
+  for _ in 0..10_000 {
+    let mut script = vec![0x6a, 0x5e];
+
+    for _ in 0..rng.below(4) {
+      match rng.below(8) {
+        0 => script.push(0x4c + rng.below(3) as u8),
+        1 => script.push(0x4f + rng.below(0xb0) as u8),
+        _ => {
+          let len = rng.below(90) as usize;
+          let data = rng.bytes(len);
+          let push: &script::PushBytes = data.as_slice().try_into().unwrap();
+          script.extend(
+            script::Builder::new()
+              .push_slice(push)
+              .into_script()
+              .as_bytes(),
+          );
+        }
+      }
+    }
+
+    if rng.below(4) == 0 {
+      let len = script.len();
+      script.truncate(len - rng.below(len as u64 - 1) as usize);
+    }
+
+    let (flaw, offset, integers) = oracle(&ScriptBuf::from_bytes(script.clone()));
+
+    let diagnostics = Runestone::diagnose_scripts(&[&script], Version::Unversioned).unwrap();
+
+    if flaw.is_some() {
+      assert_eq!(diagnostics.flaw, flaw, "{script:02x?}");
+      assert_eq!(diagnostics.offset, offset, "{script:02x?}");
+    } else {
+      assert_eq!(diagnostics.offset, None, "{script:02x?}");
+    }
+
+    assert_eq!(diagnostics.integers, integers, "{script:02x?}");
+  }
+}
+
+#[test]
+fn encipher_matches_bitcoin_script_builder() {
+  let mut rng = Rng(0xe4c1);
+
+  for _ in 0..1_000 {
+    let runestone = Runestone {
+      edicts: (0..rng.below(80))
+        .map(|_| Edict {
+          id: RuneId {
+            block: 1,
+            tx: rng.below(2) as u32,
+          },
+          amount: u128::from(rng.next()) << rng.below(72),
+          output: rng.below(4) as u32,
+        })
+        .collect(),
+      pointer: (rng.below(2) == 0).then(|| rng.below(4) as u32),
+    };
+
+    for version in [Version::Unversioned, Version::V1] {
+      let script = runestone.encipher_to_vec(version);
+
+      assert_eq!(script, oracle_encipher(&runestone, version).into_bytes());
+
+      let scripts = [[0x51].as_slice(), &[0x51], &[0x51], &[0x51], &script];
+
+      let mut expected = runestone.clone();
+      for edict in &mut expected.edicts {
+        edict.amount = edict.amount.min(Runestone::MAX_AMOUNT);
+      }
+
+      assert_eq!(
+        Runestone::decipher_scripts(&scripts, version),
+        Some(Artifact::Runestone(expected)),
+      );
+    }
+  }
+}
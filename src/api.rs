@@ -3,20 +3,38 @@ use {
   serde_hex::{SerHex, Strict},
 };
 
-pub use crate::{
-  subcommand::decode::Output as Decode,
-  templates::{
-    BlocksHtml as Blocks, RuneHtml as Rune, RunesHtml as Runes, StatusHtml as Status,
-    TransactionHtml as Transaction,
+pub use {
+  crate::{
+    index::event::EventId,
+    subcommand::decode::Output as Decode,
+    templates::{
+      BlocksHtml as Blocks, MintsHtml as Mints, RuneHtml as Rune, RunesHtml as Runes,
+      StatusHtml as Status, TransactionHtml as Transaction,
+    },
   },
+  runes_bitomc::SupplyState,
 };
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AddressInfo {
+  pub outputs: Vec<OutPoint>,
+  pub sat_balance: u64,
+  pub tighten_balance: u128,
+  pub ease_balance: u128,
+  /// Value of the Tighten and Ease balances at the marginal conversion rate,
+  /// in units of the conversion invariant
+  pub invariant_balance: u128,
+  /// Value of the sat balance in utils at the tip's `utils_per_sat`
+  pub util_balance: u128,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Block {
   pub best_height: u32,
   pub hash: BlockHash,
   pub height: u32,
   pub runes: Vec<SpacedRune>,
+  pub state_commitment: Option<sha256::Hash>,
   pub target: BlockHash,
   pub transactions: Vec<bitcoin::blockdata::transaction::Transaction>,
 }
@@ -27,6 +45,7 @@ impl Block {
     height: Height,
     best_height: Height,
     runes: Vec<SpacedRune>,
+    state_commitment: Option<sha256::Hash>,
   ) -> Self {
     Self {
       hash: block.header.block_hash(),
@@ -34,6 +53,7 @@ impl Block {
       height: height.0,
       best_height: best_height.0,
       runes,
+      state_commitment,
       transactions: block.txdata,
     }
   }
@@ -72,12 +92,19 @@ pub struct BlockInfo {
   pub version: u32,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone)]
-pub struct SupplyState {
-  pub supply0: u128,
-  pub supply1: u128,
-  pub burned0: u128,
-  pub burned1: u128,
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Event {
+  pub id: EventId,
+  pub event: crate::index::event::Event,
+}
+
+/// Body of the requests which deliver index events to webhooks. Deliveries
+/// may be retried, so consumers should ignore batches whose `id` they have
+/// already seen.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct WebhookBatch {
+  pub id: u64,
+  pub events: Vec<crate::index::event::Event>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone)]
@@ -95,6 +122,14 @@ pub struct RateHistory {
   pub history: Vec<u128>,
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Conversions {
+  pub entries: Vec<(Txid, ConversionEntry)>,
+  pub more: bool,
+  pub prev: Option<usize>,
+  pub next: Option<usize>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Output {
   pub address: Option<Address<NetworkUnchecked>>,
@@ -154,3 +189,36 @@ pub struct Sat {
   pub satpoint: Option<SatPoint>,
   pub timestamp: i64,
 }
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct RuneAmounts {
+  pub tighten: u128,
+  pub ease: u128,
+}
+
+impl From<&BTreeMap<RuneId, u128>> for RuneAmounts {
+  fn from(balances: &BTreeMap<RuneId, u128>) -> Self {
+    Self {
+      tighten: balances.get(&ID0).copied().unwrap_or_default(),
+      ease: balances.get(&ID1).copied().unwrap_or_default(),
+    }
+  }
+}
+
+/// What a transaction would do if it were mined after the simulated
+/// transactions before it, in the next block
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Simulation {
+  pub txid: Txid,
+  pub artifact: Option<Artifact>,
+  /// Runes received by each output, keyed by output index. Outputs which
+  /// would receive no runes are omitted.
+  pub allocations: BTreeMap<u32, RuneAmounts>,
+  pub burned: RuneAmounts,
+  pub minted: Option<RuneAmounts>,
+  pub conversion: Option<ConversionEntry>,
+  pub failed_conversion: Option<FailedConversionEntry>,
+  pub supply_state: SupplyState,
+  /// Util state if the block ended after this transaction
+  pub util_state: UtilState,
+}
@@ -0,0 +1,203 @@
+use {
+  super::*,
+  std::sync::mpsc::{self, RecvTimeoutError, TrySendError},
+  zeromq::{Socket, SocketRecv, SubSocket},
+};
+
+/// Block notifications published by bitcoind over ZMQ, which let the index be
+/// updated as soon as a block arrives, instead of on the next poll.
+///
+/// Subscribes to both `hashblock` and `rawblock`, so either of bitcoind's
+/// `-zmqpubhashblock` or `-zmqpubrawblock` endpoints can be used. If the
+/// endpoint can't be reached, waits fall back to the polling interval while
+/// the subscriber keeps reconnecting.
+pub(crate) struct BlockNotifications {
+  receiver: Option<mpsc::Receiver<()>>,
+}
+
+impl BlockNotifications {
+  const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+  const TOPICS: [&'static str; 2] = ["hashblock", "rawblock"];
+
+  pub(crate) fn subscribe(endpoint: Option<&str>) -> Result<Self> {
+    let Some(endpoint) = endpoint else {
+      return Ok(Self { receiver: None });
+    };
+
+    // notifications which arrive while the index is updating are coalesced
+    // into a single wakeup
+    let (sender, receiver) = mpsc::sync_channel(1);
+
+    let endpoint = endpoint.to_owned();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()?;
+
+    thread::spawn(move || {
+      runtime.block_on(async move {
+        loop {
+          match Self::listen(&endpoint, &sender).await {
+            Ok(()) => break,
+            Err(err) => log::warn!(
+              "ZMQ block notifications from `{endpoint}` failed, polling until reconnected: {err}"
+            ),
+          }
+
+          if SHUTTING_DOWN.load(atomic::Ordering::Relaxed) {
+            break;
+          }
+
+          tokio::time::sleep(Self::RECONNECT_INTERVAL).await;
+        }
+      })
+    });
+
+    Ok(Self {
+      receiver: Some(receiver),
+    })
+  }
+
+  /// Forward notifications until the receiver is dropped, or the connection
+  /// fails
+  async fn listen(endpoint: &str, sender: &mpsc::SyncSender<()>) -> Result {
+    let mut socket = SubSocket::new();
+
+    socket.connect(endpoint).await?;
+
+    for topic in Self::TOPICS {
+      socket.subscribe(topic).await?;
+    }
+
+    log::info!("Subscribed to ZMQ block notifications from `{endpoint}`");
+
+    loop {
+      let message = socket.recv().await?;
+
+      log::debug!(
+        "Received ZMQ {} notification",
+        message
+          .get(0)
+          .map(|topic| String::from_utf8_lossy(topic).into_owned())
+          .unwrap_or_default()
+      );
+
+      match sender.try_send(()) {
+        Ok(()) | Err(TrySendError::Full(())) => {}
+        Err(TrySendError::Disconnected(())) => return Ok(()),
+      }
+    }
+  }
+
+  /// Wait until a block notification arrives or `timeout` passes, returning
+  /// whether a notification arrived
+  pub(crate) fn wait(&self, timeout: Duration) -> bool {
+    match &self.receiver {
+      Some(receiver) => match receiver.recv_timeout(timeout) {
+        Ok(()) => true,
+        Err(RecvTimeoutError::Timeout) => false,
+        Err(RecvTimeoutError::Disconnected) => {
+          thread::sleep(timeout);
+          false
+        }
+      },
+      None => {
+        thread::sleep(timeout);
+        false
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use {
+    super::*,
+    zeromq::{PubSocket, SocketSend, ZmqMessage},
+  };
+
+  /// A stand-in for bitcoind's ZMQ publisher, which publishes a block
+  /// notification on `topic` every few milliseconds until dropped
+  pub(crate) struct Publisher {
+    pub(crate) endpoint: String,
+    stop: Arc<AtomicBool>,
+  }
+
+  impl Publisher {
+    pub(crate) fn spawn(topic: &'static str) -> Self {
+      let stop = Arc::new(AtomicBool::new(false));
+      let (endpoint_sender, endpoint_receiver) = mpsc::channel();
+
+      {
+        let stop = stop.clone();
+        thread::spawn(move || {
+          Runtime::new().unwrap().block_on(async move {
+            let mut socket = PubSocket::new();
+
+            endpoint_sender
+              .send(socket.bind("tcp://127.0.0.1:0").await.unwrap().to_string())
+              .unwrap();
+
+            let mut sequence = 0u32;
+
+            // subscriptions take a moment to reach the publisher, and
+            // messages published before then are dropped, so keep publishing
+            while !stop.load(atomic::Ordering::Relaxed) {
+              let mut message = ZmqMessage::from(topic);
+              message.push_back(BlockHash::all_zeros().to_byte_array().to_vec().into());
+              message.push_back(sequence.to_le_bytes().to_vec().into());
+              socket.send(message).await.unwrap();
+              sequence += 1;
+              tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+          })
+        });
+      }
+
+      Self {
+        endpoint: endpoint_receiver.recv().unwrap(),
+        stop,
+      }
+    }
+  }
+
+  impl Drop for Publisher {
+    fn drop(&mut self) {
+      self.stop.store(true, atomic::Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  fn notifications_wake_waiters() {
+    for topic in BlockNotifications::TOPICS {
+      let publisher = Publisher::spawn(topic);
+
+      let notifications = BlockNotifications::subscribe(Some(&publisher.endpoint)).unwrap();
+
+      assert!(notifications.wait(Duration::from_secs(30)), "{topic}");
+    }
+  }
+
+  #[test]
+  fn other_topics_are_ignored() {
+    let publisher = Publisher::spawn("hashtx");
+
+    let notifications = BlockNotifications::subscribe(Some(&publisher.endpoint)).unwrap();
+
+    assert!(!notifications.wait(Duration::from_millis(500)));
+  }
+
+  #[test]
+  fn without_endpoint_waits_for_timeout() {
+    let notifications = BlockNotifications::subscribe(None).unwrap();
+    let start = Instant::now();
+    assert!(!notifications.wait(Duration::from_millis(50)));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+  }
+
+  #[test]
+  fn unreachable_endpoint_falls_back_to_polling() {
+    let notifications = BlockNotifications::subscribe(Some("tcp://127.0.0.1:1")).unwrap();
+    assert!(!notifications.wait(Duration::from_millis(50)));
+  }
+}
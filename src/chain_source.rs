@@ -0,0 +1,221 @@
+use {
+  super::*, bitcoin::block::Header, bitcoincore_rpc::jsonrpc, index::BitcoinCoreRpcResultExt,
+  serde_json::value::RawValue, std::ops::Range,
+};
+
+pub(crate) mod esplora;
+
+/// Source of the chain data which the index is built from, either Bitcoin
+/// Core's JSON-RPC interface, or an Esplora REST server.
+///
+/// Lookups return `None` for blocks and transactions which don't exist, and
+/// only fail for other errors.
+pub(crate) trait ChainSource: Send + Sync {
+  fn block(&self, height: u32) -> Result<Option<Block>>;
+
+  fn block_count(&self) -> Result<u64>;
+
+  fn block_hash(&self, height: u32) -> Result<Option<BlockHash>>;
+
+  fn block_header(&self, hash: BlockHash) -> Result<Option<Header>>;
+
+  /// Headers of the blocks at `heights`, stopping early at the first height
+  /// past the tip
+  fn block_headers(&self, heights: Range<u32>) -> Result<Vec<Header>> {
+    let mut headers = Vec::new();
+
+    for height in heights {
+      let Some(hash) = self.block_hash(height)? else {
+        break;
+      };
+
+      headers.push(
+        self
+          .block_header(hash)?
+          .with_context(|| format!("missing header for block {hash}"))?,
+      );
+    }
+
+    Ok(headers)
+  }
+
+  /// Height of the first block whose full data is still available, or
+  /// `None` if old blocks are never pruned. Headers are kept for pruned
+  /// blocks.
+  fn prune_height(&self) -> Result<Option<u32>>;
+
+  fn raw_transaction(&self, txid: Txid) -> Result<Option<Transaction>>;
+
+  /// Height of the block which confirmed the transaction with `txid`, or
+  /// `None` if it is unconfirmed or doesn't exist
+  fn transaction_height(&self, txid: Txid) -> Result<Option<u32>>;
+
+  /// The output at `outpoint`, or `None` if it is spent or doesn't exist,
+  /// counting outputs created and spent by mempool transactions if
+  /// `include_mempool` is set
+  fn tx_out(&self, outpoint: OutPoint, include_mempool: bool) -> Result<Option<TxOut>>;
+}
+
+impl ChainSource for Client {
+  fn block(&self, height: u32) -> Result<Option<Block>> {
+    self
+      .get_block_hash(height.into())
+      .into_option()?
+      .map(|hash| Ok(self.get_block(&hash)?))
+      .transpose()
+  }
+
+  fn block_count(&self) -> Result<u64> {
+    Ok(self.get_block_count()?)
+  }
+
+  fn block_hash(&self, height: u32) -> Result<Option<BlockHash>> {
+    self.get_block_hash(height.into()).into_option()
+  }
+
+  fn block_header(&self, hash: BlockHash) -> Result<Option<Header>> {
+    self.get_block_header(&hash).into_option()
+  }
+
+  /// Fetches hashes and then headers in two JSON-RPC batches, instead of
+  /// making two round trips per block
+  fn block_headers(&self, heights: Range<u32>) -> Result<Vec<Header>> {
+    if heights.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let client = self.get_jsonrpc_client();
+
+    let params = heights
+      .map(|height| [jsonrpc::arg(height)])
+      .collect::<Vec<[Box<RawValue>; 1]>>();
+
+    let requests = params
+      .iter()
+      .map(|params| client.build_request("getblockhash", params))
+      .collect::<Vec<jsonrpc::Request>>();
+
+    let mut hashes = Vec::new();
+
+    for response in client.send_batch(&requests)? {
+      let Some(hash) = response
+        .context("missing getblockhash response")?
+        .result::<BlockHash>()
+        .map_err(bitcoincore_rpc::Error::JsonRpc)
+        .into_option()?
+      else {
+        break;
+      };
+
+      hashes.push(hash);
+    }
+
+    if hashes.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let params = hashes
+      .iter()
+      .map(|hash| [jsonrpc::arg(hash), jsonrpc::arg(false)])
+      .collect::<Vec<[Box<RawValue>; 2]>>();
+
+    let requests = params
+      .iter()
+      .map(|params| client.build_request("getblockheader", params))
+      .collect::<Vec<jsonrpc::Request>>();
+
+    client
+      .send_batch(&requests)?
+      .into_iter()
+      .zip(hashes)
+      .map(|(response, hash)| {
+        let header = response
+          .with_context(|| format!("missing getblockheader response for block {hash}"))?
+          .result::<String>()?;
+
+        Ok(consensus::deserialize(&hex::decode(header)?)?)
+      })
+      .collect()
+  }
+
+  fn prune_height(&self) -> Result<Option<u32>> {
+    let info = self.get_blockchain_info()?;
+
+    if !info.pruned {
+      return Ok(None);
+    }
+
+    Ok(Some(u32::try_from(info.prune_height.unwrap_or_default())?))
+  }
+
+  fn raw_transaction(&self, txid: Txid) -> Result<Option<Transaction>> {
+    self.get_raw_transaction(&txid, None).into_option()
+  }
+
+  fn transaction_height(&self, txid: Txid) -> Result<Option<u32>> {
+    let Some(blockhash) = self
+      .get_raw_transaction_info(&txid, None)
+      .into_option()?
+      .and_then(|info| info.blockhash)
+    else {
+      return Ok(None);
+    };
+
+    Ok(
+      self
+        .get_block_header_info(&blockhash)
+        .into_option()?
+        .map(|info| u32::try_from(info.height))
+        .transpose()?,
+    )
+  }
+
+  fn tx_out(&self, outpoint: OutPoint, include_mempool: bool) -> Result<Option<TxOut>> {
+    Ok(
+      self
+        .get_tx_out(&outpoint.txid, outpoint.vout, Some(include_mempool))?
+        .map(|result| TxOut {
+          value: result.value.to_sat(),
+          script_pubkey: ScriptBuf::from_bytes(result.script_pub_key.hex),
+        }),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn batched_block_headers_match_headers_fetched_one_at_a_time() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+
+    core.mine_blocks(3);
+
+    let client = Client::new(
+      &core.url(),
+      bitcoincore_rpc::Auth::UserPass("username".into(), "password".into()),
+    )
+    .unwrap();
+
+    let stand_in = esplora::tests::StandIn::spawn(esplora::tests::blocks(&core));
+
+    let esplora = esplora::Esplora::new(&stand_in.url, Chain::Regtest).unwrap();
+
+    let headers = client.block_headers(0..10).unwrap();
+
+    assert_eq!(
+      headers,
+      esplora::tests::blocks(&core)
+        .into_iter()
+        .map(|block| block.header)
+        .collect::<Vec<Header>>()
+    );
+
+    assert_eq!(esplora.block_headers(0..10).unwrap(), headers);
+
+    assert_eq!(client.block_headers(1..3).unwrap(), headers[1..3]);
+    assert_eq!(client.block_headers(2..2).unwrap(), Vec::new());
+    assert_eq!(client.block_headers(5..10).unwrap(), Vec::new());
+  }
+}
@@ -0,0 +1,452 @@
+use {
+  super::*,
+  reqwest::{blocking::Response, StatusCode},
+};
+
+#[derive(Deserialize)]
+struct Outspend {
+  spent: bool,
+  status: Option<Status>,
+}
+
+#[derive(Deserialize)]
+struct Status {
+  confirmed: bool,
+  block_height: Option<u32>,
+}
+
+/// Client for the REST API of an Esplora server, such as the one run by
+/// mempool.space or blockstream.info.
+pub(crate) struct Esplora {
+  client: reqwest::blocking::Client,
+  url: Url,
+}
+
+impl Esplora {
+  pub(crate) fn new(url: &str, chain: Chain) -> Result<Self> {
+    log::info!("Connecting to Esplora at {url}");
+
+    let mut url = Url::parse(url).with_context(|| format!("invalid Esplora URL `{url}`"))?;
+
+    // paths are joined to the URL, which replaces its last path segment
+    // unless it ends with a slash
+    if !url.path().ends_with('/') {
+      url.set_path(&format!("{}/", url.path()));
+    }
+
+    let esplora = Self {
+      client: reqwest::blocking::Client::new(),
+      url,
+    };
+
+    let genesis_block_hash = esplora
+      .block_hash(0)
+      .with_context(|| format!("failed to connect to Esplora at `{}`", esplora.url))?
+      .with_context(|| format!("Esplora at `{}` has no genesis block", esplora.url))?;
+
+    ensure!(
+      genesis_block_hash == chain.genesis_block().block_hash(),
+      "Esplora at `{}` is not on {chain}",
+      esplora.url,
+    );
+
+    Ok(esplora)
+  }
+
+  fn get(&self, path: &str) -> Result<Option<Response>> {
+    let response = self.client.get(self.url.join(path)?).send()?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+      return Ok(None);
+    }
+
+    if !response.status().is_success() {
+      bail!(
+        "Esplora request for `{path}` failed with {}: {}",
+        response.status(),
+        response.text()?,
+      );
+    }
+
+    Ok(Some(response))
+  }
+
+  fn get_bytes(&self, path: &str) -> Result<Option<Vec<u8>>> {
+    self
+      .get(path)?
+      .map(|response| Ok(response.bytes()?.to_vec()))
+      .transpose()
+  }
+
+  fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+    self
+      .get(path)?
+      .map(|response| Ok(response.json()?))
+      .transpose()
+  }
+
+  fn get_text(&self, path: &str) -> Result<Option<String>> {
+    self
+      .get(path)?
+      .map(|response| Ok(response.text()?))
+      .transpose()
+  }
+}
+
+impl ChainSource for Esplora {
+  fn block(&self, height: u32) -> Result<Option<Block>> {
+    let Some(hash) = self.block_hash(height)? else {
+      return Ok(None);
+    };
+
+    self
+      .get_bytes(&format!("block/{hash}/raw"))?
+      .map(|block| Ok(consensus::deserialize(&block)?))
+      .transpose()
+  }
+
+  fn block_count(&self) -> Result<u64> {
+    self
+      .get_text("blocks/tip/height")?
+      .context("Esplora has no blocks")?
+      .trim()
+      .parse()
+      .context("invalid Esplora block count")
+  }
+
+  fn block_hash(&self, height: u32) -> Result<Option<BlockHash>> {
+    self
+      .get_text(&format!("block-height/{height}"))?
+      .map(|hash| hash.trim().parse().context("invalid Esplora block hash"))
+      .transpose()
+  }
+
+  fn block_header(&self, hash: BlockHash) -> Result<Option<Header>> {
+    self
+      .get_text(&format!("block/{hash}/header"))?
+      .map(|header| Ok(consensus::deserialize(&hex::decode(header.trim())?)?))
+      .transpose()
+  }
+
+  fn prune_height(&self) -> Result<Option<u32>> {
+    Ok(None)
+  }
+
+  fn raw_transaction(&self, txid: Txid) -> Result<Option<Transaction>> {
+    self
+      .get_bytes(&format!("tx/{txid}/raw"))?
+      .map(|transaction| Ok(consensus::deserialize(&transaction)?))
+      .transpose()
+  }
+
+  fn transaction_height(&self, txid: Txid) -> Result<Option<u32>> {
+    Ok(
+      self
+        .get_json::<Status>(&format!("tx/{txid}/status"))?
+        .filter(|status| status.confirmed)
+        .and_then(|status| status.block_height),
+    )
+  }
+
+  fn tx_out(&self, outpoint: OutPoint, include_mempool: bool) -> Result<Option<TxOut>> {
+    let Some(tx_out) = self
+      .raw_transaction(outpoint.txid)?
+      .and_then(|transaction| {
+        transaction
+          .output
+          .into_iter()
+          .nth(outpoint.vout.into_usize())
+      })
+    else {
+      return Ok(None);
+    };
+
+    if !include_mempool {
+      let status = self
+        .get_json::<Status>(&format!("tx/{}/status", outpoint.txid))?
+        .context("missing Esplora transaction status")?;
+
+      if !status.confirmed {
+        return Ok(None);
+      }
+    }
+
+    let outspend = self
+      .get_json::<Outspend>(&format!("tx/{}/outspend/{}", outpoint.txid, outpoint.vout))?
+      .context("missing Esplora outspend")?;
+
+    let spent = outspend.spent
+      && (include_mempool
+        || outspend
+          .status
+          .map(|status| status.confirmed)
+          .unwrap_or_default());
+
+    if spent {
+      return Ok(None);
+    }
+
+    Ok(Some(tx_out))
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use {
+    super::*,
+    axum::{
+      extract::{Path, State},
+      response::{IntoResponse, Response},
+      routing::get,
+      Json, Router,
+    },
+    std::net::TcpListener,
+  };
+
+  /// A stand-in Esplora server, serving a fixed chain over the Esplora REST
+  /// API.
+  pub(crate) struct StandIn {
+    pub(crate) url: String,
+  }
+
+  struct Blocks {
+    blocks: Vec<Block>,
+    heights: HashMap<Txid, usize>,
+    spent: HashSet<OutPoint>,
+    transactions: HashMap<Txid, Transaction>,
+  }
+
+  impl StandIn {
+    pub(crate) fn spawn(blocks: Vec<Block>) -> Self {
+      let mut heights = HashMap::new();
+      let mut spent = HashSet::new();
+      let mut transactions = HashMap::new();
+
+      for (height, block) in blocks.iter().enumerate() {
+        for transaction in &block.txdata {
+          for input in &transaction.input {
+            spent.insert(input.previous_output);
+          }
+          heights.insert(transaction.txid(), height);
+          transactions.insert(transaction.txid(), transaction.clone());
+        }
+      }
+
+      let state = Arc::new(Blocks {
+        blocks,
+        heights,
+        spent,
+        transactions,
+      });
+
+      let router = Router::new()
+        .route("/api/blocks/tip/height", get(Self::tip_height))
+        .route("/api/block-height/:height", get(Self::block_height))
+        .route("/api/block/:hash/header", get(Self::block_header))
+        .route("/api/block/:hash/raw", get(Self::block_raw))
+        .route("/api/tx/:txid/outspend/:vout", get(Self::outspend))
+        .route("/api/tx/:txid/raw", get(Self::tx_raw))
+        .route("/api/tx/:txid/status", get(Self::tx_status))
+        .with_state(state);
+
+      let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+      let port = listener.local_addr().unwrap().port();
+
+      thread::spawn(move || {
+        Runtime::new().unwrap().block_on(async {
+          axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router.into_make_service())
+            .await
+            .unwrap()
+        })
+      });
+
+      Self {
+        url: format!("http://127.0.0.1:{port}/api"),
+      }
+    }
+
+    fn not_found() -> Response {
+      StatusCode::NOT_FOUND.into_response()
+    }
+
+    fn find_block(blocks: &Blocks, hash: &str) -> Option<Block> {
+      blocks
+        .blocks
+        .iter()
+        .find(|block| block.block_hash().to_string() == hash)
+        .cloned()
+    }
+
+    async fn tip_height(State(blocks): State<Arc<Blocks>>) -> Response {
+      (blocks.blocks.len() - 1).to_string().into_response()
+    }
+
+    async fn block_height(
+      State(blocks): State<Arc<Blocks>>,
+      Path(height): Path<usize>,
+    ) -> Response {
+      match blocks.blocks.get(height) {
+        Some(block) => block.block_hash().to_string().into_response(),
+        None => Self::not_found(),
+      }
+    }
+
+    async fn block_header(State(blocks): State<Arc<Blocks>>, Path(hash): Path<String>) -> Response {
+      match Self::find_block(&blocks, &hash) {
+        Some(block) => consensus::encode::serialize_hex(&block.header).into_response(),
+        None => Self::not_found(),
+      }
+    }
+
+    async fn block_raw(State(blocks): State<Arc<Blocks>>, Path(hash): Path<String>) -> Response {
+      match Self::find_block(&blocks, &hash) {
+        Some(block) => consensus::serialize(&block).into_response(),
+        None => Self::not_found(),
+      }
+    }
+
+    async fn outspend(
+      State(blocks): State<Arc<Blocks>>,
+      Path((txid, vout)): Path<(Txid, u32)>,
+    ) -> Response {
+      if blocks.spent.contains(&OutPoint { txid, vout }) {
+        Json(serde_json::json!({
+          "spent": true,
+          "status": { "confirmed": true },
+        }))
+        .into_response()
+      } else {
+        Json(serde_json::json!({ "spent": false })).into_response()
+      }
+    }
+
+    async fn tx_status(State(blocks): State<Arc<Blocks>>, Path(txid): Path<Txid>) -> Response {
+      match blocks.heights.get(&txid) {
+        Some(height) => Json(serde_json::json!({
+          "confirmed": true,
+          "block_height": height,
+        }))
+        .into_response(),
+        None => Self::not_found(),
+      }
+    }
+
+    async fn tx_raw(State(blocks): State<Arc<Blocks>>, Path(txid): Path<Txid>) -> Response {
+      match blocks.transactions.get(&txid) {
+        Some(transaction) => consensus::serialize(transaction).into_response(),
+        None => Self::not_found(),
+      }
+    }
+  }
+
+  pub(crate) fn blocks(core: &mockcore::Handle) -> Vec<Block> {
+    let state = core.state();
+    state
+      .hashes
+      .iter()
+      .map(|hash| state.blocks[hash].clone())
+      .collect()
+  }
+
+  #[test]
+  fn chain_data_matches_bitcoin_core() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+
+    core.mine_blocks(2);
+
+    let txid = core.broadcast_tx(mockcore::TransactionTemplate {
+      inputs: &[(1, 0, 0, Default::default())],
+      outputs: 2,
+      ..Default::default()
+    });
+
+    core.mine_blocks(1);
+
+    let stand_in = StandIn::spawn(blocks(&core));
+
+    let esplora = Esplora::new(&stand_in.url, Chain::Regtest).unwrap();
+
+    let client = Client::new(
+      &core.url(),
+      bitcoincore_rpc::Auth::UserPass("username".into(), "password".into()),
+    )
+    .unwrap();
+
+    assert_eq!(esplora.block_count().unwrap(), 3);
+    assert_eq!(
+      esplora.block_count().unwrap(),
+      client.block_count().unwrap()
+    );
+
+    for height in 0..5 {
+      assert_eq!(
+        esplora.block_hash(height).unwrap(),
+        client.block_hash(height).unwrap()
+      );
+      assert_eq!(
+        esplora.block(height).unwrap(),
+        client.block(height).unwrap()
+      );
+    }
+
+    let hash = client.block_hash(3).unwrap().unwrap();
+
+    assert_eq!(
+      esplora.block_header(hash).unwrap(),
+      client.block_header(hash).unwrap()
+    );
+
+    assert_eq!(esplora.block_header(BlockHash::all_zeros()).unwrap(), None);
+
+    assert_eq!(
+      esplora.raw_transaction(txid).unwrap(),
+      client.raw_transaction(txid).unwrap()
+    );
+
+    assert_eq!(esplora.raw_transaction(Txid::all_zeros()).unwrap(), None);
+
+    for outpoint in [
+      OutPoint { txid, vout: 0 },
+      OutPoint { txid, vout: 1 },
+      OutPoint { txid, vout: 2 },
+      OutPoint {
+        txid: core.tx(1, 0).txid(),
+        vout: 0,
+      },
+      OutPoint {
+        txid: core.tx(2, 0).txid(),
+        vout: 0,
+      },
+    ] {
+      for include_mempool in [false, true] {
+        assert_eq!(
+          esplora.tx_out(outpoint, include_mempool).unwrap(),
+          client.tx_out(outpoint, include_mempool).unwrap(),
+          "{outpoint}"
+        );
+      }
+    }
+
+    assert!(esplora
+      .tx_out(OutPoint { txid, vout: 0 }, false)
+      .unwrap()
+      .is_some());
+  }
+
+  #[test]
+  fn wrong_chain() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+
+    let stand_in = StandIn::spawn(blocks(&core));
+
+    assert_eq!(
+      Esplora::new(&stand_in.url, Chain::Mainnet)
+        .err()
+        .unwrap()
+        .to_string(),
+      format!("Esplora at `{}/` is not on mainnet", stand_in.url),
+    );
+  }
+}
@@ -1,13 +1,16 @@
 use {
   self::{
+    commitment::{BalanceChanges, StateCommitment},
     entry::{
-      Entry, HeaderValue, OutPointValue, RuneEntryValue, RuneIdValue, TxOutValue, TxidValue,
-      UtilEntry, UtilEntryValue,
+      ConversionEntryValue, Entry, FailedConversionEntryValue, HeaderValue, MintEntryValue,
+      OutPointValue, RuneEntryValue, RuneIdValue, SupplyStateValue, TxOutValue, TxidValue,
+      UtilEntry, UtilEntryValue, UtilStateValue,
     },
-    event::Event,
-    lot::Lot,
+    event::{Event, EventSender},
+    migration::Migration,
     reorg::Reorg,
     updater::Updater,
+    webhooks::Webhooks,
   },
   super::*,
   crate::templates::StatusHtml,
@@ -19,41 +22,64 @@ use {
   chrono::SubsecRound,
   indicatif::{ProgressBar, ProgressStyle},
   log::log_enabled,
+  num_integer::Roots,
   redb::{
     Database, DatabaseError, MultimapTable, MultimapTableDefinition, MultimapTableHandle,
-    ReadOnlyTable, ReadableTable, ReadableTableMetadata, RepairSession, StorageError, Table,
-    TableDefinition, TableHandle, TableStats, WriteTransaction,
+    ReadOnlyTable, ReadTransaction, ReadableTable, ReadableTableMetadata, RepairSession,
+    StorageError, Table, TableDefinition, TableHandle, TableStats, WriteTransaction,
   },
   std::{collections::HashMap, sync::Once},
 };
 
-pub use self::entry::RuneEntry;
+pub use self::{
+  entry::{ConversionEntry, Direction, FailedConversionEntry, MintEntry, RuneEntry},
+  migration::MigrationStep,
+  snapshot::Snapshot,
+  verify::Discrepancy,
+};
 
+mod blk_files;
+mod commitment;
 pub(crate) mod entry;
 pub mod event;
 mod fetcher;
-mod lot;
+mod migration;
+mod read_only;
 mod reorg;
 mod rtx;
+mod snapshot;
 mod updater;
+mod verify;
+pub(crate) mod webhooks;
 
 #[cfg(test)]
 pub(crate) mod testing;
 
-const SCHEMA_VERSION: u64 = 26;
+const SCHEMA_VERSION: u64 = 33;
 
 define_multimap_table! { SCRIPT_PUBKEY_TO_OUTPOINT, &[u8], OutPointValue }
+define_table! { CONVERSION_NUMBER_TO_TXID, u64, &TxidValue }
+define_table! { HEIGHT_TO_BALANCE_CHANGES, u32, &[u8; 32] }
 define_table! { HEIGHT_TO_BLOCK_HEADER, u32, &HeaderValue }
+define_table! { HEIGHT_TO_MINT, (u32, u32), MintEntryValue }
+define_table! { HEIGHT_TO_RATE, u32, u128 }
+define_table! { HEIGHT_TO_STATE_COMMITMENT, u32, &[u8; 32] }
+define_table! { HEIGHT_TO_SUPPLY_STATE, u32, SupplyStateValue }
+define_table! { HEIGHT_TO_UTIL_STATE, u32, UtilStateValue }
 define_table! { OUTPOINT_TO_RUNE_BALANCES, &OutPointValue, &[u8] }
 define_table! { OUTPOINT_TO_TXOUT, &OutPointValue, TxOutValue }
 define_table! { RUNE_ID_TO_RUNE_ENTRY, RuneIdValue, RuneEntryValue }
 define_table! { RUNE_TO_RUNE_ID, u128, RuneIdValue }
+define_table! { SCRIPT_PUBKEY_TO_RUNE_BALANCE, &[u8], (u128, u128) }
 define_table! { STATISTIC_TO_COUNT, u64, u64 }
 define_table! { TRANSACTION_ID_TO_RUNE, &TxidValue, u128 }
 define_table! { TRANSACTION_ID_TO_TRANSACTION, &TxidValue, &[u8] }
+define_table! { TXID_TO_CONVERSION, &TxidValue, ConversionEntryValue }
+define_table! { TXID_TO_FAILED_CONVERSION, &TxidValue, FailedConversionEntryValue }
 define_table! { WRITE_TRANSACTION_STARTING_BLOCK_COUNT_TO_TIMESTAMP, u32, u128 }
 define_table! { STATE_CHANGE_TO_LAST_OUTPOINT, u8, &OutPointValue }
 define_table! { STATE_CHANGE_TO_LAST_TXOUT_VALUE, u8, u64 }
+define_table! { UNCLAIMED_REWARD, u8, (u128, u128) }
 define_table! { UTIL_ENTRY, u8, UtilEntryValue }
 
 #[derive(Copy, Clone)]
@@ -78,7 +104,8 @@ impl From<Statistic> for u64 {
   }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum StateChange {
   Mint = 0,
   Convert = 1,
@@ -107,12 +134,15 @@ pub struct Info {
   metadata_bytes: u64,
   outputs_traversed: u64,
   page_size: usize,
+  prune_height: Option<u32>,
   sat_ranges: u64,
+  state_commitment: Option<sha256::Hash>,
   stored_bytes: u64,
   tables: BTreeMap<String, TableInfo>,
   total_bytes: u64,
   pub transactions: Vec<TransactionInfo>,
   tree_height: u32,
+  unavailable_features: Vec<String>,
   utxos_indexed: u64,
 }
 
@@ -173,17 +203,22 @@ impl<T> BitcoinCoreRpcResultExt<T> for Result<T, bitcoincore_rpc::Error> {
 }
 
 pub struct Index {
-  pub(crate) client: Client,
+  chain_source: Box<dyn ChainSource>,
+  client: Option<Client>,
   database: Database,
   durability: redb::Durability,
-  event_sender: Option<tokio::sync::mpsc::Sender<Event>>,
+  event_sender: Option<EventSender>,
   first_rune_height: u32,
   genesis_block_coinbase_transaction: Transaction,
   genesis_block_coinbase_txid: Txid,
+  headers_only: bool,
   height_limit: Option<u32>,
   index_addresses: bool,
   index_transactions: bool,
   path: PathBuf,
+  prune_height: Mutex<Option<u32>>,
+  read_only: Option<SystemTime>,
+  schedule: Schedule,
   settings: Settings,
   started: DateTime<Utc>,
   unrecoverably_reorged: AtomicBool,
@@ -198,7 +233,13 @@ impl Index {
     settings: &Settings,
     event_sender: Option<tokio::sync::mpsc::Sender<Event>>,
   ) -> Result<Self> {
-    let client = settings.bitcoin_rpc_client(None)?;
+    let chain_source = settings.chain_source()?;
+
+    let client = if settings.esplora_url().is_some() {
+      None
+    } else {
+      Some(settings.bitcoin_rpc_client(None)?)
+    };
 
     let path = settings.index().to_owned();
 
@@ -263,6 +304,9 @@ impl Index {
             .unwrap_or(0);
 
           match schema_version.cmp(&SCHEMA_VERSION) {
+            cmp::Ordering::Less if Migration::path(schema_version).is_some() => {
+              Self::migrate(&database, settings, schema_version, durability)?;
+            }
             cmp::Ordering::Less =>
               bail!(
                 "index at `{}` appears to have been built with an older, incompatible version of bitomc, consider deleting and rebuilding the index: index schema {schema_version}, bitomc schema {SCHEMA_VERSION}",
@@ -292,15 +336,26 @@ impl Index {
         tx.set_durability(durability);
 
         tx.open_multimap_table(SCRIPT_PUBKEY_TO_OUTPOINT)?;
+        tx.open_table(CONVERSION_NUMBER_TO_TXID)?;
+        tx.open_table(HEIGHT_TO_BALANCE_CHANGES)?;
         tx.open_table(HEIGHT_TO_BLOCK_HEADER)?;
+        tx.open_table(HEIGHT_TO_MINT)?;
+        tx.open_table(HEIGHT_TO_RATE)?;
+        tx.open_table(HEIGHT_TO_STATE_COMMITMENT)?;
+        tx.open_table(HEIGHT_TO_SUPPLY_STATE)?;
+        tx.open_table(HEIGHT_TO_UTIL_STATE)?;
         tx.open_table(OUTPOINT_TO_RUNE_BALANCES)?;
         tx.open_table(OUTPOINT_TO_TXOUT)?;
         tx.open_table(RUNE_ID_TO_RUNE_ENTRY)?;
         tx.open_table(RUNE_TO_RUNE_ID)?;
+        tx.open_table(SCRIPT_PUBKEY_TO_RUNE_BALANCE)?;
         tx.open_table(TRANSACTION_ID_TO_RUNE)?;
+        tx.open_table(TXID_TO_CONVERSION)?;
+        tx.open_table(TXID_TO_FAILED_CONVERSION)?;
         tx.open_table(WRITE_TRANSACTION_STARTING_BLOCK_COUNT_TO_TIMESTAMP)?;
         tx.open_table(STATE_CHANGE_TO_LAST_OUTPOINT)?;
         tx.open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)?;
+        tx.open_table(UNCLAIMED_REWARD)?.insert(0, (0, 0))?;
         tx.open_table(UTIL_ENTRY)?
           .insert(0, UtilEntry::new().store())?;
 
@@ -387,24 +442,204 @@ impl Index {
     let genesis_block_coinbase_transaction =
       settings.chain().genesis_block().coinbase().unwrap().clone();
 
+    let prune_height = chain_source.prune_height()?;
+
+    let webhooks = Webhooks::open(settings)?;
+
+    let event_sender = (event_sender.is_some() || webhooks.is_some()).then(|| EventSender {
+      channel: event_sender,
+      webhooks,
+    });
+
     Ok(Self {
       genesis_block_coinbase_txid: genesis_block_coinbase_transaction.txid(),
+      chain_source,
       client,
       database,
       durability,
       event_sender,
       first_rune_height: settings.first_rune_height(),
       genesis_block_coinbase_transaction,
+      headers_only: true,
+      height_limit: settings.height_limit(),
+      index_addresses,
+      index_transactions,
+      schedule: settings.schedule(),
+      settings: settings.clone(),
+      path,
+      prune_height: Mutex::new(prune_height),
+      read_only: None,
+      started: Utc::now(),
+      unrecoverably_reorged: AtomicBool::new(false),
+    })
+  }
+
+  /// Open the copy of the index published by `bitomc index update --follow`,
+  /// which can be read but not updated
+  pub fn open_read_only(settings: &Settings) -> Result<Self> {
+    let chain_source = settings.chain_source()?;
+
+    let client = if settings.esplora_url().is_some() {
+      None
+    } else {
+      Some(settings.bitcoin_rpc_client(None)?)
+    };
+
+    let path = Self::read_only_path(settings.index());
+
+    let (database, modified) = Self::open_read_only_database(&path, settings.index_cache_size())?;
+
+    let schema_version = database
+      .begin_read()?
+      .open_table(STATISTIC_TO_COUNT)?
+      .get(&Statistic::Schema.key())?
+      .map(|x| x.value())
+      .unwrap_or(0);
+
+    ensure!(
+      schema_version == SCHEMA_VERSION,
+      "read-only index at `{}` was published by an incompatible version of bitomc, update the writer and readers together: index schema {schema_version}, bitomc schema {SCHEMA_VERSION}",
+      path.display(),
+    );
+
+    let index_addresses;
+    let index_transactions;
+
+    {
+      let tx = database.begin_read()?;
+      let statistics = tx.open_table(STATISTIC_TO_COUNT)?;
+      index_addresses = Self::is_statistic_set(&statistics, Statistic::IndexAddresses)?;
+      index_transactions = Self::is_statistic_set(&statistics, Statistic::IndexTransactions)?;
+    }
+
+    let genesis_block_coinbase_transaction =
+      settings.chain().genesis_block().coinbase().unwrap().clone();
+
+    let prune_height = chain_source.prune_height()?;
+
+    Ok(Self {
+      genesis_block_coinbase_txid: genesis_block_coinbase_transaction.txid(),
+      chain_source,
+      client,
+      database,
+      // writes to a read-only index are kept in memory
+      durability: redb::Durability::None,
+      event_sender: None,
+      first_rune_height: settings.first_rune_height(),
+      genesis_block_coinbase_transaction,
+      headers_only: true,
       height_limit: settings.height_limit(),
       index_addresses,
       index_transactions,
+      schedule: settings.schedule(),
       settings: settings.clone(),
       path,
+      prune_height: Mutex::new(prune_height),
+      read_only: Some(modified),
       started: Utc::now(),
       unrecoverably_reorged: AtomicBool::new(false),
     })
   }
 
+  fn migrate(
+    database: &Database,
+    settings: &Settings,
+    schema_version: u64,
+    durability: redb::Durability,
+  ) -> Result {
+    let migrations = Migration::path(schema_version).unwrap();
+
+    let progress_bar =
+      if cfg!(test) || log_enabled!(log::Level::Info) || settings.integration_test() {
+        None
+      } else {
+        let progress_bar = ProgressBar::new(migrations.len().try_into().unwrap());
+        progress_bar.set_style(
+          ProgressStyle::with_template("[migrating index] {wide_bar} {pos}/{len}").unwrap(),
+        );
+        Some(progress_bar)
+      };
+
+    let mut wtx = database.begin_write()?;
+
+    wtx.set_durability(durability);
+
+    for migration in migrations {
+      let step = migration.step();
+
+      log::info!(
+        "Migrating index schema {} to {}: {}",
+        step.from,
+        step.to,
+        step.description
+      );
+
+      migration.run(&wtx, settings).with_context(|| {
+        format!(
+          "failed to migrate index schema {} to {}",
+          step.from, step.to
+        )
+      })?;
+
+      if let Some(progress_bar) = &progress_bar {
+        progress_bar.inc(1);
+      }
+    }
+
+    Self::set_statistic(
+      &mut wtx.open_table(STATISTIC_TO_COUNT)?,
+      Statistic::Schema,
+      SCHEMA_VERSION,
+    )?;
+
+    wtx.commit()?;
+
+    if let Some(progress_bar) = progress_bar {
+      progress_bar.finish_and_clear();
+    }
+
+    Ok(())
+  }
+
+  /// Migrations which opening the index at `settings` would run, and the
+  /// schema version they would start from
+  pub(crate) fn pending_migrations(settings: &Settings) -> Result<(u64, Vec<MigrationStep>)> {
+    let path = settings.index();
+
+    let database = Database::open(path)
+      .with_context(|| format!("failed to open index at `{}`", path.display()))?;
+
+    let schema_version = database
+      .begin_read()?
+      .open_table(STATISTIC_TO_COUNT)?
+      .get(&Statistic::Schema.key())?
+      .map(|x| x.value())
+      .unwrap_or(0);
+
+    if schema_version == SCHEMA_VERSION {
+      return Ok((schema_version, Vec::new()));
+    }
+
+    let Some(migrations) = Migration::path(schema_version) else {
+      bail!(
+        "index at `{}` cannot be migrated from schema {schema_version} to {SCHEMA_VERSION}",
+        path.display()
+      );
+    };
+
+    Ok((
+      schema_version,
+      migrations.iter().map(Migration::step).collect(),
+    ))
+  }
+
+  /// Fetch full blocks below the first rune height instead of only headers,
+  /// as a baseline for benchmarks
+  #[cfg(any(test, feature = "bench"))]
+  pub fn fetch_full_blocks(&mut self) {
+    self.headers_only = false;
+  }
+
   #[cfg(test)]
   fn set_durability(&mut self, durability: redb::Durability) {
     self.durability = durability;
@@ -455,17 +690,57 @@ impl Index {
       initial_sync_time: Duration::from_micros(initial_sync_time),
       inscriptions: 0,
       lost_sats: 0,
+      prune_height: self.prune_height(),
       runes: statistic(Statistic::Runes)?,
       started: self.started,
       transaction_index: statistic(Statistic::IndexTransactions)? != 0,
+      unavailable_features: self.unavailable_features(),
       unrecoverably_reorged: self.unrecoverably_reorged.load(atomic::Ordering::Relaxed),
       uptime: (Utc::now() - self.started).to_std()?,
       last_mint_outpoint: self.get_last_outpoint_txout_for_state_change(StateChange::Mint)?,
       last_conversion_outpoint: self
         .get_last_outpoint_txout_for_state_change(StateChange::Convert)?,
+      state_commitment: self
+        .get_latest_state_commitment()?
+        .map(|(_height, commitment)| commitment),
+      payload_version: self
+        .rules(height.map(|height| height + 1).unwrap_or_default())
+        .version,
     })
   }
 
+  /// Height of the first block which bitcoind hasn't pruned, as of the last
+  /// update, or `None` if bitcoind isn't pruned
+  pub(crate) fn prune_height(&self) -> Option<u32> {
+    *self.prune_height.lock().unwrap()
+  }
+
+  pub(crate) fn is_pruned(&self, height: u32) -> bool {
+    self
+      .prune_height()
+      .map(|prune_height| height < prune_height)
+      .unwrap_or_default()
+  }
+
+  /// Features which are unavailable because bitcoind has pruned blocks
+  fn unavailable_features(&self) -> Vec<String> {
+    let Some(prune_height) = self.prune_height().filter(|height| *height > 0) else {
+      return Vec::new();
+    };
+
+    let mut features = vec![format!("blocks below height {prune_height}")];
+
+    if !self.index_addresses {
+      features.push("address index".into());
+    }
+
+    if self.index_transactions {
+      features.push(format!("transaction index below height {prune_height}"));
+    }
+
+    features
+  }
+
   pub fn info(&self) -> Result<Info> {
     let stats = self.database.begin_write()?.stats()?;
 
@@ -518,8 +793,13 @@ impl Index {
         leaf_pages: stats.leaf_pages(),
         metadata_bytes: stats.metadata_bytes(),
         sat_ranges: 0,
+        state_commitment: rtx
+          .open_table(HEIGHT_TO_STATE_COMMITMENT)?
+          .last()?
+          .map(|(_height, commitment)| sha256::Hash::from_byte_array(*commitment.value())),
         outputs_traversed: 0,
         page_size: stats.page_size(),
+        prune_height: self.prune_height(),
         stored_bytes: stats.stored_bytes(),
         total_bytes,
         tables,
@@ -536,6 +816,7 @@ impl Index {
           })
           .collect(),
         tree_height: stats.tree_height(),
+        unavailable_features: self.unavailable_features(),
         utxos_indexed: rtx
           .open_table(OUTPOINT_TO_TXOUT)?
           .len()?
@@ -553,18 +834,60 @@ impl Index {
         .begin_read()?
         .open_table(UTIL_ENTRY)?
         .get(0)?
-        .map(|e| UtilEntry::load(e.value()))
-        .map(|u| api::UtilState {
-          bonds_per_sat: u.bonds_per_sat(),
-          utils_per_bond: u.utils_per_bond(),
-          utils_per_sat: u.utils_per_sat(),
-          interest_rate: u.interest_rate(),
-          decimals: u.decimals(),
-        })
+        .map(|e| UtilEntry::load(e.value()).state())
         .unwrap(),
     )
   }
 
+  pub fn get_util_state_at(&self, height: u32) -> Result<Option<api::UtilState>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(HEIGHT_TO_UTIL_STATE)?
+        .get(height)?
+        .map(|state| api::UtilState::load(state.value())),
+    )
+  }
+
+  pub fn get_supply_state_at(&self, height: u32) -> Result<Option<api::SupplyState>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(HEIGHT_TO_SUPPLY_STATE)?
+        .get(height)?
+        .map(|state| api::SupplyState::load(state.value())),
+    )
+  }
+
+  pub fn get_state_commitment_at(&self, height: u32) -> Result<Option<sha256::Hash>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(HEIGHT_TO_STATE_COMMITMENT)?
+        .get(height)?
+        .map(|commitment| sha256::Hash::from_byte_array(*commitment.value())),
+    )
+  }
+
+  pub fn get_latest_state_commitment(&self) -> Result<Option<(u32, sha256::Hash)>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(HEIGHT_TO_STATE_COMMITMENT)?
+        .last()?
+        .map(|(height, commitment)| {
+          (
+            height.value(),
+            sha256::Hash::from_byte_array(*commitment.value()),
+          )
+        }),
+    )
+  }
+
   pub fn get_rate_history(&self) -> Result<api::RateHistory> {
     Ok(
       self
@@ -581,21 +904,70 @@ impl Index {
     )
   }
 
+  pub fn get_rate_history_at(&self, height: u32) -> Result<Option<api::RateHistory>> {
+    let rtx = self.database.begin_read()?;
+
+    let Some(state) = rtx.open_table(HEIGHT_TO_UTIL_STATE)?.get(height)? else {
+      return Ok(None);
+    };
+
+    let history = rtx
+      .open_table(HEIGHT_TO_RATE)?
+      .range(..=height)?
+      .rev()
+      .take(100)
+      .map(|result| result.map(|(_height, rate)| rate.value()))
+      .collect::<Result<Vec<u128>, StorageError>>()?;
+
+    Ok(Some(api::RateHistory {
+      median_interest_rate: api::UtilState::load(state.value()).interest_rate,
+      history,
+    }))
+  }
+
   pub fn simulate(&self, transactions: Vec<Transaction>) -> Result<Vec<api::SupplyState>> {
-    let wtx = self.begin_write()?;
+    let Some((initial, simulations)) = self.simulate_transactions(transactions)? else {
+      return Ok(Vec::new());
+    };
 
-    let height = wtx
-      .open_table(HEIGHT_TO_BLOCK_HEADER)?
-      .range(0..)?
-      .next_back()
-      .transpose()?
-      .map(|(height, _header)| height.value() + 1)
-      .unwrap_or(0);
+    if simulations.is_empty() {
+      return Ok(vec![initial]);
+    }
+
+    Ok(
+      simulations
+        .into_iter()
+        .map(|simulation| simulation.supply_state)
+        .collect(),
+    )
+  }
+
+  pub fn simulate_verbose(&self, transactions: Vec<Transaction>) -> Result<Vec<api::Simulation>> {
+    Ok(
+      self
+        .simulate_transactions(transactions)?
+        .map(|(_initial, simulations)| simulations)
+        .unwrap_or_default(),
+    )
+  }
 
-    Updater::simulate(wtx, self, height, transactions)
+  fn simulate_transactions(
+    &self,
+    transactions: Vec<Transaction>,
+  ) -> Result<Option<(api::SupplyState, Vec<api::Simulation>)>> {
+    let rtx = self.begin_read()?;
+
+    let height = rtx.block_count()?;
+
+    Updater::simulate(rtx.0, self, height, transactions)
   }
 
   pub fn update(&self) -> Result {
+    ensure!(
+      self.read_only.is_none(),
+      "read-only index cannot be updated"
+    );
+
     loop {
       let wtx = self.begin_write()?;
 
@@ -616,6 +988,12 @@ impl Index {
         Err(err) => {
           log::info!("{}", err.to_string());
 
+          // events from blocks which weren't committed are not delivered to
+          // webhooks, since the blocks will be indexed again
+          if let Some(webhooks) = self.webhooks() {
+            webhooks.discard();
+          }
+
           match err.downcast_ref() {
             Some(&reorg::Error::Recoverable { height, depth }) => {
               Reorg::handle_reorg(self, height, depth)?;
@@ -633,6 +1011,21 @@ impl Index {
     }
   }
 
+  pub(crate) fn webhooks(&self) -> Option<&Webhooks> {
+    self
+      .event_sender
+      .as_ref()
+      .and_then(|sender| sender.webhooks.as_ref())
+  }
+
+  /// Deliver events to webhooks in the background, until the index is
+  /// dropped
+  pub(crate) fn deliver_webhooks(&self) {
+    if let Some(webhooks) = self.webhooks() {
+      webhooks.deliver();
+    }
+  }
+
   fn begin_read(&self) -> Result<rtx::Rtx> {
     Ok(rtx::Rtx(self.database.begin_read()?))
   }
@@ -724,6 +1117,26 @@ impl Index {
     self.begin_read()?.block_hash(height)
   }
 
+  /// Consensus rules in effect for the block at `height`
+  pub fn rules(&self, height: u32) -> Rules {
+    self.schedule.rules(height)
+  }
+
+  /// Consensus rules in effect for the block which confirmed the transaction
+  /// with `txid`, or for the next block if it is unconfirmed
+  pub fn transaction_rules(&self, txid: Txid) -> Result<Rules> {
+    let height = if txid == self.genesis_block_coinbase_txid {
+      Some(0)
+    } else {
+      self.chain_source.transaction_height(txid)?
+    };
+
+    Ok(self.rules(match height {
+      Some(height) => height,
+      None => self.block_count()?,
+    }))
+  }
+
   pub fn blocks(&self, take: usize) -> Result<Vec<(u32, BlockHash)>> {
     let rtx = self.begin_read()?;
 
@@ -811,6 +1224,106 @@ impl Index {
     Ok(entries)
   }
 
+  pub fn get_conversion(&self, txid: Txid) -> Result<Option<ConversionEntry>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(TXID_TO_CONVERSION)?
+        .get(&txid.store())?
+        .map(|entry| ConversionEntry::load(entry.value())),
+    )
+  }
+
+  pub fn get_failed_conversion(&self, txid: Txid) -> Result<Option<FailedConversionEntry>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(TXID_TO_FAILED_CONVERSION)?
+        .get(&txid.store())?
+        .map(|entry| FailedConversionEntry::load(entry.value())),
+    )
+  }
+
+  pub fn get_mints_at(&self, height: u32) -> Result<Vec<MintEntry>> {
+    self
+      .database
+      .begin_read()?
+      .open_table(HEIGHT_TO_MINT)?
+      .range((height, 0)..=(height, u32::MAX))?
+      .map(|result| {
+        result
+          .map(|(_key, entry)| MintEntry::load(entry.value()))
+          .map_err(|err| err.into())
+      })
+      .collect()
+  }
+
+  pub fn mints_paginated(
+    &self,
+    page_size: usize,
+    page_index: usize,
+  ) -> Result<(Vec<MintEntry>, bool)> {
+    let mut entries = Vec::new();
+
+    for result in self
+      .database
+      .begin_read()?
+      .open_table(HEIGHT_TO_MINT)?
+      .iter()?
+      .rev()
+      .skip(page_index.saturating_mul(page_size))
+      .take(page_size.saturating_add(1))
+    {
+      let (_key, entry) = result?;
+      entries.push(MintEntry::load(entry.value()));
+    }
+
+    let more = entries.len() > page_size;
+
+    if more {
+      entries.pop();
+    }
+
+    Ok((entries, more))
+  }
+
+  pub fn conversions_paginated(
+    &self,
+    page_size: usize,
+    page_index: usize,
+  ) -> Result<(Vec<(Txid, ConversionEntry)>, bool)> {
+    let rtx = self.database.begin_read()?;
+
+    let txid_to_conversion = rtx.open_table(TXID_TO_CONVERSION)?;
+
+    let mut entries = Vec::new();
+
+    for result in rtx
+      .open_table(CONVERSION_NUMBER_TO_TXID)?
+      .iter()?
+      .rev()
+      .skip(page_index.saturating_mul(page_size))
+      .take(page_size.saturating_add(1))
+    {
+      let (_number, txid) = result?;
+      let entry = txid_to_conversion.get(txid.value())?.unwrap();
+      entries.push((
+        Txid::load(*txid.value()),
+        ConversionEntry::load(entry.value()),
+      ));
+    }
+
+    let more = entries.len() > page_size;
+
+    if more {
+      entries.pop();
+    }
+
+    Ok((entries, more))
+  }
+
   pub fn runes_paginated(
     &self,
     page_size: usize,
@@ -976,31 +1489,33 @@ impl Index {
     Ok(result)
   }
 
+  /// Bitcoin Core RPC client, for lookups which the chain source doesn't
+  /// support, which fail if the chain source is Esplora
+  fn client(&self) -> Result<&Client> {
+    self
+      .client
+      .as_ref()
+      .context("not supported when fetching chain data from Esplora")
+  }
+
   pub fn block_header(&self, hash: BlockHash) -> Result<Option<Header>> {
-    self.client.get_block_header(&hash).into_option()
+    self.chain_source.block_header(hash)
   }
 
   pub fn block_header_info(&self, hash: BlockHash) -> Result<Option<GetBlockHeaderResult>> {
-    self.client.get_block_header_info(&hash).into_option()
+    self.client()?.get_block_header_info(&hash).into_option()
   }
 
   pub fn block_stats(&self, height: u64) -> Result<Option<GetBlockStatsResult>> {
-    self.client.get_block_stats(height).into_option()
+    self.client()?.get_block_stats(height).into_option()
   }
 
   pub fn get_block_by_height(&self, height: u32) -> Result<Option<Block>> {
-    Ok(
-      self
-        .client
-        .get_block_hash(height.into())
-        .into_option()?
-        .map(|hash| self.client.get_block(&hash))
-        .transpose()?,
-    )
+    self.chain_source.block(height)
   }
 
   pub fn get_block_by_hash(&self, hash: BlockHash) -> Result<Option<Block>> {
-    self.client.get_block(&hash).into_option()
+    self.client()?.get_block(&hash).into_option()
   }
 
   pub fn get_transaction(&self, txid: Txid) -> Result<Option<Transaction>> {
@@ -1020,14 +1535,16 @@ impl Index {
     }
 
     // Go through every wallet and try to find the transaction
-    for wallet in self.client.list_wallets()? {
-      let client = self.settings.bitcoin_rpc_client(Some(wallet))?;
-      if let Ok(result) = client.get_transaction(&txid, None) {
-        return Ok(result.transaction().ok());
+    if let Some(client) = &self.client {
+      for wallet in client.list_wallets()? {
+        let client = self.settings.bitcoin_rpc_client(Some(wallet))?;
+        if let Ok(result) = client.get_transaction(&txid, None) {
+          return Ok(result.transaction().ok());
+        }
       }
     }
 
-    self.client.get_raw_transaction(&txid, None).into_option()
+    self.chain_source.raw_transaction(txid)
   }
 
   pub fn is_output_spent(&self, outpoint: OutPoint) -> Result<bool> {
@@ -1042,10 +1559,7 @@ impl Index {
             .get(&outpoint.store())?
             .is_none()
         } else {
-          self
-            .client
-            .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))?
-            .is_none()
+          self.chain_source.tx_out(outpoint, true)?.is_none()
         },
     )
   }
@@ -1060,7 +1574,7 @@ impl Index {
     }
 
     let Some(info) = self
-      .client
+      .client()?
       .get_raw_transaction_info(&outpoint.txid, None)
       .into_option()?
     else {
@@ -1149,6 +1663,56 @@ impl Index {
       .collect()
   }
 
+  pub fn get_address_rune_balances(&self, address: &Address) -> Result<(u128, u128)> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(SCRIPT_PUBKEY_TO_RUNE_BALANCE)?
+        .get(address.script_pubkey().as_bytes())?
+        .map(|balances| balances.value())
+        .unwrap_or_default(),
+    )
+  }
+
+  pub fn get_address_balances(
+    &self,
+    address: &Address,
+    outputs: Vec<OutPoint>,
+  ) -> Result<api::AddressInfo> {
+    let (tighten_balance, ease_balance) = self.get_address_rune_balances(address)?;
+
+    let supply_state = match self.block_height()? {
+      Some(height) => self.get_supply_state_at(height.n())?,
+      None => None,
+    }
+    .unwrap_or_default();
+
+    // the invariant is `A^2 + B^2 = K^2`, so a marginal unit of Tighten is
+    // worth `A / K` and a marginal unit of Ease `B / K`
+    let invariant = (supply_state.supply0 * supply_state.supply0
+      + supply_state.supply1 * supply_state.supply1)
+      .sqrt();
+
+    let invariant_balance = (tighten_balance * supply_state.supply0
+      + ease_balance * supply_state.supply1)
+      .checked_div(invariant)
+      .unwrap_or_default();
+
+    let sat_balance = self.get_sat_balances_for_outputs(&outputs)?;
+
+    let util_state = self.get_util_state()?;
+
+    Ok(api::AddressInfo {
+      outputs,
+      sat_balance,
+      tighten_balance,
+      ease_balance,
+      invariant_balance,
+      util_balance: u128::from(sat_balance) * util_state.utils_per_sat / util_state.decimals,
+    })
+  }
+
   pub(crate) fn get_sat_balances_for_outputs(&self, outputs: &Vec<OutPoint>) -> Result<u64> {
     let outpoint_to_txout = self.database.begin_read()?.open_table(OUTPOINT_TO_TXOUT)?;
 
@@ -1798,6 +2362,30 @@ mod tests {
     );
   }
 
+  /// Receive the next event concerning a rune, skipping block and chain
+  /// events
+  fn next_rune_event(receiver: &mut tokio::sync::mpsc::Receiver<Event>) -> Event {
+    loop {
+      match receiver.blocking_recv().unwrap() {
+        Event::BlockCommitted { .. }
+        | Event::ConversionChainUpdated { .. }
+        | Event::MintChainUpdated { .. }
+        | Event::UtilStateUpdated { .. } => {}
+        event => return event,
+      }
+    }
+  }
+
+  fn received_events(receiver: &mut tokio::sync::mpsc::Receiver<Event>) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    while let Ok(event) = receiver.try_recv() {
+      events.push(event);
+    }
+
+    events
+  }
+
   #[allow(clippy::cast_possible_truncation)]
   #[test]
   fn rune_event_sender_channel() {
@@ -1857,7 +2445,7 @@ mod tests {
     );
 
     assert_eq!(
-      event_receiver.blocking_recv().unwrap(),
+      next_rune_event(&mut event_receiver),
       Event::RuneMinted {
         block_height: (context.get_block_count() as u32) - 1,
         txid: txid0,
@@ -1914,10 +2502,10 @@ mod tests {
       )],
     );
 
-    event_receiver.blocking_recv().unwrap();
+    next_rune_event(&mut event_receiver);
 
     pretty_assert_eq!(
-      event_receiver.blocking_recv().unwrap(),
+      next_rune_event(&mut event_receiver),
       Event::RuneTransferred {
         block_height: (context.get_block_count() as u32) - 1,
         txid: txid1,
@@ -1927,6 +2515,7 @@ mod tests {
           txid: txid1,
           vout: 0,
         },
+        script_pubkey: context.core.tx_by_id(txid1).output[0].script_pubkey.clone(),
       }
     );
 
@@ -1986,10 +2575,10 @@ mod tests {
       )],
     );
 
-    event_receiver.blocking_recv().unwrap();
+    next_rune_event(&mut event_receiver);
 
     pretty_assert_eq!(
-      event_receiver.blocking_recv().unwrap(),
+      next_rune_event(&mut event_receiver),
       Event::RuneBurned {
         block_height: (context.get_block_count() as u32) - 1,
         txid: txid2,
@@ -2000,18 +2589,35 @@ mod tests {
   }
 
   #[test]
-  fn util_state_updates_each_block() {
-    const TIGHTEN: u128 = 0;
-    const EASE: u128 = 1;
+  fn block_chain_and_conversion_events_are_sent() {
     const COIN_VALUE: u128 = 100000000;
-    const REWARD: u128 = 50 * COIN_VALUE;
-    const UTIL_BASE_VALUE: u128 = 1_000_000_000_000;
-    const BLOCKS_PER_YEAR: u128 = 52_595;
 
-    let context = Context::builder().chain(Chain::Regtest).build();
+    let (event_sender, mut event_receiver) = tokio::sync::mpsc::channel(1024);
+    let mut context = Context::builder()
+      .chain(Chain::Regtest)
+      .event_sender(event_sender)
+      .build();
+
+    context.index.set_durability(redb::Durability::Immediate);
 
     context.mine_blocks(1);
 
+    let hashes = context.core.state().hashes.clone();
+
+    assert_eq!(
+      received_events(&mut event_receiver),
+      [
+        Event::BlockCommitted {
+          block_hash: hashes[0],
+          block_height: 0,
+        },
+        Event::BlockCommitted {
+          block_hash: hashes[1],
+          block_height: 1,
+        },
+      ]
+    );
+
     // Mints 40 TIGHTEN and 30 EASE
     let txid0 = context.core.broadcast_tx(TransactionTemplate {
       inputs: &[(1, 0, 0, Witness::new())],
@@ -2041,78 +2647,67 @@ mod tests {
 
     context.mine_blocks(1);
 
-    context.assert_runes(
+    let events = received_events(&mut event_receiver);
+
+    assert_eq!(
+      events.iter().map(Event::type_name).collect::<Vec<&str>>(),
       [
-        (
-          ID0,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(TIGHTEN),
-              spacers: 0,
-            },
-            mints: 1,
-            supply: 40 * COIN_VALUE,
-            ..default()
-          },
-        ),
-        (
-          ID1,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(EASE),
-              spacers: 0,
-            },
-            mints: 1,
-            supply: 30 * COIN_VALUE,
-            ..default()
-          },
-        ),
-      ],
-      [(
-        OutPoint {
-          txid: txid0,
-          vout: 2,
-        },
-        vec![(ID0, 40 * COIN_VALUE), (ID1, 30 * COIN_VALUE)],
-      )],
+        "rune_converted",
+        "rune_minted",
+        "rune_transferred",
+        "rune_transferred",
+        "mint_chain_updated",
+        "conversion_chain_updated",
+        "util_state_updated",
+        "block_committed",
+      ]
     );
 
-    let interest_rate2 = UTIL_BASE_VALUE * (40 - 30) / (40 + 30);
-    let interest2 = UTIL_BASE_VALUE * interest_rate2 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
-    let bonds_per_sat2 = UTIL_BASE_VALUE + interest2;
-    let utils_per_bond2 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate2;
-    let utils_per_sat2 = bonds_per_sat2 * utils_per_bond2 / UTIL_BASE_VALUE;
-
-    pretty_assert_eq!(
-      context.index.get_util_state().unwrap(),
-      api::UtilState {
-        bonds_per_sat: bonds_per_sat2,
-        utils_per_bond: utils_per_bond2,
-        utils_per_sat: utils_per_sat2,
-        interest_rate: interest_rate2,
-        decimals: UTIL_BASE_VALUE,
+    assert_eq!(
+      events[0],
+      Event::RuneConverted {
+        block_height: 2,
+        entry: context.index.get_conversion(txid0).unwrap().unwrap(),
+        txid: txid0,
       }
     );
 
-    // Convert 50 EASE to sqrt(100^2 - 10^2) TIGHTEN
-    let balance1 = 10 * COIN_VALUE;
-    let balance0 = (4 * REWARD * REWARD - 100 * COIN_VALUE * COIN_VALUE).sqrt();
+    assert!(events[4..6].iter().all(|event| matches!(
+      event,
+      Event::MintChainUpdated { outpoint, .. } | Event::ConversionChainUpdated { outpoint, .. }
+        if outpoint.txid == txid0
+    )));
+
+    let Event::UtilStateUpdated {
+      block_height,
+      supply_state,
+      util_state,
+      ..
+    } = &events[6]
+    else {
+      panic!("expected util state update");
+    };
+
+    assert_eq!(*block_height, 2);
+    assert_eq!(
+      Some(*supply_state),
+      context.index.get_supply_state_at(2).unwrap()
+    );
+    assert_eq!(*util_state, context.index.get_util_state().unwrap());
+
+    // Mints 50 TIGHTEN and tries to convert 20 TIGHTEN to at least 1000 EASE
     let txid1 = context.core.broadcast_tx(TransactionTemplate {
-      inputs: &[
-        (context.get_block_count() - 1, 1, 0, Witness::new()),
-        (context.get_block_count() - 1, 1, 1, Witness::new()),
-        (context.get_block_count() - 1, 1, 2, Witness::new()),
-      ],
+      inputs: &[(2, 1, 0, Witness::new()), (2, 1, 1, Witness::new())],
       mint: true,
       convert: true,
-      outputs: 3,
+      outputs: 2,
       op_return: Some(
         Runestone {
           edicts: vec![
             Edict {
-              id: ID1,
-              amount: balance1,
-              output: 2,
+              id: ID0,
+              amount: 30 * COIN_VALUE,
+              output: 0,
             },
             Edict {
               id: ID0,
@@ -2120,12 +2715,12 @@ mod tests {
               output: 2,
             },
             Edict {
-              id: ID0,
-              amount: 1,
-              output: 2,
+              id: ID1,
+              amount: 1000 * COIN_VALUE,
+              output: 0,
             },
           ],
-          pointer: Some(3),
+          pointer: Some(2),
         }
         .encipher(),
       ),
@@ -2134,140 +2729,1278 @@ mod tests {
 
     context.mine_blocks(1);
 
-    context.assert_runes(
-      [
-        (
-          ID0,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(TIGHTEN),
-              spacers: 0,
-            },
-            mints: 2,
-            supply: balance0,
-            ..default()
-          },
-        ),
-        (
-          ID1,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(EASE),
-              spacers: 0,
-            },
-            mints: 2,
-            supply: balance1,
-            ..default()
-          },
-        ),
-      ],
-      [(
-        OutPoint {
-          txid: txid1,
-          vout: 2,
-        },
-        vec![(ID0, balance0), (ID1, 10 * COIN_VALUE)],
-      )],
-    );
-
-    let rate3 = UTIL_BASE_VALUE * (balance0 - 10 * COIN_VALUE) / (balance0 + 10 * COIN_VALUE);
-    let interest_rate3 = (interest_rate2 + rate3) / 2;
-    let interest3 = bonds_per_sat2 * interest_rate3 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
-    let bonds_per_sat3 = bonds_per_sat2 + interest3;
-    let utils_per_bond3 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate3;
-    let utils_per_sat3 = bonds_per_sat3 * utils_per_bond3 / UTIL_BASE_VALUE;
+    let events = received_events(&mut event_receiver);
 
-    pretty_assert_eq!(
-      context.index.get_util_state().unwrap(),
-      api::UtilState {
-        bonds_per_sat: bonds_per_sat3,
-        utils_per_bond: utils_per_bond3,
-        utils_per_sat: utils_per_sat3,
-        interest_rate: interest_rate3,
-        decimals: UTIL_BASE_VALUE,
+    assert_eq!(
+      events[0],
+      Event::RuneConversionFailed {
+        block_height: 3,
+        entry: context.index.get_failed_conversion(txid1).unwrap().unwrap(),
+        supply_state: context.index.get_supply_state_at(3).unwrap().unwrap(),
+        txid: txid1,
       }
     );
 
-    context.mine_blocks(1);
+    assert!(events.iter().any(|event| matches!(
+      event,
+      Event::MintChainUpdated { outpoint, .. } if outpoint.txid == txid1
+    )));
 
-    let balance0_2 =
-      balance0 + REWARD * balance0 / (balance0 * balance0 + balance1 * balance1).sqrt();
-    let balance1_2 =
-      balance1 + REWARD * balance1 / (balance0 * balance0 + balance1 * balance1).sqrt();
-    context.assert_runes(
-      [
-        (
-          ID0,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(TIGHTEN),
-              spacers: 0,
-            },
-            mints: 3,
-            supply: balance0_2,
-            burned: balance0_2 - balance0,
-            ..default()
-          },
-        ),
-        (
-          ID1,
-          RuneEntry {
-            spaced_rune: SpacedRune {
-              rune: Rune(EASE),
-              spacers: 0,
-            },
-            mints: 3,
-            supply: balance1_2,
-            burned: balance1_2 - balance1,
-            ..default()
-          },
-        ),
-      ],
-      [(
-        OutPoint {
-          txid: txid1,
-          vout: 2,
-        },
-        vec![(ID0, balance0), (ID1, 10 * COIN_VALUE)],
-      )],
-    );
+    context.core.invalidate_tip();
+    context.mine_blocks(2);
 
-    let interest_rate4 = UTIL_BASE_VALUE * (balance0_2 - balance1_2) / (balance0_2 + balance1_2);
-    let interest4 = bonds_per_sat3 * interest_rate4 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
-    let bonds_per_sat4 = bonds_per_sat3 + interest4;
-    let utils_per_bond4 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate4;
-    let utils_per_sat4 = bonds_per_sat4 * utils_per_bond4 / UTIL_BASE_VALUE;
+    let events = received_events(&mut event_receiver);
 
-    pretty_assert_eq!(
-      context.index.get_util_state().unwrap(),
-      api::UtilState {
-        bonds_per_sat: bonds_per_sat4,
-        utils_per_bond: utils_per_bond4,
-        utils_per_sat: utils_per_sat4,
-        interest_rate: interest_rate4,
-        decimals: UTIL_BASE_VALUE,
+    assert_eq!(
+      events[0],
+      Event::ReorgRolledBack {
+        block_height: 3,
+        depth: 2,
       }
     );
 
-    let invariant = (balance0_2 * balance0_2 + balance1_2 * balance1_2).sqrt();
-    let balance0_3 = balance0_2 + REWARD * balance0_2 / invariant;
-    let balance1_3 = balance1_2 + REWARD * balance1_2 / invariant;
+    assert_eq!(
+      events.last().unwrap(),
+      &Event::BlockCommitted {
+        block_hash: context.index.block_hash(None).unwrap().unwrap(),
+        block_height: 4,
+      }
+    );
+  }
 
-    // Convert to 120 EASE and 90 TIGHTEN
-    let balance0_4 = 90 * COIN_VALUE;
-    let balance1_4 =
-      (balance0_3 * balance0_3 + balance1_3 * balance1_3 - balance0_4 * balance0_4).sqrt();
-    let txid2 = context.core.broadcast_tx(TransactionTemplate {
-      inputs: &[
-        (context.get_block_count() - 2, 1, 0, Witness::new()),
-        (context.get_block_count() - 2, 1, 1, Witness::new()),
-        (context.get_block_count() - 2, 1, 2, Witness::new()),
-      ],
-      mint: true,
-      convert: true,
-      outputs: 3,
-      op_return: Some(
-        Runestone {
+  #[test]
+  fn supply_and_util_state_are_recorded_at_each_height() {
+    const COIN_VALUE: u128 = 100000000;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    let initial = context.index.get_util_state().unwrap();
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 40 * COIN_VALUE,
+              output: 2,
+            },
+            Edict {
+              id: ID1,
+              amount: 30 * COIN_VALUE,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let after_mint = context.index.get_util_state().unwrap();
+
+    context.mine_blocks(1);
+
+    // nothing is recorded before the first rune height
+    assert_eq!(context.index.get_supply_state_at(1).unwrap(), None);
+    assert_eq!(context.index.get_util_state_at(1).unwrap(), None);
+    assert_eq!(context.index.get_rate_history_at(1).unwrap(), None);
+
+    assert_ne!(initial, after_mint);
+
+    assert_eq!(
+      context.index.get_supply_state_at(2).unwrap(),
+      Some(api::SupplyState {
+        supply0: 40 * COIN_VALUE,
+        supply1: 30 * COIN_VALUE,
+        burned0: 0,
+        burned1: 0,
+      }),
+    );
+    assert_eq!(
+      context.index.get_util_state_at(2).unwrap(),
+      Some(after_mint)
+    );
+
+    assert_eq!(
+      context.index.get_util_state_at(3).unwrap(),
+      Some(context.index.get_util_state().unwrap()),
+    );
+    assert_ne!(after_mint, context.index.get_util_state().unwrap());
+
+    assert_eq!(
+      context.index.get_rate_history_at(2).unwrap().unwrap(),
+      api::RateHistory {
+        median_interest_rate: after_mint.interest_rate,
+        history: vec![after_mint.interest_rate],
+      },
+    );
+    assert_eq!(
+      context.index.get_rate_history_at(3).unwrap().unwrap(),
+      context.index.get_rate_history().unwrap(),
+    );
+
+    assert_eq!(context.index.get_supply_state_at(4).unwrap(), None);
+    assert_eq!(context.index.get_util_state_at(4).unwrap(), None);
+    assert_eq!(context.index.get_rate_history_at(4).unwrap(), None);
+  }
+
+  #[test]
+  fn mints_are_recorded_by_height() {
+    const COIN_VALUE: u128 = 100000000;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    let txid = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(2);
+
+    let mint = MintEntry {
+      txid,
+      height: 2,
+      amount0: 50 * COIN_VALUE,
+      amount1: 0,
+      recycled0: Some(0),
+      recycled1: Some(0),
+      recipient: Some(1),
+    };
+
+    assert_eq!(context.index.get_mints_at(1).unwrap(), []);
+    assert_eq!(context.index.get_mints_at(2).unwrap(), [mint]);
+    assert_eq!(context.index.get_mints_at(3).unwrap(), []);
+
+    assert_eq!(
+      context.index.mints_paginated(10, 0).unwrap(),
+      (vec![mint], false)
+    );
+    assert_eq!(
+      context.index.mints_paginated(10, 1).unwrap(),
+      (Vec::new(), false)
+    );
+  }
+
+  #[test]
+  fn simulate_does_not_take_the_write_lock_or_modify_the_index() {
+    const COIN_VALUE: u128 = 100000000;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 40 * COIN_VALUE,
+              output: 2,
+            },
+            Edict {
+              id: ID1,
+              amount: 30 * COIN_VALUE,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    let mempool = context.core.mempool();
+
+    let wtx = context.index.begin_write().unwrap();
+
+    let simulation = context.index.simulate(mempool.clone()).unwrap();
+
+    assert_eq!(context.index.simulate(mempool).unwrap(), simulation);
+
+    drop(wtx);
+
+    assert_eq!(context.index.get_supply_state_at(2).unwrap(), None);
+
+    context.mine_blocks(1);
+
+    assert_eq!(
+      simulation,
+      [context.index.get_supply_state_at(2).unwrap().unwrap()]
+    );
+  }
+
+  #[test]
+  fn verify_finds_no_discrepancies_in_consistent_index() {
+    const COIN_VALUE: u128 = 100000000;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    assert_eq!(context.index.verify().unwrap(), []);
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 40 * COIN_VALUE,
+              output: 2,
+            },
+            Edict {
+              id: ID1,
+              amount: 30 * COIN_VALUE,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(2);
+
+    assert_ne!(
+      context
+        .index
+        .get_last_outpoint_txout_for_state_change(StateChange::Convert)
+        .unwrap()
+        .0,
+      OutPoint::null()
+    );
+
+    assert_eq!(context.index.verify().unwrap(), []);
+  }
+
+  #[test]
+  fn verify_reports_discrepancies() {
+    const COIN_VALUE: u128 = 100000000;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    let txid = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(2);
+
+    let mint_outpoint = OutPoint { txid, vout: 0 };
+
+    let (last_outpoint, mint_value) = context
+      .index
+      .get_last_outpoint_txout_for_state_change(StateChange::Mint)
+      .unwrap();
+
+    assert_eq!(last_outpoint, mint_outpoint);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 1, 0, Witness::new())],
+      outputs: 1,
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    {
+      let wtx = context.index.database.begin_write().unwrap();
+      wtx
+        .open_table(OUTPOINT_TO_RUNE_BALANCES)
+        .unwrap()
+        .remove(&OutPoint { txid, vout: 1 }.store())
+        .unwrap();
+      wtx
+        .open_table(HEIGHT_TO_BLOCK_HEADER)
+        .unwrap()
+        .remove(1)
+        .unwrap();
+      wtx
+        .open_table(STATE_CHANGE_TO_LAST_OUTPOINT)
+        .unwrap()
+        .insert(&StateChange::Mint.key(), &mint_outpoint.store())
+        .unwrap();
+      wtx
+        .open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)
+        .unwrap()
+        .insert(&StateChange::Mint.key(), mint_value)
+        .unwrap();
+      wtx.commit().unwrap();
+    }
+
+    assert_eq!(
+      context.index.verify().unwrap(),
+      [
+        Discrepancy::HeaderGap {
+          expected: 1,
+          actual: 2,
+        },
+        Discrepancy::Supply {
+          rune: ID0,
+          supply: 150 * COIN_VALUE,
+          balances: 0,
+          burned: 100 * COIN_VALUE,
+        },
+        Discrepancy::ChainOutputSpent {
+          state_change: StateChange::Mint,
+          outpoint: mint_outpoint,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn verify_checks_chain_outputs_against_utxo_set() {
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let (outpoint, value) = context
+      .index
+      .get_last_outpoint_txout_for_state_change(StateChange::Mint)
+      .unwrap();
+
+    {
+      let wtx = context.index.database.begin_write().unwrap();
+      wtx
+        .open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)
+        .unwrap()
+        .insert(&StateChange::Mint.key(), value + 1)
+        .unwrap();
+      wtx.commit().unwrap();
+    }
+
+    assert_eq!(
+      context.index.verify().unwrap(),
+      [Discrepancy::ChainOutputValue {
+        state_change: StateChange::Mint,
+        outpoint,
+        expected: value + 1,
+        actual: value,
+      }]
+    );
+  }
+
+  #[test]
+  fn verify_ignores_spends_in_blocks_which_are_not_indexed() {
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    let txid = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 1, 0, Witness::new())],
+      outputs: 1,
+      ..default()
+    });
+
+    context.core.mine_blocks(1);
+
+    assert_eq!(context.index.block_count().unwrap(), 3);
+
+    assert_eq!(context.index.verify().unwrap(), []);
+
+    assert_eq!(context.index.block_count().unwrap(), 3);
+
+    context.index.update().unwrap();
+
+    assert_ne!(
+      context
+        .index
+        .get_last_outpoint_txout_for_state_change(StateChange::Mint)
+        .unwrap()
+        .0,
+      OutPoint { txid, vout: 0 },
+    );
+
+    assert_eq!(context.index.verify().unwrap(), []);
+  }
+
+  #[test]
+  fn import_checks_snapshot() {
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(2);
+
+    let snapshot = context.index.export(2).unwrap();
+
+    assert_eq!(
+      context.index.export(1).unwrap_err().to_string(),
+      "index is at height 2, not 1, export its tip or rebuild it with `--height-limit 2` to export height 1",
+    );
+
+    assert_eq!(
+      context
+        .index
+        .import(Snapshot {
+          block_hash: BlockHash::all_zeros(),
+          ..context.index.export(2).unwrap()
+        })
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "snapshot block {} at height 2 is not in the active chain, which has block {}",
+        BlockHash::all_zeros(),
+        snapshot.block_hash,
+      ),
+    );
+
+    assert_eq!(
+      context
+        .index
+        .import(Snapshot {
+          chain: Chain::Mainnet,
+          ..context.index.export(2).unwrap()
+        })
+        .unwrap_err()
+        .to_string(),
+      "snapshot is for mainnet, not regtest",
+    );
+
+    let state_commitment = snapshot.state_commitment.unwrap();
+
+    let mut util_entry = snapshot.util_entry.clone();
+    util_entry.2 += 1;
+
+    assert_regex_match!(
+      context
+        .index
+        .import(Snapshot {
+          util_entry,
+          ..context.index.export(2).unwrap()
+        })
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "snapshot state commitment {state_commitment} does not match its contents, which commit to [[:xdigit:]]{{64}}",
+      ),
+    );
+
+    assert_eq!(
+      context
+        .index
+        .import(Snapshot {
+          balance_changes: None,
+          ..context.index.export(2).unwrap()
+        })
+        .unwrap_err()
+        .to_string(),
+      "snapshot state commitment cannot be checked without the balance changes at its height",
+    );
+
+    assert_eq!(
+      Context::builder()
+        .chain(Chain::Regtest)
+        .arg("--index-transactions")
+        .build()
+        .index
+        .import(context.index.export(2).unwrap())
+        .unwrap_err()
+        .to_string(),
+      "snapshots cannot be imported into an index with a transaction index",
+    );
+
+    assert_eq!(
+      context.index.import(snapshot).unwrap_err().to_string(),
+      "snapshots can only be imported into an empty index",
+    );
+  }
+
+  #[test]
+  fn older_schemas_are_migrated() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--index-addresses")
+      .build();
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let address = Address::from_script(
+      &ScriptBuf::new_v0_p2wpkh(&bitcoin::WPubkeyHash::all_zeros()),
+      Network::Regtest,
+    )
+    .unwrap();
+
+    let balances = context.index.get_address_rune_balances(&address).unwrap();
+    let supply_state = context.index.get_supply_state_at(2).unwrap();
+    let util_state = context.index.get_util_state_at(2).unwrap();
+
+    assert_eq!(balances.0, 50 * 100000000);
+
+    {
+      let mut wtx = context.index.database.begin_write().unwrap();
+
+      wtx.delete_table(HEIGHT_TO_RATE).unwrap();
+      wtx.delete_table(HEIGHT_TO_SUPPLY_STATE).unwrap();
+      wtx.delete_table(HEIGHT_TO_UTIL_STATE).unwrap();
+      wtx.delete_table(CONVERSION_NUMBER_TO_TXID).unwrap();
+      wtx.delete_table(TXID_TO_CONVERSION).unwrap();
+      wtx.delete_table(TXID_TO_FAILED_CONVERSION).unwrap();
+      wtx.delete_table(HEIGHT_TO_MINT).unwrap();
+      wtx.delete_table(UNCLAIMED_REWARD).unwrap();
+      wtx.delete_table(SCRIPT_PUBKEY_TO_RUNE_BALANCE).unwrap();
+      wtx.delete_table(HEIGHT_TO_STATE_COMMITMENT).unwrap();
+      wtx.delete_table(HEIGHT_TO_BALANCE_CHANGES).unwrap();
+
+      Index::set_statistic(
+        &mut wtx.open_table(STATISTIC_TO_COUNT).unwrap(),
+        Statistic::Schema,
+        26,
+      )
+      .unwrap();
+
+      wtx.set_durability(redb::Durability::Immediate);
+      wtx.commit().unwrap();
+    }
+
+    let Context {
+      index,
+      core,
+      tempdir,
+    } = context;
+
+    let settings = index.settings.clone();
+
+    drop(index);
+
+    let (from, migrations) = Index::pending_migrations(&settings).unwrap();
+
+    assert_eq!(from, 26);
+    assert_eq!(
+      migrations.iter().map(|step| step.to).collect::<Vec<u64>>(),
+      (27..=SCHEMA_VERSION).collect::<Vec<u64>>(),
+    );
+
+    let context = Context {
+      index: Index::open(&settings).unwrap(),
+      core,
+      tempdir,
+    };
+
+    assert_eq!(
+      context.index.get_address_rune_balances(&address).unwrap(),
+      balances
+    );
+    assert_eq!(context.index.get_supply_state_at(2).unwrap(), supply_state);
+    assert_eq!(context.index.get_util_state_at(2).unwrap(), util_state);
+
+    context.mine_blocks(1);
+
+    assert!(context.index.get_supply_state_at(3).unwrap().is_some());
+    assert_eq!(context.index.get_latest_state_commitment().unwrap(), None);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 1, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let mints = context.index.get_mints_at(4).unwrap();
+    assert_eq!(mints.len(), 1);
+    assert_eq!(mints[0].recycled0, None);
+    assert_eq!(mints[0].reward0(), None);
+    assert_eq!(context.index.verify().unwrap(), []);
+  }
+
+  #[test]
+  fn initial_sync_reads_blocks_from_blk_files() {
+    let bitcoin_data_dir = tempfile::TempDir::new().unwrap();
+
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--blk-files")
+      .arg("--bitcoin-data-dir")
+      .arg(bitcoin_data_dir.path())
+      .build();
+
+    context.mine_blocks_with_update(1, false);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks_with_update(110, false);
+
+    let blocks = {
+      let state = context.core.state();
+      state
+        .hashes
+        .iter()
+        .map(|hash| state.blocks[hash].clone())
+        .collect::<Vec<Block>>()
+    };
+
+    blk_files::tests::write_blk_files(
+      &bitcoin_data_dir.path().join("regtest/blocks"),
+      &blocks,
+      [1, 2, 3, 4, 5, 6, 7, 8],
+      32,
+    );
+
+    // blocks below the tip distance can only be read from blk files
+    let tip = blocks.len() - 1 - usize::try_from(updater::BLK_FILES_TIP_DISTANCE).unwrap();
+
+    for block in &blocks[..=tip] {
+      context.core.state().blocks.remove(&block.block_hash());
+    }
+
+    context.index.update().unwrap();
+
+    assert_eq!(context.get_block_count(), blocks.len());
+
+    for block in &blocks[..=tip] {
+      context
+        .core
+        .state()
+        .blocks
+        .insert(block.block_hash(), block.clone());
+    }
+
+    let rpc_index = context.open_index::<&str, _>([]).unwrap();
+
+    rpc_index.update().unwrap();
+
+    assert!(context
+      .index
+      .get_latest_state_commitment()
+      .unwrap()
+      .is_some());
+
+    assert_eq!(
+      context.index.get_latest_state_commitment().unwrap(),
+      rpc_index.get_latest_state_commitment().unwrap(),
+    );
+
+    assert_eq!(context.index.runes().unwrap(), rpc_index.runes().unwrap());
+  }
+
+  #[test]
+  fn index_can_be_built_from_esplora() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--index-addresses")
+      .build();
+
+    context.mine_blocks(1);
+
+    let txid = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 1, 0, Witness::new()), (2, 1, 1, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(2);
+
+    let stand_in = chain_source::esplora::tests::StandIn::spawn(
+      chain_source::esplora::tests::blocks(&context.core),
+    );
+
+    let esplora_index = context
+      .open_index(["--index-addresses", "--esplora-url", &stand_in.url])
+      .unwrap();
+
+    esplora_index.update().unwrap();
+
+    assert_eq!(
+      esplora_index.block_count().unwrap(),
+      context.index.block_count().unwrap()
+    );
+
+    assert!(context
+      .index
+      .get_latest_state_commitment()
+      .unwrap()
+      .is_some());
+
+    assert_eq!(
+      esplora_index.get_latest_state_commitment().unwrap(),
+      context.index.get_latest_state_commitment().unwrap(),
+    );
+
+    assert_eq!(
+      esplora_index.get_rune_balances().unwrap(),
+      context.index.get_rune_balances().unwrap()
+    );
+
+    let address = Address::from_script(
+      &ScriptBuf::new_v0_p2wpkh(&bitcoin::WPubkeyHash::all_zeros()),
+      Network::Regtest,
+    )
+    .unwrap();
+
+    assert_eq!(
+      esplora_index.get_address_rune_balances(&address).unwrap(),
+      context.index.get_address_rune_balances(&address).unwrap()
+    );
+
+    assert_eq!(
+      esplora_index.get_transaction(txid).unwrap(),
+      context.index.get_transaction(txid).unwrap()
+    );
+
+    assert_eq!(esplora_index.verify().unwrap(), []);
+
+    assert_eq!(
+      esplora_index.block_stats(1).unwrap_err().to_string(),
+      "not supported when fetching chain data from Esplora"
+    );
+  }
+
+  #[test]
+  fn only_headers_are_fetched_below_first_rune_height() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--first-rune-height")
+      .arg("2010")
+      .build();
+
+    context.mine_blocks_with_update(2005, false);
+
+    context.index.update().unwrap();
+
+    assert_eq!(context.index.block_count().unwrap(), 2006);
+
+    let hashes = context.core.state().hashes.clone();
+
+    for (height, hash) in hashes.iter().enumerate() {
+      assert_eq!(
+        context
+          .index
+          .block_hash(Some(u32::try_from(height).unwrap()))
+          .unwrap(),
+        Some(*hash),
+      );
+    }
+
+    assert!(context
+      .index
+      .database
+      .begin_read()
+      .unwrap()
+      .open_table(OUTPOINT_TO_TXOUT)
+      .unwrap()
+      .is_empty()
+      .unwrap());
+  }
+
+  #[test]
+  fn full_blocks_are_fetched_below_first_rune_height_when_indexing_addresses() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--first-rune-height")
+      .arg("10")
+      .arg("--index-addresses")
+      .build();
+
+    context.mine_blocks(3);
+
+    let coinbase = context.core.tx(1, 0);
+
+    let address =
+      Address::from_script(&coinbase.output[0].script_pubkey, Network::Regtest).unwrap();
+
+    assert!(context
+      .index
+      .get_address_info(&address)
+      .unwrap()
+      .contains(&OutPoint {
+        txid: coinbase.txid(),
+        vout: 0,
+      }));
+  }
+
+  #[test]
+  fn full_blocks_are_fetched_below_first_rune_height_when_requested() {
+    let mut context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--first-rune-height")
+      .arg("10")
+      .build();
+
+    context.mine_blocks_with_update(20, false);
+
+    context.core.prune(8);
+
+    context.index.fetch_full_blocks();
+
+    assert_regex_match!(
+      context.index.update().unwrap_err().to_string(),
+      "bitcoind has pruned blocks .* through 7, which are needed to index runes",
+    );
+  }
+
+  #[test]
+  fn pruned_blocks_below_first_rune_height_are_synced_as_headers() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--first-rune-height")
+      .arg("10")
+      .build();
+
+    context.mine_blocks_with_update(20, false);
+
+    context.core.prune(8);
+
+    context.index.update().unwrap();
+
+    assert_eq!(context.index.block_count().unwrap(), 21);
+
+    let status = context.index.status().unwrap();
+
+    assert_eq!(status.prune_height, Some(8));
+    assert_eq!(
+      status.unavailable_features,
+      ["blocks below height 8", "address index"]
+    );
+
+    assert!(context.index.is_pruned(7));
+    assert!(!context.index.is_pruned(8));
+  }
+
+  #[test]
+  fn pruned_blocks_above_first_rune_height_are_an_error() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--first-rune-height")
+      .arg("2")
+      .build();
+
+    context.mine_blocks_with_update(10, false);
+
+    context.core.prune(5);
+
+    assert_eq!(
+      context.index.update().unwrap_err().to_string(),
+      "bitcoind has pruned blocks 2 through 4, which are needed to index runes",
+    );
+  }
+
+  #[test]
+  fn address_index_needs_unpruned_blocks_above_index_height() {
+    let context = Context::builder()
+      .chain(Chain::Regtest)
+      .arg("--index-addresses")
+      .build();
+
+    context.mine_blocks(3);
+
+    context.mine_blocks_with_update(3, false);
+
+    context.core.prune(4);
+
+    context.index.update().unwrap();
+
+    assert_eq!(context.index.block_count().unwrap(), 7);
+
+    assert_eq!(
+      context.index.status().unwrap().unavailable_features,
+      ["blocks below height 4"]
+    );
+
+    let index = context.open_index(["--index-addresses"]).unwrap();
+
+    assert_eq!(
+      index.update().unwrap_err().to_string(),
+      "bitcoind has pruned blocks 0 through 3, which are needed to index addresses",
+    );
+  }
+
+  #[test]
+  fn unpruned_nodes_have_no_unavailable_features() {
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    let status = context.index.status().unwrap();
+
+    assert_eq!(status.prune_height, None);
+    assert!(status.unavailable_features.is_empty());
+  }
+
+  #[test]
+  fn state_commitments_are_recorded_per_height() {
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(2);
+
+    assert_eq!(context.index.get_state_commitment_at(1).unwrap(), None);
+
+    let commitment2 = context.index.get_state_commitment_at(2).unwrap().unwrap();
+    let commitment3 = context.index.get_state_commitment_at(3).unwrap().unwrap();
+
+    assert_ne!(commitment2, commitment3);
+
+    assert_eq!(
+      context.index.get_latest_state_commitment().unwrap(),
+      Some((3, commitment3))
+    );
+
+    assert_eq!(
+      context.index.status().unwrap().state_commitment,
+      Some(commitment3)
+    );
+  }
+
+  #[test]
+  fn util_state_updates_each_block() {
+    const TIGHTEN: u128 = 0;
+    const EASE: u128 = 1;
+    const COIN_VALUE: u128 = 100000000;
+    const REWARD: u128 = 50 * COIN_VALUE;
+    const UTIL_BASE_VALUE: u128 = 1_000_000_000_000;
+    const BLOCKS_PER_YEAR: u128 = 52_595;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    // Mints 40 TIGHTEN and 30 EASE
+    let txid0 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 40 * COIN_VALUE,
+              output: 2,
+            },
+            Edict {
+              id: ID1,
+              amount: 30 * COIN_VALUE,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    context.assert_runes(
+      [
+        (
+          ID0,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(TIGHTEN),
+              spacers: 0,
+            },
+            mints: 1,
+            supply: 40 * COIN_VALUE,
+            ..default()
+          },
+        ),
+        (
+          ID1,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(EASE),
+              spacers: 0,
+            },
+            mints: 1,
+            supply: 30 * COIN_VALUE,
+            ..default()
+          },
+        ),
+      ],
+      [(
+        OutPoint {
+          txid: txid0,
+          vout: 2,
+        },
+        vec![(ID0, 40 * COIN_VALUE), (ID1, 30 * COIN_VALUE)],
+      )],
+    );
+
+    let interest_rate2 = UTIL_BASE_VALUE * (40 - 30) / (40 + 30);
+    let interest2 = UTIL_BASE_VALUE * interest_rate2 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
+    let bonds_per_sat2 = UTIL_BASE_VALUE + interest2;
+    let utils_per_bond2 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate2;
+    let utils_per_sat2 = bonds_per_sat2 * utils_per_bond2 / UTIL_BASE_VALUE;
+
+    pretty_assert_eq!(
+      context.index.get_util_state().unwrap(),
+      api::UtilState {
+        bonds_per_sat: bonds_per_sat2,
+        utils_per_bond: utils_per_bond2,
+        utils_per_sat: utils_per_sat2,
+        interest_rate: interest_rate2,
+        decimals: UTIL_BASE_VALUE,
+      }
+    );
+
+    // Convert 50 EASE to sqrt(100^2 - 10^2) TIGHTEN
+    let balance1 = 10 * COIN_VALUE;
+    let balance0 = (4 * REWARD * REWARD - 100 * COIN_VALUE * COIN_VALUE).sqrt();
+    let txid1 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[
+        (context.get_block_count() - 1, 1, 0, Witness::new()),
+        (context.get_block_count() - 1, 1, 1, Witness::new()),
+        (context.get_block_count() - 1, 1, 2, Witness::new()),
+      ],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID1,
+              amount: balance1,
+              output: 2,
+            },
+            Edict {
+              id: ID0,
+              amount: 0,
+              output: 2,
+            },
+            Edict {
+              id: ID0,
+              amount: 1,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    context.assert_runes(
+      [
+        (
+          ID0,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(TIGHTEN),
+              spacers: 0,
+            },
+            mints: 2,
+            supply: balance0,
+            ..default()
+          },
+        ),
+        (
+          ID1,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(EASE),
+              spacers: 0,
+            },
+            mints: 2,
+            supply: balance1,
+            ..default()
+          },
+        ),
+      ],
+      [(
+        OutPoint {
+          txid: txid1,
+          vout: 2,
+        },
+        vec![(ID0, balance0), (ID1, 10 * COIN_VALUE)],
+      )],
+    );
+
+    let rate3 = UTIL_BASE_VALUE * (balance0 - 10 * COIN_VALUE) / (balance0 + 10 * COIN_VALUE);
+    let interest_rate3 = (interest_rate2 + rate3) / 2;
+    let interest3 = bonds_per_sat2 * interest_rate3 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
+    let bonds_per_sat3 = bonds_per_sat2 + interest3;
+    let utils_per_bond3 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate3;
+    let utils_per_sat3 = bonds_per_sat3 * utils_per_bond3 / UTIL_BASE_VALUE;
+
+    pretty_assert_eq!(
+      context.index.get_util_state().unwrap(),
+      api::UtilState {
+        bonds_per_sat: bonds_per_sat3,
+        utils_per_bond: utils_per_bond3,
+        utils_per_sat: utils_per_sat3,
+        interest_rate: interest_rate3,
+        decimals: UTIL_BASE_VALUE,
+      }
+    );
+
+    context.mine_blocks(1);
+
+    let balance0_2 =
+      balance0 + REWARD * balance0 / (balance0 * balance0 + balance1 * balance1).sqrt();
+    let balance1_2 =
+      balance1 + REWARD * balance1 / (balance0 * balance0 + balance1 * balance1).sqrt();
+    context.assert_runes(
+      [
+        (
+          ID0,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(TIGHTEN),
+              spacers: 0,
+            },
+            mints: 3,
+            supply: balance0_2,
+            burned: balance0_2 - balance0,
+            ..default()
+          },
+        ),
+        (
+          ID1,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(EASE),
+              spacers: 0,
+            },
+            mints: 3,
+            supply: balance1_2,
+            burned: balance1_2 - balance1,
+            ..default()
+          },
+        ),
+      ],
+      [(
+        OutPoint {
+          txid: txid1,
+          vout: 2,
+        },
+        vec![(ID0, balance0), (ID1, 10 * COIN_VALUE)],
+      )],
+    );
+
+    let interest_rate4 = UTIL_BASE_VALUE * (balance0_2 - balance1_2) / (balance0_2 + balance1_2);
+    let interest4 = bonds_per_sat3 * interest_rate4 / UTIL_BASE_VALUE / BLOCKS_PER_YEAR;
+    let bonds_per_sat4 = bonds_per_sat3 + interest4;
+    let utils_per_bond4 = UTIL_BASE_VALUE * UTIL_BASE_VALUE / interest_rate4;
+    let utils_per_sat4 = bonds_per_sat4 * utils_per_bond4 / UTIL_BASE_VALUE;
+
+    pretty_assert_eq!(
+      context.index.get_util_state().unwrap(),
+      api::UtilState {
+        bonds_per_sat: bonds_per_sat4,
+        utils_per_bond: utils_per_bond4,
+        utils_per_sat: utils_per_sat4,
+        interest_rate: interest_rate4,
+        decimals: UTIL_BASE_VALUE,
+      }
+    );
+
+    let invariant = (balance0_2 * balance0_2 + balance1_2 * balance1_2).sqrt();
+    let balance0_3 = balance0_2 + REWARD * balance0_2 / invariant;
+    let balance1_3 = balance1_2 + REWARD * balance1_2 / invariant;
+
+    // Convert to 120 EASE and 90 TIGHTEN
+    let balance0_4 = 90 * COIN_VALUE;
+    let balance1_4 =
+      (balance0_3 * balance0_3 + balance1_3 * balance1_3 - balance0_4 * balance0_4).sqrt();
+    let txid2 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[
+        (context.get_block_count() - 2, 1, 0, Witness::new()),
+        (context.get_block_count() - 2, 1, 1, Witness::new()),
+        (context.get_block_count() - 2, 1, 2, Witness::new()),
+      ],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
           edicts: vec![
             Edict {
               id: ID0,
@@ -2347,4 +4080,122 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn conversions_are_recorded_by_txid() {
+    const COIN_VALUE: u128 = 100000000;
+    const REWARD: u128 = 50 * COIN_VALUE;
+
+    let context = Context::builder().chain(Chain::Regtest).build();
+
+    context.mine_blocks(1);
+
+    // Mints 40 TIGHTEN and 30 EASE
+    let txid0 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID0,
+              amount: 40 * COIN_VALUE,
+              output: 2,
+            },
+            Edict {
+              id: ID1,
+              amount: 30 * COIN_VALUE,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    // Convert 50 EASE to sqrt(100^2 - 10^2) TIGHTEN
+    let balance1 = 10 * COIN_VALUE;
+    let balance0 = (4 * REWARD * REWARD - 100 * COIN_VALUE * COIN_VALUE).sqrt();
+    let txid1 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[
+        (2, 1, 0, Witness::new()),
+        (2, 1, 1, Witness::new()),
+        (2, 1, 2, Witness::new()),
+      ],
+      mint: true,
+      convert: true,
+      outputs: 3,
+      op_return: Some(
+        Runestone {
+          edicts: vec![
+            Edict {
+              id: ID1,
+              amount: balance1,
+              output: 2,
+            },
+            Edict {
+              id: ID0,
+              amount: 0,
+              output: 2,
+            },
+            Edict {
+              id: ID0,
+              amount: 1,
+              output: 2,
+            },
+          ],
+          pointer: Some(3),
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let conversion = context.index.get_conversion(txid1).unwrap().unwrap();
+
+    assert_eq!(conversion.number, 1);
+    assert_eq!(conversion.height, 3);
+    assert_eq!(conversion.direction, Direction::EaseToTighten);
+    assert!(conversion.exact_input);
+    assert_eq!(conversion.supply_after.supply0, balance0);
+    assert_eq!(conversion.supply_after.supply1, balance1);
+    assert_eq!(
+      conversion.supply_before.supply0 + conversion.output,
+      conversion.supply_after.supply0
+    );
+    assert_eq!(
+      conversion.supply_before.supply1 - conversion.input,
+      conversion.supply_after.supply1
+    );
+    assert_eq!(conversion.residual, conversion.output - 1);
+
+    let mint_conversion = context.index.get_conversion(txid0).unwrap().unwrap();
+
+    assert_eq!(mint_conversion.number, 0);
+    assert_eq!(mint_conversion.height, 2);
+    assert_eq!(mint_conversion.direction, Direction::TightenToEase);
+    assert_eq!(mint_conversion.supply_after.supply0, 40 * COIN_VALUE);
+    assert_eq!(mint_conversion.supply_after.supply1, 30 * COIN_VALUE);
+
+    assert_eq!(
+      context.index.conversions_paginated(10, 0).unwrap(),
+      (vec![(txid1, conversion), (txid0, mint_conversion)], false)
+    );
+    assert_eq!(
+      context.index.conversions_paginated(1, 1).unwrap(),
+      (vec![(txid0, mint_conversion)], false)
+    );
+    assert_eq!(
+      context.index.conversions_paginated(1, 0).unwrap(),
+      (vec![(txid1, conversion)], true)
+    );
+  }
 }
@@ -0,0 +1,361 @@
+use {
+  super::*,
+  std::{
+    fs::File,
+    io::{Seek, SeekFrom},
+  },
+};
+
+mod block_index;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BlockLocation {
+  file: u32,
+  offset: u64,
+  prev_blockhash: BlockHash,
+}
+
+/// Blocks stored in bitcoind's `blk*.dat` files.
+///
+/// Blocks are located using the entries in bitcoind's LevelDB block index,
+/// and heights are recovered by following previous block hashes back from a
+/// block in the active chain.
+pub(crate) struct BlkFiles {
+  blocks: HashMap<BlockHash, BlockLocation>,
+  dir: PathBuf,
+  key: [u8; 8],
+  magic: [u8; 4],
+}
+
+impl BlkFiles {
+  const HEADER_SIZE: u32 = 80;
+  const RECORD_HEADER_SIZE: u32 = 8;
+
+  pub(crate) fn open(dir: &Path, network: Network) -> Result<Self> {
+    let key = match fs::read(dir.join("xor.dat")) {
+      Ok(key) => key
+        .try_into()
+        .map_err(|key: Vec<u8>| anyhow!("xor.dat is {} bytes, expected 8", key.len()))?,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => [0; 8],
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("failed to read {}", dir.join("xor.dat").display()))
+      }
+    };
+
+    let index = dir.join("index");
+
+    let blocks = block_index::read(&index)
+      .with_context(|| format!("failed to read block index in {}", index.display()))?
+      .into_iter()
+      .map(|entry| {
+        (
+          entry.hash,
+          BlockLocation {
+            file: entry.file,
+            offset: entry.offset,
+            prev_blockhash: entry.prev_blockhash,
+          },
+        )
+      })
+      .collect();
+
+    Ok(Self {
+      blocks,
+      dir: dir.into(),
+      key,
+      magic: network.magic().to_bytes(),
+    })
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.blocks.len()
+  }
+
+  /// Hashes of the blocks from genesis to `tip`, indexed by height, or `None`
+  /// if any of them are missing from the blk files
+  pub(crate) fn chain(&self, tip: BlockHash) -> Option<Vec<BlockHash>> {
+    let mut chain = vec![tip];
+
+    loop {
+      let location = self.blocks.get(chain.last().unwrap())?;
+
+      if location.prev_blockhash == BlockHash::all_zeros() {
+        break;
+      }
+
+      chain.push(location.prev_blockhash);
+    }
+
+    chain.reverse();
+
+    Some(chain)
+  }
+
+  pub(crate) fn header(&self, hash: BlockHash) -> Result<Option<Header>> {
+    let Some(location) = self.blocks.get(&hash) else {
+      return Ok(None);
+    };
+
+    let buffer = self.read(location.file, location.offset, Self::HEADER_SIZE)?;
+
+    Ok(Some(consensus::deserialize(&buffer)?))
+  }
+
+  pub(crate) fn block(&self, hash: BlockHash) -> Result<Option<Block>> {
+    let Some(location) = self.blocks.get(&hash) else {
+      return Ok(None);
+    };
+
+    let record_header = self.read(
+      location.file,
+      location
+        .offset
+        .checked_sub(Self::RECORD_HEADER_SIZE.into())
+        .with_context(|| format!("block {hash} has no record header"))?,
+      Self::RECORD_HEADER_SIZE,
+    )?;
+
+    ensure!(
+      record_header[..4] == self.magic,
+      "block {hash} in {} does not begin with the network magic",
+      self.path(location.file).display(),
+    );
+
+    let size = u32::from_le_bytes(record_header[4..].try_into().unwrap());
+
+    let block: Block = consensus::deserialize(&self.read(location.file, location.offset, size)?)?;
+
+    ensure!(
+      block.block_hash() == hash,
+      "block {hash} in {} has hash {}",
+      self.path(location.file).display(),
+      block.block_hash(),
+    );
+
+    Ok(Some(block))
+  }
+
+  fn path(&self, file: u32) -> PathBuf {
+    self.dir.join(format!("blk{file:05}.dat"))
+  }
+
+  fn read(&self, file: u32, offset: u64, size: u32) -> Result<Vec<u8>> {
+    let mut reader = File::open(self.path(file))?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0; size.into_usize()];
+    reader.read_exact(&mut buffer)?;
+    self.unmask(offset, &mut buffer);
+    Ok(buffer)
+  }
+
+  fn unmask(&self, offset: u64, buffer: &mut [u8]) {
+    if self.key == [0; 8] {
+      return;
+    }
+
+    for (i, byte) in buffer.iter_mut().enumerate() {
+      *byte ^= self.key[usize::try_from((offset + i as u64) % 8).unwrap()];
+    }
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use {super::*, tempfile::TempDir};
+
+  /// Write blocks to blk files in `dir` the way bitcoind does, masked with
+  /// `key`, starting a new file after every `per_file` blocks, and padding
+  /// each file with zeros, and write their locations to a block index in
+  /// `dir/index`, with the first half in a table and the rest in a log
+  pub(crate) fn write_blk_files(dir: &Path, blocks: &[Block], key: [u8; 8], per_file: usize) {
+    fs::create_dir_all(dir.join("index")).unwrap();
+
+    if key != [0; 8] {
+      fs::write(dir.join("xor.dat"), key).unwrap();
+    }
+
+    let magic = Network::Regtest.magic().to_bytes();
+
+    let mut records = Vec::new();
+
+    for (file, blocks) in blocks.chunks(per_file).enumerate() {
+      let mut buffer = Vec::new();
+
+      for block in blocks {
+        let block_bytes = consensus::serialize(block);
+        buffer.extend_from_slice(&magic);
+        buffer.extend_from_slice(&u32::try_from(block_bytes.len()).unwrap().to_le_bytes());
+
+        records.push(block_index::tests::block_record(
+          &block.header,
+          records.len().try_into().unwrap(),
+          Some((file.try_into().unwrap(), buffer.len().try_into().unwrap())),
+        ));
+
+        buffer.extend_from_slice(&block_bytes);
+      }
+
+      buffer.extend_from_slice(&[0; 64]);
+
+      for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte ^= key[i % 8];
+      }
+
+      fs::write(dir.join(format!("blk{file:05}.dat")), buffer).unwrap();
+    }
+
+    let (table, log) = records.split_at(records.len() / 2);
+
+    block_index::tests::write_table(&dir.join("index/000005.ldb"), 1, table, 16);
+    block_index::tests::write_log(
+      &dir.join("index/000006.log"),
+      u64::try_from(table.len()).unwrap() + 1,
+      log,
+    );
+  }
+
+  fn blocks(core: &mockcore::Handle) -> Vec<Block> {
+    let state = core.state();
+    state
+      .hashes
+      .iter()
+      .map(|hash| state.blocks[hash].clone())
+      .collect()
+  }
+
+  #[test]
+  fn blocks_are_read_from_blk_files() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+    core.mine_blocks(5);
+    core.broadcast_tx(mockcore::TransactionTemplate {
+      inputs: &[(1, 0, 0, Default::default())],
+      ..Default::default()
+    });
+    core.mine_blocks(1);
+
+    let blocks = blocks(&core);
+
+    for key in [[0; 8], [1, 2, 3, 4, 5, 6, 7, 8]] {
+      let tempdir = TempDir::new().unwrap();
+
+      write_blk_files(tempdir.path(), &blocks, key, 4);
+
+      let blk_files = BlkFiles::open(tempdir.path(), Network::Regtest).unwrap();
+
+      assert_eq!(blk_files.len(), blocks.len());
+
+      let tip = blocks.last().unwrap().block_hash();
+
+      assert_eq!(
+        blk_files.chain(tip).unwrap(),
+        blocks
+          .iter()
+          .map(Block::block_hash)
+          .collect::<Vec<BlockHash>>(),
+      );
+
+      for block in &blocks {
+        assert_eq!(
+          blk_files.block(block.block_hash()).unwrap().as_ref(),
+          Some(block)
+        );
+        assert_eq!(
+          blk_files.header(block.block_hash()).unwrap(),
+          Some(block.header)
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn missing_blocks() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+    core.mine_blocks(3);
+
+    let blocks = blocks(&core);
+
+    let tempdir = TempDir::new().unwrap();
+
+    write_blk_files(tempdir.path(), &blocks[1..], [0; 8], 2);
+
+    let blk_files = BlkFiles::open(tempdir.path(), Network::Regtest).unwrap();
+
+    assert_eq!(blk_files.chain(blocks[3].block_hash()), None);
+    assert_eq!(blk_files.block(blocks[0].block_hash()).unwrap(), None);
+    assert_eq!(blk_files.header(blocks[0].block_hash()).unwrap(), None);
+  }
+
+  #[test]
+  fn blocks_missing_from_block_index_are_ignored() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+    core.mine_blocks(2);
+
+    let blocks = blocks(&core);
+
+    let tempdir = TempDir::new().unwrap();
+
+    write_blk_files(tempdir.path(), &blocks, [0; 8], 3);
+
+    fs::remove_file(tempdir.path().join("index/000006.log")).unwrap();
+
+    let blk_files = BlkFiles::open(tempdir.path(), Network::Regtest).unwrap();
+
+    assert_eq!(blk_files.len(), 1);
+    assert_eq!(blk_files.block(blocks[1].block_hash()).unwrap(), None);
+  }
+
+  #[test]
+  fn wrong_network_blocks_are_errors() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+    core.mine_blocks(1);
+
+    let blocks = blocks(&core);
+
+    let tempdir = TempDir::new().unwrap();
+
+    write_blk_files(tempdir.path(), &blocks, [0; 8], 1);
+
+    let blk_files = BlkFiles::open(tempdir.path(), Network::Bitcoin).unwrap();
+
+    assert_eq!(
+      blk_files
+        .block(blocks[1].block_hash())
+        .unwrap_err()
+        .to_string(),
+      format!(
+        "block {} in {} does not begin with the network magic",
+        blocks[1].block_hash(),
+        tempdir.path().join("blk00001.dat").display(),
+      ),
+    );
+  }
+
+  #[test]
+  fn missing_block_index() {
+    let tempdir = TempDir::new().unwrap();
+    assert_eq!(
+      BlkFiles::open(tempdir.path(), Network::Regtest)
+        .err()
+        .unwrap()
+        .to_string(),
+      format!(
+        "failed to read block index in {}",
+        tempdir.path().join("index").display()
+      ),
+    );
+  }
+
+  #[test]
+  fn invalid_key() {
+    let tempdir = TempDir::new().unwrap();
+    fs::write(tempdir.path().join("xor.dat"), [0; 4]).unwrap();
+    assert_eq!(
+      BlkFiles::open(tempdir.path(), Network::Regtest)
+        .err()
+        .unwrap()
+        .to_string(),
+      "xor.dat is 4 bytes, expected 8",
+    );
+  }
+}
@@ -0,0 +1,720 @@
+use super::*;
+
+const BLOCK_HAVE_DATA: u64 = 8;
+const BLOCK_HAVE_UNDO: u64 = 16;
+const BLOCK_INDEX_PREFIX: u8 = b'b';
+const FOOTER_SIZE: usize = 48;
+const LOG_BLOCK_SIZE: usize = 32768;
+const LOG_HEADER_SIZE: usize = 7;
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Entry {
+  pub(super) file: u32,
+  pub(super) hash: BlockHash,
+  pub(super) offset: u64,
+  pub(super) prev_blockhash: BlockHash,
+}
+
+/// Read the blocks with data in blk files from bitcoind's LevelDB block index
+/// in `dir`.
+///
+/// LevelDB's lock only excludes other writers, so the table and log files are
+/// read directly rather than through LevelDB, and when a key appears in more
+/// than one of them, the record with the highest sequence number wins.
+pub(super) fn read(dir: &Path) -> Result<Vec<Entry>> {
+  let mut records = HashMap::<Vec<u8>, (u64, Option<Vec<u8>>)>::new();
+
+  let mut insert = |key: &[u8], sequence: u64, value: Option<&[u8]>| {
+    if key.first() != Some(&BLOCK_INDEX_PREFIX) {
+      return;
+    }
+
+    if records
+      .get(key)
+      .map_or(true, |(existing, _)| *existing < sequence)
+    {
+      records.insert(key.into(), (sequence, value.map(Into::into)));
+    }
+  };
+
+  let mut paths = fs::read_dir(dir)?
+    .map(|entry| entry.map(|entry| entry.path()))
+    .collect::<io::Result<Vec<PathBuf>>>()?;
+
+  paths.sort();
+
+  for path in paths {
+    let result = match path.extension().and_then(|extension| extension.to_str()) {
+      Some("ldb" | "sst") => read_table(&path, &mut insert),
+      Some("log") => read_log(&path, &mut insert),
+      _ => continue,
+    };
+
+    result.with_context(|| format!("failed to read {}", path.display()))?;
+  }
+
+  let mut entries = Vec::new();
+
+  for (key, (_, value)) in records {
+    let Some(value) = value else {
+      continue;
+    };
+
+    if let Some(entry) =
+      decode(&key, &value).with_context(|| format!("invalid block index entry {key:?}"))?
+    {
+      entries.push(entry);
+    }
+  }
+
+  Ok(entries)
+}
+
+fn decode(key: &[u8], mut value: &[u8]) -> Result<Option<Entry>> {
+  let hash = BlockHash::from_slice(&key[1..])?;
+
+  let _version = bitcoin_varint(&mut value)?;
+  let _height = bitcoin_varint(&mut value)?;
+  let status = bitcoin_varint(&mut value)?;
+  let _transactions = bitcoin_varint(&mut value)?;
+
+  let file = if status & (BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO) != 0 {
+    Some(u32::try_from(bitcoin_varint(&mut value)?)?)
+  } else {
+    None
+  };
+
+  let offset = if status & BLOCK_HAVE_DATA != 0 {
+    Some(bitcoin_varint(&mut value)?)
+  } else {
+    None
+  };
+
+  if status & BLOCK_HAVE_UNDO != 0 {
+    bitcoin_varint(&mut value)?;
+  }
+
+  let header: Header = consensus::deserialize(value)?;
+
+  let (Some(file), Some(offset)) = (file, offset) else {
+    return Ok(None);
+  };
+
+  Ok(Some(Entry {
+    file,
+    hash,
+    offset,
+    prev_blockhash: header.prev_blockhash,
+  }))
+}
+
+fn read_log(path: &Path, insert: &mut impl FnMut(&[u8], u64, Option<&[u8]>)) -> Result {
+  let contents = fs::read(path)?;
+
+  let mut fragments = Vec::new();
+
+  for block in contents.chunks(LOG_BLOCK_SIZE) {
+    let mut offset = 0;
+
+    // blocks end with zero padding when too little space is left for a record
+    // header, and the last record may still be being written, so stop at the
+    // first record which extends past the end of the file
+    while offset + LOG_HEADER_SIZE <= block.len() {
+      let length = usize::from(u16::from_le_bytes(
+        block[offset + 4..offset + 6].try_into().unwrap(),
+      ));
+      let kind = block[offset + 6];
+
+      let start = offset + LOG_HEADER_SIZE;
+
+      let Some(data) = block.get(start..start + length) else {
+        return Ok(());
+      };
+
+      offset = start + length;
+
+      match kind {
+        0 => return Ok(()),
+        1 => read_batch(data, insert)?,
+        2 => fragments = data.to_vec(),
+        3 => fragments.extend_from_slice(data),
+        4 => {
+          fragments.extend_from_slice(data);
+          read_batch(&fragments, insert)?;
+          fragments.clear();
+        }
+        _ => bail!("unknown log record type {kind}"),
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn read_batch(mut batch: &[u8], insert: &mut impl FnMut(&[u8], u64, Option<&[u8]>)) -> Result {
+  let sequence = u64::from_le_bytes(take(&mut batch, 8)?.try_into().unwrap());
+  let count = u32::from_le_bytes(take(&mut batch, 4)?.try_into().unwrap());
+
+  for i in 0..count {
+    let tag = take(&mut batch, 1)?[0];
+    let key = length_prefixed(&mut batch)?;
+    let value = match tag {
+      0 => None,
+      1 => Some(length_prefixed(&mut batch)?),
+      _ => bail!("unknown write batch record type {tag}"),
+    };
+
+    insert(key, sequence + u64::from(i), value);
+  }
+
+  Ok(())
+}
+
+fn read_table(path: &Path, insert: &mut impl FnMut(&[u8], u64, Option<&[u8]>)) -> Result {
+  let contents = fs::read(path)?;
+
+  let mut footer = contents
+    .len()
+    .checked_sub(FOOTER_SIZE)
+    .map(|start| &contents[start..])
+    .context("table is shorter than its footer")?;
+
+  ensure!(
+    u64::from_le_bytes(footer[FOOTER_SIZE - 8..].try_into().unwrap()) == TABLE_MAGIC,
+    "table has invalid magic number",
+  );
+
+  let _metaindex = block_handle(&mut footer)?;
+  let index = block_handle(&mut footer)?;
+
+  for (_, mut handle) in block_entries(table_block(&contents, index)?)? {
+    for (key, value) in block_entries(table_block(&contents, block_handle(&mut handle)?)?)? {
+      let split = key
+        .len()
+        .checked_sub(8)
+        .context("internal key is shorter than its trailer")?;
+
+      let (key, trailer) = key.split_at(split);
+
+      let trailer = u64::from_le_bytes(trailer.try_into().unwrap());
+
+      let value = match trailer & 0xff {
+        0 => None,
+        1 => Some(value),
+        kind => bail!("unknown table record type {kind}"),
+      };
+
+      insert(key, trailer >> 8, value);
+    }
+  }
+
+  Ok(())
+}
+
+fn table_block(contents: &[u8], (offset, size): (usize, usize)) -> Result<&[u8]> {
+  let block = offset
+    .checked_add(size)
+    .and_then(|end| contents.get(offset..end.checked_add(5)?))
+    .context("block handle extends past the end of the table")?;
+
+  // bitcoind builds LevelDB without snappy, so blocks are never compressed
+  ensure!(
+    block[size] == 0,
+    "block has unsupported compression type {}",
+    block[size],
+  );
+
+  Ok(&block[..size])
+}
+
+fn block_entries(block: &[u8]) -> Result<Vec<(Vec<u8>, &[u8])>> {
+  let restarts = block
+    .len()
+    .checked_sub(4)
+    .map(|start| u32::from_le_bytes(block[start..].try_into().unwrap()))
+    .context("block is shorter than its restart count")?;
+
+  let mut data = usize::try_from(restarts)
+    .ok()
+    .and_then(|restarts| restarts.checked_add(1)?.checked_mul(4))
+    .and_then(|trailer| block.len().checked_sub(trailer))
+    .map(|end| &block[..end])
+    .context("block is shorter than its restart array")?;
+
+  let mut key = Vec::new();
+  let mut entries = Vec::new();
+
+  while !data.is_empty() {
+    let shared = leveldb_varint(&mut data)?;
+    let unshared = leveldb_varint(&mut data)?;
+    let value_length = leveldb_varint(&mut data)?;
+
+    ensure!(
+      shared <= key.len(),
+      "block entry shares more than the previous key"
+    );
+
+    key.truncate(shared);
+    key.extend_from_slice(take(&mut data, unshared)?);
+
+    entries.push((key.clone(), take(&mut data, value_length)?));
+  }
+
+  Ok(entries)
+}
+
+fn block_handle(data: &mut &[u8]) -> Result<(usize, usize)> {
+  Ok((leveldb_varint(data)?, leveldb_varint(data)?))
+}
+
+fn length_prefixed<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+  let length = leveldb_varint(data)?;
+  take(data, length)
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+  ensure!(data.len() >= n, "unexpected end of data");
+  let (head, tail) = data.split_at(n);
+  *data = tail;
+  Ok(head)
+}
+
+/// LevelDB varints are little-endian base 128
+fn leveldb_varint(data: &mut &[u8]) -> Result<usize> {
+  let mut n = 0u64;
+
+  for shift in (0..64).step_by(7) {
+    let byte = take(data, 1)?[0];
+
+    n |= u64::from(byte & 0x7f) << shift;
+
+    if byte & 0x80 == 0 {
+      return Ok(n.try_into()?);
+    }
+  }
+
+  bail!("varint too long")
+}
+
+/// Bitcoin Core varints are big-endian base 128, with one subtracted from each
+/// byte but the last so that every value has a single encoding
+fn bitcoin_varint(data: &mut &[u8]) -> Result<u64> {
+  let mut n = 0u64;
+
+  loop {
+    let byte = take(data, 1)?[0];
+
+    ensure!(n <= u64::MAX >> 7, "varint too large");
+
+    n = (n << 7) | u64::from(byte & 0x7f);
+
+    if byte & 0x80 == 0 {
+      return Ok(n);
+    }
+
+    n = n.checked_add(1).context("varint too large")?;
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use {super::*, tempfile::TempDir};
+
+  pub(crate) enum Record {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+  }
+
+  fn encode_bitcoin_varint(mut n: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    loop {
+      bytes.push(u8::try_from(n & 0x7f).unwrap() | if bytes.is_empty() { 0 } else { 0x80 });
+
+      if n <= 0x7f {
+        break;
+      }
+
+      n = (n >> 7) - 1;
+    }
+
+    bytes.reverse();
+
+    bytes
+  }
+
+  fn encode_leveldb_varint(buffer: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+      buffer.push(u8::try_from(n & 0x7f).unwrap() | 0x80);
+      n >>= 7;
+    }
+
+    buffer.push(u8::try_from(n).unwrap());
+  }
+
+  /// A block index record for `header`, with data at `offset` in blk file
+  /// `file`, or with no data if `file` is `None`
+  pub(crate) fn block_record(header: &Header, height: u32, location: Option<(u32, u64)>) -> Record {
+    let mut value = encode_bitcoin_varint(259900);
+    value.extend(encode_bitcoin_varint(height.into()));
+
+    match location {
+      Some((file, offset)) => {
+        value.extend(encode_bitcoin_varint(BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO | 5));
+        value.extend(encode_bitcoin_varint(1));
+        value.extend(encode_bitcoin_varint(file.into()));
+        value.extend(encode_bitcoin_varint(offset));
+        value.extend(encode_bitcoin_varint(offset + 1000));
+      }
+      None => {
+        value.extend(encode_bitcoin_varint(1));
+        value.extend(encode_bitcoin_varint(0));
+      }
+    }
+
+    value.extend(consensus::serialize(header));
+
+    let mut key = vec![BLOCK_INDEX_PREFIX];
+    key.extend(header.block_hash().as_byte_array());
+
+    Record::Put(key, value)
+  }
+
+  /// Write `records` to a LevelDB log file at `path` as one write batch per
+  /// record, starting at sequence number `sequence`
+  pub(crate) fn write_log(path: &Path, sequence: u64, records: &[Record]) {
+    let mut log = Vec::new();
+
+    for (i, record) in records.iter().enumerate() {
+      let mut batch = (sequence + u64::try_from(i).unwrap())
+        .to_le_bytes()
+        .to_vec();
+      batch.extend(1u32.to_le_bytes());
+
+      match record {
+        Record::Put(key, value) => {
+          batch.push(1);
+          encode_leveldb_varint(&mut batch, key.len());
+          batch.extend(key);
+          encode_leveldb_varint(&mut batch, value.len());
+          batch.extend(value);
+        }
+        Record::Delete(key) => {
+          batch.push(0);
+          encode_leveldb_varint(&mut batch, key.len());
+          batch.extend(key);
+        }
+      }
+
+      let mut remaining = batch.as_slice();
+      let mut first = true;
+
+      loop {
+        let available = LOG_BLOCK_SIZE - log.len() % LOG_BLOCK_SIZE;
+
+        if available < LOG_HEADER_SIZE {
+          log.resize(log.len() + available, 0);
+          continue;
+        }
+
+        let length = remaining.len().min(available - LOG_HEADER_SIZE);
+        let last = length == remaining.len();
+
+        log.extend([0; 4]);
+        log.extend(u16::try_from(length).unwrap().to_le_bytes());
+        log.push(match (first, last) {
+          (true, true) => 1,
+          (true, false) => 2,
+          (false, false) => 3,
+          (false, true) => 4,
+        });
+        log.extend(&remaining[..length]);
+
+        remaining = &remaining[length..];
+        first = false;
+
+        if last {
+          break;
+        }
+      }
+    }
+
+    fs::write(path, log).unwrap();
+  }
+
+  fn write_block(table: &mut Vec<u8>, entries: &[(Vec<u8>, Vec<u8>)]) -> (usize, usize) {
+    let mut block = Vec::new();
+    let mut restarts = Vec::new();
+    let mut previous: &[u8] = &[];
+
+    for (i, (key, value)) in entries.iter().enumerate() {
+      let shared = if i % 4 == 0 {
+        restarts.push(u32::try_from(block.len()).unwrap());
+        0
+      } else {
+        previous.iter().zip(key).take_while(|(a, b)| a == b).count()
+      };
+
+      encode_leveldb_varint(&mut block, shared);
+      encode_leveldb_varint(&mut block, key.len() - shared);
+      encode_leveldb_varint(&mut block, value.len());
+      block.extend(&key[shared..]);
+      block.extend(value);
+
+      previous = key;
+    }
+
+    if restarts.is_empty() {
+      restarts.push(0);
+    }
+
+    for restart in &restarts {
+      block.extend(restart.to_le_bytes());
+    }
+
+    block.extend(u32::try_from(restarts.len()).unwrap().to_le_bytes());
+
+    let handle = (table.len(), block.len());
+
+    table.extend(block);
+    table.extend([0; 5]);
+
+    handle
+  }
+
+  /// Write `records` to a LevelDB table file at `path`, starting at sequence
+  /// number `sequence`, with `per_block` records in each data block
+  pub(crate) fn write_table(path: &Path, sequence: u64, records: &[Record], per_block: usize) {
+    let mut entries = records
+      .iter()
+      .enumerate()
+      .map(|(i, record)| {
+        let sequence = sequence + u64::try_from(i).unwrap();
+        let (key, kind, value) = match record {
+          Record::Put(key, value) => (key, 1, value.clone()),
+          Record::Delete(key) => (key, 0, Vec::new()),
+        };
+        (
+          [key.as_slice(), &(sequence << 8 | kind).to_le_bytes()].concat(),
+          value,
+        )
+      })
+      .collect::<Vec<(Vec<u8>, Vec<u8>)>>();
+
+    entries.sort_by(|(a, _), (b, _)| a[..a.len() - 8].cmp(&b[..b.len() - 8]));
+
+    let mut table = Vec::new();
+
+    let index = entries
+      .chunks(per_block)
+      .map(|chunk| {
+        let (offset, size) = write_block(&mut table, chunk);
+        let mut handle = Vec::new();
+        encode_leveldb_varint(&mut handle, offset);
+        encode_leveldb_varint(&mut handle, size);
+        (chunk.last().unwrap().0.clone(), handle)
+      })
+      .collect::<Vec<(Vec<u8>, Vec<u8>)>>();
+
+    let metaindex = write_block(&mut table, &[]);
+    let index = write_block(&mut table, &index);
+
+    let mut footer = Vec::new();
+    for (offset, size) in [metaindex, index] {
+      encode_leveldb_varint(&mut footer, offset);
+      encode_leveldb_varint(&mut footer, size);
+    }
+    footer.resize(FOOTER_SIZE - 8, 0);
+    footer.extend(TABLE_MAGIC.to_le_bytes());
+
+    table.extend(footer);
+
+    fs::write(path, table).unwrap();
+  }
+
+  fn header(n: u8) -> Header {
+    consensus::deserialize(&[n; 80]).unwrap()
+  }
+
+  fn entry(header: &Header, file: u32, offset: u64) -> Entry {
+    Entry {
+      file,
+      hash: header.block_hash(),
+      offset,
+      prev_blockhash: header.prev_blockhash,
+    }
+  }
+
+  fn read_sorted(dir: &Path) -> Vec<Entry> {
+    let mut entries = read(dir).unwrap();
+    entries.sort_by_key(|entry| (entry.file, entry.offset));
+    entries
+  }
+
+  #[test]
+  fn records_are_read_from_tables_and_logs() {
+    let tempdir = TempDir::new().unwrap();
+
+    let headers = (0..20).map(header).collect::<Vec<Header>>();
+
+    let records = headers
+      .iter()
+      .enumerate()
+      .map(|(i, header)| block_record(header, i.try_into().unwrap(), Some((0, i as u64 * 100))))
+      .collect::<Vec<Record>>();
+
+    write_table(&tempdir.path().join("000005.ldb"), 1, &records[..10], 3);
+    write_log(&tempdir.path().join("000006.log"), 11, &records[10..]);
+
+    assert_eq!(
+      read_sorted(tempdir.path()),
+      headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| entry(header, 0, i as u64 * 100))
+        .collect::<Vec<Entry>>(),
+    );
+  }
+
+  #[test]
+  fn records_with_higher_sequence_numbers_win() {
+    let tempdir = TempDir::new().unwrap();
+
+    let a = header(1);
+    let b = header(2);
+    let c = header(3);
+
+    write_log(
+      &tempdir.path().join("000003.log"),
+      10,
+      &[block_record(&a, 1, Some((2, 8)))],
+    );
+
+    write_table(
+      &tempdir.path().join("000004.ldb"),
+      1,
+      &[
+        block_record(&a, 1, Some((0, 8))),
+        block_record(&b, 2, Some((0, 300))),
+        block_record(&c, 3, None),
+      ],
+      1,
+    );
+
+    let Record::Put(b_key, _) = block_record(&b, 2, None) else {
+      unreachable!()
+    };
+
+    write_log(
+      &tempdir.path().join("000005.log"),
+      11,
+      &[block_record(&c, 3, Some((1, 8))), Record::Delete(b_key)],
+    );
+
+    assert_eq!(
+      read_sorted(tempdir.path()),
+      [entry(&c, 1, 8), entry(&a, 2, 8)],
+    );
+  }
+
+  #[test]
+  fn records_are_reassembled_from_log_fragments() {
+    let tempdir = TempDir::new().unwrap();
+
+    let headers = (0..=255).map(header).collect::<Vec<Header>>();
+
+    let mut records = vec![Record::Put(b"large".to_vec(), vec![0; LOG_BLOCK_SIZE * 2])];
+
+    records.extend(
+      headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| block_record(header, i.try_into().unwrap(), Some((1, i as u64)))),
+    );
+
+    let path = tempdir.path().join("000001.log");
+
+    write_log(&path, 1, &records);
+
+    assert!(fs::metadata(&path).unwrap().len() > u64::try_from(LOG_BLOCK_SIZE * 3).unwrap());
+
+    assert_eq!(
+      read_sorted(tempdir.path()),
+      headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| entry(header, 1, i as u64))
+        .collect::<Vec<Entry>>(),
+    );
+  }
+
+  #[test]
+  fn partially_written_log_records_are_ignored() {
+    let tempdir = TempDir::new().unwrap();
+
+    let a = header(1);
+    let b = header(2);
+
+    let path = tempdir.path().join("000001.log");
+
+    write_log(
+      &path,
+      1,
+      &[
+        block_record(&a, 1, Some((0, 8))),
+        block_record(&b, 2, Some((0, 300))),
+      ],
+    );
+
+    let mut contents = fs::read(&path).unwrap();
+    contents.truncate(contents.len() - 10);
+    fs::write(&path, contents).unwrap();
+
+    assert_eq!(read_sorted(tempdir.path()), [entry(&a, 0, 8)]);
+  }
+
+  #[test]
+  fn compressed_tables_are_rejected() {
+    let tempdir = TempDir::new().unwrap();
+
+    let path = tempdir.path().join("000001.ldb");
+
+    write_table(&path, 1, &[block_record(&header(1), 1, Some((0, 8)))], 1);
+
+    let mut contents = fs::read(&path).unwrap();
+
+    let (offset, size) = {
+      let mut footer = &contents[contents.len() - FOOTER_SIZE..];
+      block_handle(&mut footer).unwrap();
+      let index = block_handle(&mut footer).unwrap();
+      let (_, mut handle) = block_entries(table_block(&contents, index).unwrap())
+        .unwrap()
+        .remove(0);
+      block_handle(&mut handle).unwrap()
+    };
+
+    contents[offset + size] = 1;
+    fs::write(&path, contents).unwrap();
+
+    assert_eq!(
+      format!("{:#}", read(tempdir.path()).unwrap_err()),
+      format!(
+        "failed to read {}: block has unsupported compression type 1",
+        path.display()
+      ),
+    );
+  }
+
+  #[test]
+  fn bitcoin_varints() {
+    for n in [0, 1, 127, 128, 255, 16511, 16512, u32::MAX.into(), u64::MAX] {
+      let encoded = encode_bitcoin_varint(n);
+      assert_eq!(bitcoin_varint(&mut encoded.as_slice()).unwrap(), n);
+    }
+
+    assert_eq!(encode_bitcoin_varint(128), [0x80, 0x00]);
+    assert_eq!(encode_bitcoin_varint(16511), [0xff, 0x7f]);
+    assert!(bitcoin_varint(&mut [0x80].as_slice()).is_err());
+  }
+}
@@ -0,0 +1,177 @@
+use {super::*, bitcoin::hashes::HashEngine};
+
+/// Digest of the rune balance changes made by a block, in the order in which
+/// they were applied.
+#[derive(Default)]
+pub(crate) struct BalanceChanges(sha256::HashEngine);
+
+impl BalanceChanges {
+  const CREATED: u8 = 1;
+  const SPENT: u8 = 0;
+
+  pub(crate) fn spent(&mut self, outpoint: OutPoint, balances: &[u8]) {
+    self.record(Self::SPENT, outpoint, balances);
+  }
+
+  pub(crate) fn created(&mut self, outpoint: OutPoint, balances: &[u8]) {
+    self.record(Self::CREATED, outpoint, balances);
+  }
+
+  fn record(&mut self, tag: u8, outpoint: OutPoint, balances: &[u8]) {
+    self.0.input(&[tag]);
+    self.0.input(&outpoint.store());
+    self.0.input(&(balances.len() as u64).to_le_bytes());
+    self.0.input(balances);
+  }
+
+  pub(crate) fn finish(self) -> sha256::Hash {
+    sha256::Hash::from_engine(self.0)
+  }
+}
+
+/// Rune state committed to at the end of each block.
+pub(crate) struct StateCommitment<'a> {
+  pub(crate) balance_changes: sha256::Hash,
+  pub(crate) conversion_chain: (OutPoint, u64),
+  pub(crate) height: u32,
+  pub(crate) mint_chain: (OutPoint, u64),
+  pub(crate) previous: Option<sha256::Hash>,
+  pub(crate) supply_state: api::SupplyState,
+  pub(crate) util_entry: &'a UtilEntryValue,
+}
+
+impl StateCommitment<'_> {
+  /// Hash the state together with the commitment of the previous block, so
+  /// that two indexes agree at a height only if they agree at every height
+  /// before it.
+  pub(crate) fn hash(&self) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+
+    engine.input(
+      &self
+        .previous
+        .unwrap_or_else(sha256::Hash::all_zeros)
+        .to_byte_array(),
+    );
+
+    engine.input(&self.height.to_le_bytes());
+
+    let api::SupplyState {
+      supply0,
+      supply1,
+      burned0,
+      burned1,
+    } = self.supply_state;
+
+    for value in [supply0, supply1, burned0, burned1] {
+      engine.input(&value.to_le_bytes());
+    }
+
+    let (index, rates, bonds_per_sat) = self.util_entry;
+
+    engine.input(&index.to_le_bytes());
+    engine.input(&(rates.len() as u64).to_le_bytes());
+    for rate in rates {
+      engine.input(&rate.to_le_bytes());
+    }
+    engine.input(&bonds_per_sat.to_le_bytes());
+
+    for (outpoint, value) in [self.mint_chain, self.conversion_chain] {
+      engine.input(&outpoint.store());
+      engine.input(&value.to_le_bytes());
+    }
+
+    engine.input(&self.balance_changes.to_byte_array());
+
+    sha256::Hash::from_engine(engine)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn commitment(util_entry: &UtilEntryValue) -> StateCommitment<'_> {
+    StateCommitment {
+      balance_changes: BalanceChanges::default().finish(),
+      conversion_chain: (OutPoint::null(), 0),
+      height: 2,
+      mint_chain: (OutPoint::null(), 0),
+      previous: None,
+      supply_state: api::SupplyState {
+        supply0: 1,
+        supply1: 2,
+        burned0: 3,
+        burned1: 4,
+      },
+      util_entry,
+    }
+  }
+
+  #[test]
+  fn hash_is_deterministic() {
+    let util_entry = UtilEntry::new().store();
+    assert_eq!(
+      commitment(&util_entry).hash(),
+      commitment(&util_entry).hash()
+    );
+  }
+
+  #[test]
+  fn hash_commits_to_every_field() {
+    let util_entry = UtilEntry::new().store();
+    let base = commitment(&util_entry).hash();
+
+    let mut balance_changes = BalanceChanges::default();
+    balance_changes.created(OutPoint::null(), &[1]);
+
+    let mut changed_util_entry = UtilEntry::new().store();
+    changed_util_entry.2 += 1;
+
+    for changed in [
+      StateCommitment {
+        balance_changes: balance_changes.finish(),
+        ..commitment(&util_entry)
+      },
+      StateCommitment {
+        conversion_chain: (OutPoint::null(), 1),
+        ..commitment(&util_entry)
+      },
+      StateCommitment {
+        height: 3,
+        ..commitment(&util_entry)
+      },
+      StateCommitment {
+        mint_chain: (outpoint(1), 0),
+        ..commitment(&util_entry)
+      },
+      StateCommitment {
+        previous: Some(base),
+        ..commitment(&util_entry)
+      },
+      StateCommitment {
+        supply_state: api::SupplyState {
+          burned1: 5,
+          ..commitment(&util_entry).supply_state
+        },
+        ..commitment(&util_entry)
+      },
+      commitment(&changed_util_entry),
+    ] {
+      assert_ne!(changed.hash(), base);
+    }
+  }
+
+  #[test]
+  fn balance_changes_are_ordered() {
+    let mut a = BalanceChanges::default();
+    a.spent(outpoint(1), &[1]);
+    a.created(outpoint(2), &[1]);
+
+    let mut b = BalanceChanges::default();
+    b.created(outpoint(2), &[1]);
+    b.spent(outpoint(1), &[1]);
+
+    assert_ne!(a.finish(), b.finish());
+  }
+}
@@ -290,17 +290,30 @@ impl UtilEntry {
     }
   }
 
-  pub fn update(&mut self, supply0: u128, supply1: u128) {
-    if supply0 > supply1 {
-      let rate = Self::BASE_VALUE * (supply0 - supply1) / (supply0 + supply1);
-      if rate > 0 {
-        self.rates[self.index as usize % 100] = rate;
-        self.index = (self.index + 1) % 100;
-      }
+  /// Record the rate implied by the supplies at the end of a block and
+  /// accrue interest, returning the rate if one was recorded
+  pub fn update(&mut self, supply0: u128, supply1: u128) -> Option<u128> {
+    let recorded = Self::rate(supply0, supply1);
+
+    if let Some(rate) = recorded {
+      self.rates[self.index as usize % 100] = rate;
+      self.index = (self.index + 1) % 100;
     }
 
     self.bonds_per_sat +=
       self.bonds_per_sat * self.interest_rate() / Self::BASE_VALUE / Self::BLOCKS_PER_YEAR;
+
+    recorded
+  }
+
+  pub fn state(&self) -> api::UtilState {
+    api::UtilState {
+      bonds_per_sat: self.bonds_per_sat(),
+      utils_per_bond: self.utils_per_bond(),
+      utils_per_sat: self.utils_per_sat(),
+      interest_rate: self.interest_rate(),
+      decimals: self.decimals(),
+    }
   }
 
   pub fn interest_rate(&self) -> u128 {
@@ -325,6 +338,17 @@ impl UtilEntry {
     self.bonds_per_sat
   }
 
+  /// The rate implied by the supplies, if it is recorded
+  pub fn rate(supply0: u128, supply1: u128) -> Option<u128> {
+    if supply0 <= supply1 {
+      return None;
+    }
+
+    let rate = Self::BASE_VALUE * (supply0 - supply1) / (supply0 + supply1);
+
+    (rate > 0).then_some(rate)
+  }
+
   pub fn utils_per_bond(&self) -> u128 {
     Self::BASE_VALUE * Self::BASE_VALUE / self.interest_rate()
   }
@@ -369,6 +393,306 @@ impl Entry for UtilEntry {
   }
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+  TightenToEase,
+  EaseToTighten,
+}
+
+impl Display for Direction {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::TightenToEase => write!(f, "TIGHTEN → EASE"),
+      Self::EaseToTighten => write!(f, "EASE → TIGHTEN"),
+    }
+  }
+}
+
+impl Direction {
+  pub fn from_input(input_id: RuneId) -> Self {
+    if input_id == ID0 {
+      Self::TightenToEase
+    } else {
+      Self::EaseToTighten
+    }
+  }
+
+  pub fn input(self) -> RuneId {
+    match self {
+      Self::TightenToEase => ID0,
+      Self::EaseToTighten => ID1,
+    }
+  }
+
+  pub fn output(self) -> RuneId {
+    match self {
+      Self::TightenToEase => ID1,
+      Self::EaseToTighten => ID0,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct ConversionEntry {
+  pub number: u64,
+  pub height: u32,
+  pub direction: Direction,
+  pub exact_input: bool,
+  pub input: u128,
+  pub output: u128,
+  pub residual: u128,
+  pub supply_before: api::SupplyState,
+  pub supply_after: api::SupplyState,
+}
+
+pub(super) type ConversionEntryValue = (
+  u64,              // number
+  u32,              // height
+  bool,             // tighten to ease
+  bool,             // exact input
+  u128,             // input
+  u128,             // output
+  u128,             // residual
+  SupplyStateValue, // supply before
+  SupplyStateValue, // supply after
+);
+
+impl Entry for ConversionEntry {
+  type Value = ConversionEntryValue;
+
+  fn load(
+    (
+      number,
+      height,
+      tighten_to_ease,
+      exact_input,
+      input,
+      output,
+      residual,
+      supply_before,
+      supply_after,
+    ): Self::Value,
+  ) -> Self {
+    Self {
+      number,
+      height,
+      direction: if tighten_to_ease {
+        Direction::TightenToEase
+      } else {
+        Direction::EaseToTighten
+      },
+      exact_input,
+      input,
+      output,
+      residual,
+      supply_before: api::SupplyState::load(supply_before),
+      supply_after: api::SupplyState::load(supply_after),
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    (
+      self.number,
+      self.height,
+      self.direction == Direction::TightenToEase,
+      self.exact_input,
+      self.input,
+      self.output,
+      self.residual,
+      self.supply_before.store(),
+      self.supply_after.store(),
+    )
+  }
+}
+
+/// A conversion that was attempted but not applied, and where its input went.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct FailedConversionEntry {
+  pub height: u32,
+  pub direction: Direction,
+  pub exact_input: bool,
+  pub input: u128,
+  pub output: u128,
+  pub failure: ConversionFailure,
+  /// Output that received the input, or `None` if it was burned
+  pub refund: Option<u32>,
+}
+
+pub(super) type FailedConversionEntryValue = (
+  u32,         // height
+  bool,        // tighten to ease
+  bool,        // exact input
+  u128,        // input
+  u128,        // output
+  u8,          // failure
+  Option<u32>, // refund
+);
+
+impl Entry for FailedConversionEntry {
+  type Value = FailedConversionEntryValue;
+
+  fn load(
+    (height, tighten_to_ease, exact_input, input, output, failure, refund): Self::Value,
+  ) -> Self {
+    Self {
+      height,
+      direction: if tighten_to_ease {
+        Direction::TightenToEase
+      } else {
+        Direction::EaseToTighten
+      },
+      exact_input,
+      input,
+      output,
+      failure: match failure {
+        0 => ConversionFailure::Slippage,
+        1 => ConversionFailure::InsufficientSupply,
+        2 => ConversionFailure::MissingRbf,
+        3 => ConversionFailure::BrokenChain,
+        n => panic!("unknown conversion failure {n}"),
+      },
+      refund,
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    (
+      self.height,
+      self.direction == Direction::TightenToEase,
+      self.exact_input,
+      self.input,
+      self.output,
+      match self.failure {
+        ConversionFailure::Slippage => 0,
+        ConversionFailure::InsufficientSupply => 1,
+        ConversionFailure::MissingRbf => 2,
+        ConversionFailure::BrokenChain => 3,
+      },
+      self.refund,
+    )
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct MintEntry {
+  pub txid: Txid,
+  pub height: u32,
+  pub amount0: u128,
+  pub amount1: u128,
+  /// Portion of `amount0` recycled from burns rather than block rewards, or
+  /// `None` if the mint claimed rewards issued before they were tracked
+  pub recycled0: Option<u128>,
+  /// Portion of `amount1` recycled from burns rather than block rewards, or
+  /// `None` if the mint claimed rewards issued before they were tracked
+  pub recycled1: Option<u128>,
+  /// Output assigned the minted runes not allocated by edicts, the pointer or
+  /// the first valid destination
+  pub recipient: Option<u32>,
+}
+
+impl MintEntry {
+  pub fn reward0(&self) -> Option<u128> {
+    self.recycled0.map(|recycled0| self.amount0 - recycled0)
+  }
+
+  pub fn reward1(&self) -> Option<u128> {
+    self.recycled1.map(|recycled1| self.amount1 - recycled1)
+  }
+}
+
+pub(super) type MintEntryValue = (
+  TxidValue,            // txid
+  u32,                  // height
+  (u128, u128),         // amounts
+  Option<(u128, u128)>, // recycled
+  Option<u32>,          // recipient
+);
+
+impl Entry for MintEntry {
+  type Value = MintEntryValue;
+
+  fn load((txid, height, (amount0, amount1), recycled, recipient): Self::Value) -> Self {
+    Self {
+      txid: Txid::load(txid),
+      height,
+      amount0,
+      amount1,
+      recycled0: recycled.map(|(recycled0, _)| recycled0),
+      recycled1: recycled.map(|(_, recycled1)| recycled1),
+      recipient,
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    (
+      self.txid.store(),
+      self.height,
+      (self.amount0, self.amount1),
+      self.recycled0.zip(self.recycled1),
+      self.recipient,
+    )
+  }
+}
+
+pub(crate) type UtilStateValue = (
+  u128, // bonds_per_sat
+  u128, // utils_per_bond
+  u128, // utils_per_sat
+  u128, // interest_rate
+  u128, // decimals
+);
+
+impl Entry for api::UtilState {
+  type Value = UtilStateValue;
+
+  fn load(
+    (bonds_per_sat, utils_per_bond, utils_per_sat, interest_rate, decimals): Self::Value,
+  ) -> Self {
+    Self {
+      bonds_per_sat,
+      utils_per_bond,
+      utils_per_sat,
+      interest_rate,
+      decimals,
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    (
+      self.bonds_per_sat,
+      self.utils_per_bond,
+      self.utils_per_sat,
+      self.interest_rate,
+      self.decimals,
+    )
+  }
+}
+
+pub(crate) type SupplyStateValue = (
+  u128, // supply0
+  u128, // supply1
+  u128, // burned0
+  u128, // burned1
+);
+
+impl Entry for api::SupplyState {
+  type Value = SupplyStateValue;
+
+  fn load((supply0, supply1, burned0, burned1): Self::Value) -> Self {
+    Self {
+      supply0,
+      supply1,
+      burned0,
+      burned1,
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    (self.supply0, self.supply1, self.burned0, self.burned1)
+  }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct InscriptionEntry {
   pub charms: u16,
@@ -733,6 +1057,134 @@ mod tests {
     assert_eq!(RuneId { block: 1, tx: 2 }, RuneId::load((1, 2)),);
   }
 
+  #[test]
+  fn conversion_entry() {
+    let entry = ConversionEntry {
+      number: 1,
+      height: 2,
+      direction: Direction::EaseToTighten,
+      exact_input: true,
+      input: 3,
+      output: 4,
+      residual: 5,
+      supply_before: api::SupplyState {
+        supply0: 6,
+        supply1: 7,
+        burned0: 8,
+        burned1: 9,
+      },
+      supply_after: api::SupplyState {
+        supply0: 10,
+        supply1: 11,
+        burned0: 12,
+        burned1: 13,
+      },
+    };
+
+    let value = (1, 2, false, true, 3, 4, 5, (6, 7, 8, 9), (10, 11, 12, 13));
+
+    assert_eq!(entry.store(), value);
+    assert_eq!(ConversionEntry::load(value), entry);
+  }
+
+  #[test]
+  fn failed_conversion_entry() {
+    for (failure, n) in [
+      (ConversionFailure::Slippage, 0),
+      (ConversionFailure::InsufficientSupply, 1),
+      (ConversionFailure::MissingRbf, 2),
+      (ConversionFailure::BrokenChain, 3),
+    ] {
+      let entry = FailedConversionEntry {
+        height: 1,
+        direction: Direction::TightenToEase,
+        exact_input: false,
+        input: 2,
+        output: 3,
+        failure,
+        refund: Some(4),
+      };
+
+      let value = (1, true, false, 2, 3, n, Some(4));
+
+      assert_eq!(entry.store(), value);
+      assert_eq!(FailedConversionEntry::load(value), entry);
+    }
+  }
+
+  #[test]
+  fn mint_entry() {
+    let txid = Txid::from_byte_array([
+      0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
+      0x0F, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D,
+      0x1E, 0x1F,
+    ]);
+
+    let entry = MintEntry {
+      txid,
+      height: 1,
+      amount0: 2,
+      amount1: 3,
+      recycled0: Some(1),
+      recycled1: Some(0),
+      recipient: Some(4),
+    };
+
+    let value = (txid.to_byte_array(), 1, (2, 3), Some((1, 0)), Some(4));
+
+    assert_eq!(entry.store(), value);
+    assert_eq!(MintEntry::load(value), entry);
+    assert_eq!(entry.reward0(), Some(1));
+    assert_eq!(entry.reward1(), Some(3));
+
+    let entry = MintEntry {
+      recycled0: None,
+      recycled1: None,
+      ..entry
+    };
+
+    let value = (txid.to_byte_array(), 1, (2, 3), None, Some(4));
+
+    assert_eq!(entry.store(), value);
+    assert_eq!(MintEntry::load(value), entry);
+    assert_eq!(entry.reward0(), None);
+  }
+
+  #[test]
+  fn supply_state_entry() {
+    let state = api::SupplyState {
+      supply0: 1,
+      supply1: 2,
+      burned0: 3,
+      burned1: 4,
+    };
+
+    assert_eq!(state.store(), (1, 2, 3, 4));
+    assert_eq!(api::SupplyState::load((1, 2, 3, 4)), state);
+  }
+
+  #[test]
+  fn util_state_entry() {
+    let state = api::UtilState {
+      bonds_per_sat: 1,
+      utils_per_bond: 2,
+      utils_per_sat: 3,
+      interest_rate: 4,
+      decimals: 5,
+    };
+
+    assert_eq!(state.store(), (1, 2, 3, 4, 5));
+    assert_eq!(api::UtilState::load((1, 2, 3, 4, 5)), state);
+  }
+
+  #[test]
+  fn util_entry_update_returns_recorded_rate() {
+    let mut entry = UtilEntry::new();
+    assert_eq!(entry.update(1, 1), None);
+    assert_eq!(entry.update(3, 1), Some(UtilEntry::BASE_VALUE / 2));
+    assert_eq!(entry.history(), [UtilEntry::BASE_VALUE / 2]);
+  }
+
   #[test]
   fn header() {
     let expected = [
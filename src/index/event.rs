@@ -1,31 +1,43 @@
-use super::*;
+use {super::*, tokio::sync::mpsc, webhooks::Webhooks};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Event {
-  InscriptionCreated {
+  /// Blocks through `block_height` have been committed to the index, and
+  /// will only be rolled back by a reorg
+  BlockCommitted {
+    block_hash: BlockHash,
     block_height: u32,
-    charms: u16,
-    inscription_id: InscriptionId,
-    location: Option<SatPoint>,
-    parent_inscription_ids: Vec<InscriptionId>,
-    sequence_number: u32,
   },
-  InscriptionTransferred {
+  ConversionChainUpdated {
     block_height: u32,
-    inscription_id: InscriptionId,
-    new_location: SatPoint,
-    old_location: SatPoint,
-    sequence_number: u32,
+    outpoint: OutPoint,
+    value: u64,
+  },
+  MintChainUpdated {
+    block_height: u32,
+    outpoint: OutPoint,
+    value: u64,
   },
+  /// The index was rolled back after a reorg of `depth` blocks, and will
+  /// index blocks again from `block_height`
+  ReorgRolledBack { block_height: u32, depth: u32 },
   RuneBurned {
     amount: u128,
     block_height: u32,
     rune_id: RuneId,
     txid: Txid,
   },
-  RuneEtched {
+  /// A conversion failed and left the supply unchanged at `supply_state`
+  RuneConversionFailed {
     block_height: u32,
-    rune_id: RuneId,
+    entry: FailedConversionEntry,
+    supply_state: api::SupplyState,
+    txid: Txid,
+  },
+  RuneConverted {
+    block_height: u32,
+    entry: ConversionEntry,
     txid: Txid,
   },
   RuneMinted {
@@ -39,6 +51,225 @@ pub enum Event {
     block_height: u32,
     outpoint: OutPoint,
     rune_id: RuneId,
+    script_pubkey: ScriptBuf,
     txid: Txid,
   },
+  UtilStateUpdated {
+    block_height: u32,
+    rate: Option<u128>,
+    supply_state: api::SupplyState,
+    util_state: api::UtilState,
+  },
+}
+
+impl Event {
+  pub const TYPES: [&'static str; 10] = [
+    "block_committed",
+    "conversion_chain_updated",
+    "mint_chain_updated",
+    "reorg_rolled_back",
+    "rune_burned",
+    "rune_conversion_failed",
+    "rune_converted",
+    "rune_minted",
+    "rune_transferred",
+    "util_state_updated",
+  ];
+
+  pub fn block_height(&self) -> u32 {
+    match self {
+      Self::BlockCommitted { block_height, .. }
+      | Self::ConversionChainUpdated { block_height, .. }
+      | Self::MintChainUpdated { block_height, .. }
+      | Self::ReorgRolledBack { block_height, .. }
+      | Self::RuneBurned { block_height, .. }
+      | Self::RuneConversionFailed { block_height, .. }
+      | Self::RuneConverted { block_height, .. }
+      | Self::RuneMinted { block_height, .. }
+      | Self::RuneTransferred { block_height, .. }
+      | Self::UtilStateUpdated { block_height, .. } => *block_height,
+    }
+  }
+
+  /// Name of the event's type, which it is serialized under
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Self::BlockCommitted { .. } => "block_committed",
+      Self::ConversionChainUpdated { .. } => "conversion_chain_updated",
+      Self::MintChainUpdated { .. } => "mint_chain_updated",
+      Self::ReorgRolledBack { .. } => "reorg_rolled_back",
+      Self::RuneBurned { .. } => "rune_burned",
+      Self::RuneConversionFailed { .. } => "rune_conversion_failed",
+      Self::RuneConverted { .. } => "rune_converted",
+      Self::RuneMinted { .. } => "rune_minted",
+      Self::RuneTransferred { .. } => "rune_transferred",
+      Self::UtilStateUpdated { .. } => "util_state_updated",
+    }
+  }
+}
+
+/// Destinations of the events emitted while indexing: the channel passed to
+/// `Index::open_with_event_sender`, and configured webhooks
+pub(crate) struct EventSender {
+  pub(crate) channel: Option<mpsc::Sender<Event>>,
+  pub(crate) webhooks: Option<Webhooks>,
+}
+
+impl EventSender {
+  pub(crate) fn send(&self, event: Event) -> Result {
+    if let Some(webhooks) = &self.webhooks {
+      webhooks.push(event.clone());
+    }
+
+    if let Some(channel) = &self.channel {
+      channel.blocking_send(event)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Filter for the events sent to `/events` subscribers and webhooks
+#[derive(Debug, Default)]
+pub(crate) struct Filter {
+  pub(crate) outpoint: Option<OutPoint>,
+  pub(crate) rune: Option<RuneId>,
+  pub(crate) script_pubkey: Option<ScriptBuf>,
+  pub(crate) types: Option<HashSet<String>>,
+}
+
+impl Filter {
+  /// Whether `event` matches the filter.
+  ///
+  /// Events which concern a different rune, address, or outpoint than the
+  /// filter's don't match, but block commits and reorgs concern every
+  /// consumer, so only the filter's event types apply to them.
+  pub(crate) fn matches(&self, event: &Event) -> bool {
+    if let Some(types) = &self.types {
+      if !types.contains(event.type_name()) {
+        return false;
+      }
+    }
+
+    if matches!(
+      event,
+      Event::BlockCommitted { .. } | Event::ReorgRolledBack { .. }
+    ) {
+      return true;
+    }
+
+    if let Some(rune) = self.rune {
+      let matches = match event {
+        Event::RuneBurned { rune_id, .. } | Event::RuneTransferred { rune_id, .. } => {
+          *rune_id == rune
+        }
+        Event::RuneMinted {
+          amount0, amount1, ..
+        } => (rune == ID0 && *amount0 > 0) || (rune == ID1 && *amount1 > 0),
+        // conversions always exchange one rune for the other
+        Event::RuneConversionFailed { .. } | Event::RuneConverted { .. } => true,
+        _ => false,
+      };
+
+      if !matches {
+        return false;
+      }
+    }
+
+    if let Some(script_pubkey) = &self.script_pubkey {
+      match event {
+        Event::RuneTransferred {
+          script_pubkey: transferred_to,
+          ..
+        } if transferred_to == script_pubkey => {}
+        _ => return false,
+      }
+    }
+
+    if let Some(outpoint) = self.outpoint {
+      match event {
+        Event::RuneTransferred {
+          outpoint: updated, ..
+        }
+        | Event::ConversionChainUpdated {
+          outpoint: updated, ..
+        }
+        | Event::MintChainUpdated {
+          outpoint: updated, ..
+        } if *updated == outpoint => {}
+        _ => return false,
+      }
+    }
+
+    true
+  }
+}
+
+/// Position of an event in the stream of index events, made of the height of
+/// the block which produced it, and its position among that block's events
+#[derive(
+  Debug, PartialEq, Copy, Clone, Eq, PartialOrd, Ord, DeserializeFromStr, SerializeDisplay,
+)]
+pub struct EventId {
+  pub height: u32,
+  pub sequence: u32,
+}
+
+impl Display for EventId {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}:{}", self.height, self.sequence)
+  }
+}
+
+impl FromStr for EventId {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (height, sequence) = s
+      .split_once(':')
+      .with_context(|| format!("invalid event ID `{s}`, expected `<HEIGHT>:<SEQUENCE>`"))?;
+
+    Ok(Self {
+      height: height.parse()?,
+      sequence: sequence.parse()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn event_id_round_trips() {
+    let id = EventId {
+      height: 840000,
+      sequence: 7,
+    };
+
+    assert_eq!(id.to_string(), "840000:7");
+    assert_eq!("840000:7".parse::<EventId>().unwrap(), id);
+    assert_eq!(
+      serde_json::to_string(&id).unwrap(),
+      r#""840000:7""#.to_string()
+    );
+
+    assert!("840000".parse::<EventId>().is_err());
+    assert!("a:7".parse::<EventId>().is_err());
+  }
+
+  #[test]
+  fn events_are_serialized_under_their_type() {
+    let event = Event::RuneBurned {
+      amount: 5,
+      block_height: 3,
+      rune_id: RuneId { block: 1, tx: 0 },
+      txid: Txid::all_zeros(),
+    };
+
+    let json = serde_json::to_string(&event).unwrap();
+
+    assert!(json.starts_with(&format!(r#"{{"{}":{{"#, event.type_name())));
+    assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+  }
 }
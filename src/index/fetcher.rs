@@ -5,10 +5,13 @@ use {
   serde_json::{json, Value},
 };
 
-pub(crate) struct Fetcher {
-  auth: String,
-  client: Client<HttpConnector>,
-  url: Uri,
+pub(crate) enum Fetcher {
+  Core {
+    auth: String,
+    client: Client<HttpConnector>,
+    url: Uri,
+  },
+  Esplora(Box<dyn ChainSource>),
 }
 
 #[derive(Deserialize, Debug)]
@@ -26,6 +29,10 @@ struct JsonError {
 
 impl Fetcher {
   pub(crate) fn new(settings: &Settings) -> Result<Self> {
+    if settings.esplora_url().is_some() {
+      return Ok(Fetcher::Esplora(settings.chain_source()?));
+    }
+
     let client = Client::new();
 
     let url = if settings.bitcoin_rpc_url(None).starts_with("http://") {
@@ -42,7 +49,7 @@ impl Fetcher {
       "Basic {}",
       &base64::engine::general_purpose::STANDARD.encode(auth)
     );
-    Ok(Fetcher { client, url, auth })
+    Ok(Fetcher::Core { client, url, auth })
   }
 
   pub(crate) async fn get_transactions(&self, txids: Vec<Txid>) -> Result<Vec<Transaction>> {
@@ -50,6 +57,21 @@ impl Fetcher {
       return Ok(Vec::new());
     }
 
+    if let Fetcher::Esplora(esplora) = self {
+      // Esplora has no batch requests, so transactions are fetched one at a
+      // time
+      return task::block_in_place(|| {
+        txids
+          .into_iter()
+          .map(|txid| {
+            esplora
+              .raw_transaction(txid)?
+              .with_context(|| format!("transaction {txid} not found"))
+          })
+          .collect()
+      });
+    }
+
     let mut reqs = Vec::with_capacity(txids.len());
     for (i, txid) in txids.iter().enumerate() {
       let req = json!({
@@ -120,14 +142,18 @@ impl Fetcher {
   }
 
   async fn try_get_transactions(&self, body: String) -> Result<Vec<JsonResponse<String>>> {
+    let Fetcher::Core { auth, client, url } = self else {
+      unreachable!("Esplora does not support batched JSON-RPC requests");
+    };
+
     let req = Request::builder()
       .method(Method::POST)
-      .uri(&self.url)
-      .header(hyper::header::AUTHORIZATION, &self.auth)
+      .uri(url)
+      .header(hyper::header::AUTHORIZATION, auth)
       .header(hyper::header::CONTENT_TYPE, "application/json")
       .body(Body::from(body))?;
 
-    let response = self.client.request(req).await?;
+    let response = client.request(req).await?;
 
     let buf = hyper::body::to_bytes(response).await?;
 
@@ -0,0 +1,224 @@
+use super::*;
+
+/// A step which migrates an index from schema version `from` to `from + 1`,
+/// creating new tables and backfilling them where the data already in the
+/// index allows it.
+pub(crate) struct Migration {
+  pub(crate) from: u64,
+  pub(crate) description: &'static str,
+  run: fn(&WriteTransaction, &Settings) -> Result,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MigrationStep {
+  pub from: u64,
+  pub to: u64,
+  pub description: String,
+}
+
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    from: 26,
+    description: "record supply and util state by height",
+    run: |wtx, settings| {
+      wtx.open_table(HEIGHT_TO_RATE)?;
+      wtx.open_table(HEIGHT_TO_SUPPLY_STATE)?;
+      wtx.open_table(HEIGHT_TO_UTIL_STATE)?;
+
+      // only the state at the tip is known, earlier states are lost
+      let Some(height) = tip(wtx)?.filter(|height| *height >= settings.first_rune_height()) else {
+        return Ok(());
+      };
+
+      let id_to_entry = wtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?;
+      let entry0 = RuneEntry::load(id_to_entry.get(&ID0.store())?.unwrap().value());
+      let entry1 = RuneEntry::load(id_to_entry.get(&ID1.store())?.unwrap().value());
+
+      let supply_state = api::SupplyState {
+        supply0: entry0.supply,
+        supply1: entry1.supply,
+        burned0: entry0.burned,
+        burned1: entry1.burned,
+      };
+
+      let util_entry = UtilEntry::load(wtx.open_table(UTIL_ENTRY)?.get(0)?.unwrap().value());
+
+      if let Some(rate) = UtilEntry::rate(supply_state.supply0, supply_state.supply1) {
+        wtx.open_table(HEIGHT_TO_RATE)?.insert(height, rate)?;
+      }
+
+      wtx
+        .open_table(HEIGHT_TO_SUPPLY_STATE)?
+        .insert(height, supply_state.store())?;
+
+      wtx
+        .open_table(HEIGHT_TO_UTIL_STATE)?
+        .insert(height, util_entry.state().store())?;
+
+      Ok(())
+    },
+  },
+  Migration {
+    from: 27,
+    description: "record conversions by txid",
+    run: |wtx, _settings| {
+      wtx.open_table(CONVERSION_NUMBER_TO_TXID)?;
+      wtx.open_table(TXID_TO_CONVERSION)?;
+      Ok(())
+    },
+  },
+  Migration {
+    from: 28,
+    description: "record failed conversions by txid",
+    run: |wtx, _settings| {
+      wtx.open_table(TXID_TO_FAILED_CONVERSION)?;
+      Ok(())
+    },
+  },
+  Migration {
+    from: 29,
+    description: "record mints by height",
+    run: |wtx, _settings| {
+      wtx.open_table(HEIGHT_TO_MINT)?;
+
+      // unclaimed rewards cannot be told apart from burned runes, so they
+      // are left untracked and the next mint's recycled amount is unknown
+      wtx.open_table(UNCLAIMED_REWARD)?;
+
+      Ok(())
+    },
+  },
+  Migration {
+    from: 30,
+    description: "index rune balances by script pubkey",
+    run: |wtx, _settings| {
+      let mut script_pubkey_to_rune_balance = wtx.open_table(SCRIPT_PUBKEY_TO_RUNE_BALANCE)?;
+
+      let index_addresses = wtx
+        .open_table(STATISTIC_TO_COUNT)?
+        .get(&Statistic::IndexAddresses.key())?
+        .map(|guard| guard.value())
+        .unwrap_or_default()
+        != 0;
+
+      if !index_addresses {
+        return Ok(());
+      }
+
+      let outpoint_to_txout = wtx.open_table(OUTPOINT_TO_TXOUT)?;
+
+      let mut balances = HashMap::<ScriptBuf, (u128, u128)>::new();
+
+      for result in wtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?.iter()? {
+        let (outpoint, buffer) = result?;
+
+        let Some(txout) = outpoint_to_txout.get(outpoint.value())? else {
+          continue;
+        };
+
+        let (balance0, balance1) = balances
+          .entry(TxOut::load(txout.value()).script_pubkey)
+          .or_default();
+
+        let buffer = buffer.value();
+        let mut i = 0;
+        while i < buffer.len() {
+          let ((id, balance), len) = Index::decode_rune_balance(&buffer[i..])?;
+          i += len;
+          if id == ID0 {
+            *balance0 += balance;
+          } else if id == ID1 {
+            *balance1 += balance;
+          }
+        }
+      }
+
+      for (script_pubkey, balance) in balances {
+        script_pubkey_to_rune_balance.insert(script_pubkey.as_bytes(), balance)?;
+      }
+
+      Ok(())
+    },
+  },
+  Migration {
+    from: 31,
+    description: "record state commitments by height",
+    run: |wtx, _settings| {
+      // commitments chain over every block since the first rune height, so
+      // they cannot be backfilled, and migrated indexes do not record them
+      wtx.open_table(HEIGHT_TO_STATE_COMMITMENT)?;
+      Ok(())
+    },
+  },
+  Migration {
+    from: 32,
+    description: "record balance change digests by height",
+    run: |wtx, _settings| {
+      // digests of earlier blocks are lost, so snapshots can only be
+      // exported from heights indexed after the migration
+      wtx.open_table(HEIGHT_TO_BALANCE_CHANGES)?;
+      Ok(())
+    },
+  },
+];
+
+fn tip(wtx: &WriteTransaction) -> Result<Option<u32>> {
+  Ok(
+    wtx
+      .open_table(HEIGHT_TO_BLOCK_HEADER)?
+      .last()?
+      .map(|(height, _header)| height.value()),
+  )
+}
+
+impl Migration {
+  /// Steps which migrate an index at schema `version` to the current schema,
+  /// or `None` if no such steps exist
+  pub(crate) fn path(version: u64) -> Option<&'static [Migration]> {
+    let start = MIGRATIONS
+      .iter()
+      .position(|migration| migration.from == version)?;
+
+    Some(&MIGRATIONS[start..])
+  }
+
+  pub(crate) fn step(&self) -> MigrationStep {
+    MigrationStep {
+      from: self.from,
+      to: self.from + 1,
+      description: self.description.into(),
+    }
+  }
+
+  pub(crate) fn run(&self, wtx: &WriteTransaction, settings: &Settings) -> Result {
+    (self.run)(wtx, settings)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn migrations_are_contiguous_and_end_at_current_schema() {
+    for window in MIGRATIONS.windows(2) {
+      assert_eq!(window[0].from + 1, window[1].from);
+    }
+
+    assert_eq!(MIGRATIONS.last().unwrap().from + 1, SCHEMA_VERSION);
+  }
+
+  #[test]
+  fn path() {
+    assert_eq!(Migration::path(25).map(<[Migration]>::len), None);
+    assert_eq!(
+      Migration::path(26).map(<[Migration]>::len),
+      Some(MIGRATIONS.len())
+    );
+    assert_eq!(Migration::path(SCHEMA_VERSION - 1).unwrap().len(), 1);
+    assert_eq!(
+      Migration::path(SCHEMA_VERSION).map(<[Migration]>::len),
+      None
+    );
+  }
+}
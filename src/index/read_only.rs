@@ -0,0 +1,394 @@
+use {
+  super::*,
+  redb::StorageBackend,
+  std::{
+    fs::File,
+    io::{Seek, SeekFrom},
+    time::SystemTime,
+  },
+};
+
+/// Storage for a read-only index, which reads from a copy of the index
+/// published by `bitomc index update --follow`, and keeps writes in memory.
+///
+/// redb takes an exclusive lock on its file, and writes its header when a
+/// database is opened and closed, even if nothing else is written, so a
+/// published copy can't be opened by more than one server directly. Reading
+/// through this backend leaves the copy untouched, so any number of servers
+/// can share it.
+#[derive(Debug)]
+pub(crate) struct CopyOnWriteFile {
+  file: Mutex<File>,
+  overlay: Mutex<Overlay>,
+}
+
+#[derive(Debug)]
+struct Overlay {
+  file_len: u64,
+  len: u64,
+  pages: HashMap<u64, Vec<u8>>,
+}
+
+impl CopyOnWriteFile {
+  const PAGE_SIZE: u64 = 4096;
+
+  pub(crate) fn new(file: File) -> io::Result<Self> {
+    let len = file.metadata()?.len();
+
+    Ok(Self {
+      file: Mutex::new(file),
+      overlay: Mutex::new(Overlay {
+        file_len: len,
+        len,
+        pages: HashMap::new(),
+      }),
+    })
+  }
+
+  /// Read `buffer.len()` bytes at `offset` from the underlying file, with
+  /// bytes past its end read as zeros
+  fn read_file(&self, file_len: u64, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+    buffer.fill(0);
+
+    if offset >= file_len {
+      return Ok(());
+    }
+
+    let available = usize::try_from(file_len - offset)
+      .unwrap_or(usize::MAX)
+      .min(buffer.len());
+
+    let mut file = self.file.lock().unwrap();
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer[..available])
+  }
+}
+
+impl StorageBackend for CopyOnWriteFile {
+  fn len(&self) -> io::Result<u64> {
+    Ok(self.overlay.lock().unwrap().len)
+  }
+
+  fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let overlay = self.overlay.lock().unwrap();
+
+    let end = offset + u64::try_from(len).unwrap();
+
+    if end > overlay.len {
+      return Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+          "read of {len} bytes at {offset} past end of {}",
+          overlay.len
+        ),
+      ));
+    }
+
+    let mut buffer = vec![0; len];
+
+    let mut position = offset;
+
+    while position < end {
+      let page = position / Self::PAGE_SIZE;
+      let start = position % Self::PAGE_SIZE;
+      let n = (Self::PAGE_SIZE - start).min(end - position);
+
+      let i = usize::try_from(position - offset).unwrap();
+      let slice = &mut buffer[i..i + usize::try_from(n).unwrap()];
+
+      match overlay.pages.get(&page) {
+        Some(data) => {
+          let start = usize::try_from(start).unwrap();
+          slice.copy_from_slice(&data[start..start + slice.len()]);
+        }
+        None => self.read_file(overlay.file_len, position, slice)?,
+      }
+
+      position += n;
+    }
+
+    Ok(buffer)
+  }
+
+  fn set_len(&self, len: u64) -> io::Result<()> {
+    let mut overlay = self.overlay.lock().unwrap();
+
+    // bytes past the new length must read as zeros if the storage is
+    // extended again, so they are hidden in the file, and dropped or
+    // zeroed in the overlay
+    overlay.file_len = overlay.file_len.min(len);
+    overlay.len = len;
+
+    let last_page = len / Self::PAGE_SIZE;
+    overlay.pages.retain(|page, _| *page <= last_page);
+
+    if let Some(data) = overlay.pages.get_mut(&last_page) {
+      data[usize::try_from(len % Self::PAGE_SIZE).unwrap()..].fill(0);
+    }
+
+    Ok(())
+  }
+
+  fn sync_data(&self, _eventual: bool) -> io::Result<()> {
+    Ok(())
+  }
+
+  fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+    let mut overlay = self.overlay.lock().unwrap();
+
+    let end = offset + u64::try_from(data.len()).unwrap();
+
+    if end > overlay.len {
+      return Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+          "write of {} bytes at {offset} past end of {}",
+          data.len(),
+          overlay.len
+        ),
+      ));
+    }
+
+    let mut position = offset;
+
+    while position < end {
+      let page = position / Self::PAGE_SIZE;
+      let start = usize::try_from(position % Self::PAGE_SIZE).unwrap();
+      let n = (Self::PAGE_SIZE - position % Self::PAGE_SIZE).min(end - position);
+
+      if !overlay.pages.contains_key(&page) {
+        let mut buffer = vec![0; usize::try_from(Self::PAGE_SIZE).unwrap()];
+        self.read_file(overlay.file_len, page * Self::PAGE_SIZE, &mut buffer)?;
+        overlay.pages.insert(page, buffer);
+      }
+
+      let i = usize::try_from(position - offset).unwrap();
+      let n = usize::try_from(n).unwrap();
+
+      overlay.pages.get_mut(&page).unwrap()[start..start + n].copy_from_slice(&data[i..i + n]);
+
+      position += u64::try_from(n).unwrap();
+    }
+
+    Ok(())
+  }
+}
+
+impl Index {
+  /// Path of the copy of the index at `path` which `bitomc index update
+  /// --follow` publishes, and `bitomc server --read-only` serves
+  pub(crate) fn read_only_path(path: &Path) -> PathBuf {
+    path.with_extension("read-only.redb")
+  }
+
+  pub(crate) fn open_read_only_database(
+    path: &Path,
+    cache_size: usize,
+  ) -> Result<(Database, SystemTime)> {
+    let file = match File::open(path) {
+      Ok(file) => file,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => bail!(
+        "no read-only copy of the index at `{}`, run `bitomc index update --follow` to publish one",
+        path.display()
+      ),
+      Err(err) => return Err(err).with_context(|| format!("failed to open `{}`", path.display())),
+    };
+
+    let modified = file.metadata()?.modified()?;
+
+    let database = Database::builder()
+      .set_cache_size(cache_size)
+      .create_with_backend(CopyOnWriteFile::new(file)?)?;
+
+    Ok((database, modified))
+  }
+
+  /// Close the index, and replace the published read-only copy with a copy
+  /// of it.
+  ///
+  /// redb only marks the file as cleanly closed once the database is
+  /// dropped, so the copy is taken after closing, and servers never have to
+  /// repair it. The copy is written next to the published copy and renamed
+  /// over it, so servers only ever see complete copies, and servers which
+  /// still have the previous copy open keep reading it until they reload.
+  /// Reorgs are rolled back by the writer before the copy is taken, so
+  /// published copies never contain partially rolled back state.
+  pub(crate) fn publish(self) -> Result {
+    ensure!(
+      self.read_only.is_none(),
+      "read-only index cannot be published"
+    );
+
+    let path = self.path.clone();
+
+    drop(self);
+
+    let published = Self::read_only_path(&path);
+
+    let mut partial = published.clone().into_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    fs::copy(&path, &partial).with_context(|| {
+      format!(
+        "failed to copy `{}` to `{}`",
+        path.display(),
+        partial.display()
+      )
+    })?;
+
+    File::open(&partial)?.sync_all()?;
+
+    fs::rename(&partial, &published).with_context(|| {
+      format!(
+        "failed to rename `{}` to `{}`",
+        partial.display(),
+        published.display()
+      )
+    })?;
+
+    Ok(())
+  }
+
+  /// Whether a newer copy of the index has been published since this
+  /// read-only index was opened
+  pub(crate) fn is_stale(&self) -> Result<bool> {
+    let Some(modified) = self.read_only else {
+      return Ok(false);
+    };
+
+    match fs::metadata(&self.path) {
+      Ok(metadata) => Ok(metadata.modified()? != modified),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+      Err(err) => Err(err.into()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, crate::index::testing::Context, tempfile::TempDir};
+
+  fn backend(contents: &[u8]) -> (TempDir, CopyOnWriteFile) {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("file");
+    fs::write(&path, contents).unwrap();
+    let backend = CopyOnWriteFile::new(File::open(&path).unwrap()).unwrap();
+    (tempdir, backend)
+  }
+
+  #[test]
+  fn writes_are_kept_in_memory() {
+    let contents = (0..10000)
+      .map(|i| u8::try_from(i % 251).unwrap())
+      .collect::<Vec<u8>>();
+
+    let (tempdir, backend) = backend(&contents);
+
+    assert_eq!(backend.len().unwrap(), 10000);
+    assert_eq!(backend.read(0, 10000).unwrap(), contents);
+
+    backend.write(4000, &[0xff; 200]).unwrap();
+
+    let mut expected = contents.clone();
+    expected[4000..4200].fill(0xff);
+
+    assert_eq!(backend.read(0, 10000).unwrap(), expected);
+    assert_eq!(backend.read(4090, 20).unwrap(), expected[4090..4110]);
+
+    assert_eq!(fs::read(tempdir.path().join("file")).unwrap(), contents);
+  }
+
+  #[test]
+  fn extended_storage_reads_as_zeros() {
+    let (_tempdir, backend) = backend(&[1; 100]);
+
+    backend.write(50, &[2; 10]).unwrap();
+
+    backend.set_len(40).unwrap();
+
+    assert_eq!(backend.len().unwrap(), 40);
+    assert!(backend.read(0, 50).is_err());
+    assert!(backend.write(30, &[0; 20]).is_err());
+
+    backend.set_len(8192).unwrap();
+
+    let mut expected = vec![0; 8192];
+    expected[..40].fill(1);
+
+    assert_eq!(backend.read(0, 8192).unwrap(), expected);
+
+    backend.write(8000, &[3; 100]).unwrap();
+    expected[8000..8100].fill(3);
+
+    assert_eq!(backend.read(0, 8192).unwrap(), expected);
+  }
+
+  #[test]
+  fn published_copies_are_served_read_only() {
+    let context = Context::builder().build();
+
+    let writer = context.open_index::<&str, _>([]).unwrap();
+
+    let settings = writer.settings.clone();
+
+    context.mine_blocks(2);
+
+    writer.update().unwrap();
+    writer.publish().unwrap();
+
+    let published = Index::read_only_path(settings.index());
+    let contents = fs::read(&published).unwrap();
+
+    let reader = Index::open_read_only(&settings).unwrap();
+    let other_reader = Index::open_read_only(&settings).unwrap();
+
+    assert_eq!(reader.block_count().unwrap(), 3);
+    assert_eq!(other_reader.block_count().unwrap(), 3);
+    assert_eq!(reader.runes().unwrap(), context.index.runes().unwrap());
+    assert!(!reader.is_stale().unwrap());
+
+    assert_eq!(
+      reader.update().unwrap_err().to_string(),
+      "read-only index cannot be updated"
+    );
+
+    drop(other_reader);
+
+    assert_eq!(fs::read(&published).unwrap(), contents);
+
+    let writer = Index::open(&settings).unwrap();
+
+    context.mine_blocks(1);
+
+    writer.update().unwrap();
+    writer.publish().unwrap();
+
+    assert!(reader.is_stale().unwrap());
+    assert_eq!(reader.block_count().unwrap(), 3);
+
+    let reader = Index::open_read_only(&settings).unwrap();
+
+    assert!(!reader.is_stale().unwrap());
+    assert_eq!(reader.block_count().unwrap(), 4);
+    assert_eq!(
+      reader.block_hash(None).unwrap(),
+      context.index.block_hash(None).unwrap()
+    );
+  }
+
+  #[test]
+  fn missing_copy() {
+    let context = Context::builder().build();
+
+    let settings = context.index.settings.clone();
+
+    assert_eq!(
+      Index::open_read_only(&settings).err().unwrap().to_string(),
+      format!(
+        "no read-only copy of the index at `{}`, run `bitomc index update --follow` to publish one",
+        Index::read_only_path(settings.index()).display(),
+      ),
+    );
+  }
+}
@@ -38,9 +38,8 @@ impl Reorg {
         for depth in 1..max_recoverable_reorg_depth {
           let index_block_hash = index.block_hash(height.checked_sub(depth))?;
           let bitcoind_block_hash = index
-            .client
-            .get_block_hash(u64::from(height.saturating_sub(depth)))
-            .into_option()?;
+            .chain_source
+            .block_hash(height.saturating_sub(depth))?;
 
           if index_block_hash == bitcoind_block_hash {
             return Err(anyhow!(reorg::Error::Recoverable { height, depth }));
@@ -70,10 +69,20 @@ impl Reorg {
     Index::increment_statistic(&wtx, Statistic::Commits, 1)?;
     wtx.commit()?;
 
-    log::info!(
-      "successfully rolled back database to height {}",
-      index.begin_read()?.block_count()?
-    );
+    let block_count = index.begin_read()?.block_count()?;
+
+    log::info!("successfully rolled back database to height {block_count}");
+
+    if let Some(sender) = &index.event_sender {
+      sender.send(Event::ReorgRolledBack {
+        block_height: block_count,
+        depth,
+      })?;
+    }
+
+    if let Some(webhooks) = index.webhooks() {
+      webhooks.enqueue()?;
+    }
 
     Ok(())
   }
@@ -84,15 +93,9 @@ impl Reorg {
     }
 
     if (height < SAVEPOINT_INTERVAL || height % SAVEPOINT_INTERVAL == 0)
-      && u32::try_from(
-        index
-          .settings
-          .bitcoin_rpc_client(None)?
-          .get_blockchain_info()?
-          .headers,
-      )
-      .unwrap()
-      .saturating_sub(height)
+      && u32::try_from(index.chain_source.block_count()?)
+        .unwrap()
+        .saturating_sub(height)
         <= CHAIN_TIP_DISTANCE
     {
       let wtx = index.begin_write()?;
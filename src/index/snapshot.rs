@@ -0,0 +1,298 @@
+use super::*;
+
+/// Rune state of an index at a single height, from which a fresh index can
+/// be bootstrapped and continue syncing at the next height.
+///
+/// Histories, such as conversions, mints, and per-height supply and util
+/// states, are not included, and an imported index only records them from
+/// the height after the snapshot.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+  pub version: u64,
+  pub chain: Chain,
+  pub height: u32,
+  pub block_hash: BlockHash,
+  pub state_commitment: Option<sha256::Hash>,
+  pub previous_state_commitment: Option<sha256::Hash>,
+  pub balance_changes: Option<sha256::Hash>,
+  pub rune_entries: Vec<(RuneId, RuneEntry)>,
+  pub balances: Vec<(OutPoint, Vec<(RuneId, u128)>)>,
+  pub util_entry: (u32, Vec<u128>, u128),
+  pub mint_chain: (OutPoint, u64),
+  pub conversion_chain: (OutPoint, u64),
+  pub unclaimed_reward: Option<(u128, u128)>,
+}
+
+impl Snapshot {
+  pub const VERSION: u64 = 2;
+}
+
+impl Index {
+  /// Snapshot of the index at `height`, which must be its tip. The index is
+  /// not updated, so an index can be held at `height` with `--height-limit`.
+  pub(crate) fn export(&self, height: u32) -> Result<Snapshot> {
+    let rtx = self.database.begin_read()?;
+
+    let Some((tip, header)) = rtx
+      .open_table(HEIGHT_TO_BLOCK_HEADER)?
+      .last()?
+      .map(|(height, header)| (height.value(), Header::load(*header.value())))
+    else {
+      bail!("index has no blocks");
+    };
+
+    ensure!(
+      tip == height,
+      "index is at height {tip}, not {height}, export its tip or rebuild it with `--height-limit {}` to export height {height}",
+      height + 1,
+    );
+
+    let mut rune_entries = Vec::new();
+    for result in rtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?.iter()? {
+      let (id, entry) = result?;
+      rune_entries.push((RuneId::load(id.value()), RuneEntry::load(entry.value())));
+    }
+
+    let mut balances = Vec::new();
+    for result in rtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?.iter()? {
+      let (outpoint, buffer) = result?;
+      let buffer = buffer.value();
+      let mut output_balances = Vec::new();
+      let mut i = 0;
+      while i < buffer.len() {
+        let (balance, len) = Self::decode_rune_balance(&buffer[i..])?;
+        i += len;
+        output_balances.push(balance);
+      }
+      balances.push((OutPoint::load(*outpoint.value()), output_balances));
+    }
+
+    let height_to_state_commitment = rtx.open_table(HEIGHT_TO_STATE_COMMITMENT)?;
+
+    Ok(Snapshot {
+      version: Snapshot::VERSION,
+      chain: self.settings.chain(),
+      height,
+      block_hash: header.block_hash(),
+      state_commitment: height_to_state_commitment
+        .get(height)?
+        .map(|commitment| sha256::Hash::from_byte_array(*commitment.value())),
+      previous_state_commitment: height
+        .checked_sub(1)
+        .map(|height| height_to_state_commitment.get(height))
+        .transpose()?
+        .flatten()
+        .map(|commitment| sha256::Hash::from_byte_array(*commitment.value())),
+      balance_changes: rtx
+        .open_table(HEIGHT_TO_BALANCE_CHANGES)?
+        .get(height)?
+        .map(|digest| sha256::Hash::from_byte_array(*digest.value())),
+      rune_entries,
+      balances,
+      util_entry: rtx.open_table(UTIL_ENTRY)?.get(0)?.unwrap().value(),
+      mint_chain: self.get_last_outpoint_txout_for_state_change(StateChange::Mint)?,
+      conversion_chain: self.get_last_outpoint_txout_for_state_change(StateChange::Convert)?,
+      unclaimed_reward: rtx
+        .open_table(UNCLAIMED_REWARD)?
+        .get(0)?
+        .map(|reward| reward.value()),
+    })
+  }
+
+  /// Bootstrap an empty index from a snapshot, after checking that the
+  /// snapshot's block is in bitcoind's active chain and that its state
+  /// commitment matches its contents
+  pub(crate) fn import(&self, snapshot: Snapshot) -> Result {
+    ensure!(
+      snapshot.version == Snapshot::VERSION,
+      "unsupported snapshot version {}, expected {}",
+      snapshot.version,
+      Snapshot::VERSION,
+    );
+
+    ensure!(
+      snapshot.chain == self.settings.chain(),
+      "snapshot is for {}, not {}",
+      snapshot.chain,
+      self.settings.chain(),
+    );
+
+    ensure!(
+      !self.index_addresses,
+      "snapshots cannot be imported into an index with an address index",
+    );
+
+    ensure!(
+      !self.index_transactions,
+      "snapshots cannot be imported into an index with a transaction index",
+    );
+
+    let block_hash = self
+      .chain_source
+      .block_hash(snapshot.height)?
+      .with_context(|| format!("no block at snapshot height {}", snapshot.height))?;
+
+    ensure!(
+      block_hash == snapshot.block_hash,
+      "snapshot block {} at height {} is not in the active chain, which has block {block_hash}",
+      snapshot.block_hash,
+      snapshot.height,
+    );
+
+    let mut supplies = HashMap::<RuneId, u128>::new();
+    for (_outpoint, balances) in &snapshot.balances {
+      for (id, balance) in balances {
+        *supplies.entry(*id).or_default() += balance;
+      }
+    }
+
+    for (id, entry) in &snapshot.rune_entries {
+      ensure!(
+        supplies.get(id).copied().unwrap_or_default() + entry.burned == entry.supply,
+        "snapshot balances of rune {id} do not match its supply",
+      );
+    }
+
+    let supply_state = {
+      let entry = |id| {
+        snapshot
+          .rune_entries
+          .iter()
+          .find(|(entry_id, _entry)| *entry_id == id)
+          .map(|(_id, entry)| *entry)
+          .with_context(|| format!("snapshot is missing rune {id}"))
+      };
+
+      let entry0 = entry(ID0)?;
+      let entry1 = entry(ID1)?;
+
+      api::SupplyState {
+        supply0: entry0.supply,
+        supply1: entry1.supply,
+        burned0: entry0.burned,
+        burned1: entry1.burned,
+      }
+    };
+
+    // balances are only committed to by the changes made in the snapshot's
+    // block, earlier changes are covered by the previous commitment
+    if let Some(state_commitment) = snapshot.state_commitment {
+      let balance_changes = snapshot.balance_changes.context(
+        "snapshot state commitment cannot be checked without the balance changes at its height",
+      )?;
+
+      let recomputed = StateCommitment {
+        balance_changes,
+        conversion_chain: snapshot.conversion_chain,
+        height: snapshot.height,
+        mint_chain: snapshot.mint_chain,
+        previous: snapshot.previous_state_commitment,
+        supply_state,
+        util_entry: &snapshot.util_entry,
+      }
+      .hash();
+
+      ensure!(
+        recomputed == state_commitment,
+        "snapshot state commitment {state_commitment} does not match its contents, which commit to {recomputed}",
+      );
+    }
+
+    let header = self
+      .chain_source
+      .block_header(block_hash)?
+      .with_context(|| format!("missing header for block {block_hash}"))?;
+
+    let wtx = self.begin_write()?;
+
+    ensure!(
+      wtx.open_table(HEIGHT_TO_BLOCK_HEADER)?.is_empty()?,
+      "snapshots can only be imported into an empty index",
+    );
+
+    wtx
+      .open_table(HEIGHT_TO_BLOCK_HEADER)?
+      .insert(snapshot.height, &header.store())?;
+
+    {
+      let mut rune_id_to_rune_entry = wtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?;
+      for (id, entry) in snapshot.rune_entries {
+        rune_id_to_rune_entry.insert(id.store(), entry.store())?;
+      }
+    }
+
+    {
+      let mut outpoint_to_rune_balances = wtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?;
+      let mut buffer = Vec::new();
+      for (outpoint, balances) in snapshot.balances {
+        buffer.clear();
+        for (id, balance) in balances {
+          Self::encode_rune_balance(id, balance, &mut buffer);
+        }
+        outpoint_to_rune_balances.insert(&outpoint.store(), buffer.as_slice())?;
+      }
+    }
+
+    let util_entry = UtilEntry::load(snapshot.util_entry);
+
+    if snapshot.height >= self.settings.first_rune_height() {
+      if let Some(rate) = UtilEntry::rate(supply_state.supply0, supply_state.supply1) {
+        wtx
+          .open_table(HEIGHT_TO_RATE)?
+          .insert(snapshot.height, rate)?;
+      }
+
+      wtx
+        .open_table(HEIGHT_TO_SUPPLY_STATE)?
+        .insert(snapshot.height, supply_state.store())?;
+
+      wtx
+        .open_table(HEIGHT_TO_UTIL_STATE)?
+        .insert(snapshot.height, util_entry.state().store())?;
+    }
+
+    if let Some(commitment) = snapshot.state_commitment {
+      let mut height_to_state_commitment = wtx.open_table(HEIGHT_TO_STATE_COMMITMENT)?;
+
+      height_to_state_commitment.insert(snapshot.height, &commitment.to_byte_array())?;
+
+      // kept so that the imported index can export the same snapshot
+      if let Some(previous) = snapshot.previous_state_commitment {
+        height_to_state_commitment.insert(snapshot.height - 1, &previous.to_byte_array())?;
+      }
+    }
+
+    if let Some(balance_changes) = snapshot.balance_changes {
+      wtx
+        .open_table(HEIGHT_TO_BALANCE_CHANGES)?
+        .insert(snapshot.height, &balance_changes.to_byte_array())?;
+    }
+
+    wtx.open_table(UTIL_ENTRY)?.insert(0, util_entry.store())?;
+
+    for (state_change, (outpoint, value)) in [
+      (StateChange::Mint, snapshot.mint_chain),
+      (StateChange::Convert, snapshot.conversion_chain),
+    ] {
+      wtx
+        .open_table(STATE_CHANGE_TO_LAST_OUTPOINT)?
+        .insert(&state_change.key(), &outpoint.store())?;
+      wtx
+        .open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)?
+        .insert(&state_change.key(), &value)?;
+    }
+
+    {
+      let mut unclaimed_reward = wtx.open_table(UNCLAIMED_REWARD)?;
+
+      match snapshot.unclaimed_reward {
+        Some(reward) => unclaimed_reward.insert(0, reward)?,
+        None => unclaimed_reward.remove(0)?,
+      };
+    }
+
+    wtx.commit()?;
+
+    Ok(())
+  }
+}
@@ -132,6 +132,35 @@ impl Context {
     }
   }
 
+  /// Open another index on the same chain, with its own data dir, and
+  /// settings from `args` and the config file they name
+  pub(crate) fn open_index<T: Into<OsString>, I: IntoIterator<Item = T>>(
+    &self,
+    args: I,
+  ) -> Result<Index> {
+    let data_dir = TempDir::new_in(self.tempdir.path())?.into_path();
+
+    let command: Vec<OsString> = vec![
+      "bitomc".into(),
+      "--bitcoin-rpc-url".into(),
+      self.core.url().into(),
+      "--datadir".into(),
+      data_dir.into(),
+      "--cookie-file".into(),
+      self.tempdir.path().join("cookie").into(),
+      format!("--chain={}", self.index.settings.chain()).into(),
+    ];
+
+    Index::open(&Settings::merge(
+      Options::try_parse_from(
+        command
+          .into_iter()
+          .chain(args.into_iter().map(|arg| arg.into())),
+      )?,
+      BTreeMap::new(),
+    )?)
+  }
+
   pub(crate) fn get_block_count(&self) -> usize {
     usize::try_from(self.index.block_count().unwrap()).unwrap()
   }
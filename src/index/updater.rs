@@ -1,6 +1,6 @@
 use {
-  self::rune_updater::RuneUpdater,
-  super::{fetcher::Fetcher, *},
+  self::{overlay::Overlay, rune_updater::RuneUpdater},
+  super::{blk_files::BlkFiles, fetcher::Fetcher, *},
   futures::future::try_join_all,
   tokio::sync::{
     broadcast::{self, error::TryRecvError},
@@ -8,8 +8,13 @@ use {
   },
 };
 
+mod overlay;
 mod rune_updater;
 
+pub(super) const BLK_FILES_TIP_DISTANCE: u32 = 100;
+
+const HEADER_BATCH_SIZE: u32 = 2000;
+
 pub(crate) struct BlockData {
   pub(crate) header: Header,
   pub(crate) txdata: Vec<(Transaction, Txid)>,
@@ -40,7 +45,7 @@ pub(crate) struct Updater<'index> {
 impl<'index> Updater<'index> {
   pub(crate) fn update_index(&mut self, mut wtx: WriteTransaction) -> Result {
     let start = Instant::now();
-    let starting_height = u32::try_from(self.index.client.get_block_count()?).unwrap() + 1;
+    let starting_height = u32::try_from(self.index.chain_source.block_count()?).unwrap() + 1;
     let starting_index_height = self.height;
 
     wtx
@@ -88,7 +93,7 @@ impl<'index> Updater<'index> {
         progress_bar.inc(1);
 
         if progress_bar.position() > progress_bar.length().unwrap() {
-          if let Ok(count) = self.index.client.get_block_count() {
+          if let Ok(count) = self.index.chain_source.block_count() {
             progress_bar.set_length(count + 1);
           } else {
             log::warn!("Failed to fetch latest block height");
@@ -156,29 +161,107 @@ impl<'index> Updater<'index> {
 
     let height_limit = index.height_limit;
 
-    let client = index.settings.bitcoin_rpc_client(None)?;
+    let chain_source = index.settings.chain_source()?;
 
-    let first_rune_height = index.first_rune_height;
+    let prune_height = chain_source.prune_height()?;
 
-    thread::spawn(move || loop {
-      if let Some(height_limit) = height_limit {
-        if height >= height_limit {
-          break;
-        }
-      }
+    *index.prune_height.lock().unwrap() = prune_height;
+
+    // blocks before the first rune height carry no rune state, so only their
+    // headers are needed, unless they are needed for the address or
+    // transaction indexes, and the transaction index skips pruned blocks
+    let headers_only_until = if index.index_addresses || !index.headers_only {
+      0
+    } else if index.index_transactions {
+      prune_height
+        .unwrap_or_default()
+        .min(index.first_rune_height)
+    } else {
+      index.first_rune_height
+    };
+
+    if let Some(prune_height) = prune_height {
+      let needed = height.max(headers_only_until);
+
+      ensure!(
+        prune_height <= needed,
+        "bitcoind has pruned blocks {needed} through {}, which are needed to index {}",
+        prune_height - 1,
+        if index.index_addresses {
+          "addresses"
+        } else {
+          "runes"
+        },
+      );
+    }
 
-      match Self::get_block_with_retries(&client, height, first_rune_height) {
-        Ok(Some(block)) => {
-          if let Err(err) = tx.send(block.into()) {
-            log::info!("Block receiver disconnected: {err}");
+    let blk_files_dir = index.settings.blk_files_dir();
+
+    let network = index.settings.chain().network();
+
+    thread::spawn(move || {
+      let blk_files = blk_files_dir.and_then(|dir| {
+        Self::open_blk_files(&*chain_source, &dir, network, height).unwrap_or_else(|err| {
+          log::warn!(
+            "failed to read blk files in {}, fetching blocks over RPC: {err}",
+            dir.display()
+          );
+          None
+        })
+      });
+
+      loop {
+        if let Some(height_limit) = height_limit {
+          if height >= height_limit {
             break;
           }
-          height += 1;
         }
-        Ok(None) => break,
-        Err(err) => {
-          log::error!("failed to fetch block {height}: {err}");
-          break;
+
+        let block = blk_files.as_ref().and_then(|(blk_files, chain)| {
+          let hash = *chain.get(height.into_usize())?;
+          Self::get_block_from_blk_files(blk_files, hash, height, headers_only_until)
+            .unwrap_or_else(|err| {
+              log::warn!("failed to read block {height} from blk files: {err}");
+              None
+            })
+        });
+
+        let blocks = match block {
+          Some(block) => Ok(vec![block]),
+          None if height < headers_only_until => {
+            let end = headers_only_until
+              .min(height_limit.unwrap_or(u32::MAX))
+              .min(height.saturating_add(HEADER_BATCH_SIZE));
+
+            Self::with_retries(height, || chain_source.block_headers(height..end)).map(|headers| {
+              headers
+                .into_iter()
+                .map(|header| Block {
+                  header,
+                  txdata: Vec::new(),
+                })
+                .collect()
+            })
+          }
+          None => Self::with_retries(height, || chain_source.block(height))
+            .map(|block| block.into_iter().collect()),
+        };
+
+        match blocks {
+          Ok(blocks) if blocks.is_empty() => break,
+          Ok(blocks) => {
+            for block in blocks {
+              if let Err(err) = tx.send(block.into()) {
+                log::info!("Block receiver disconnected: {err}");
+                return;
+              }
+              height += 1;
+            }
+          }
+          Err(err) => {
+            log::error!("failed to fetch block {height}: {err}");
+            break;
+          }
         }
       }
     });
@@ -186,30 +269,71 @@ impl<'index> Updater<'index> {
     Ok(rx)
   }
 
-  fn get_block_with_retries(
-    client: &Client,
+  /// Open the blk files in `dir`, and recover the hashes of the blocks in the
+  /// active chain up to `BLK_FILES_TIP_DISTANCE` blocks below the tip. Blocks
+  /// nearer the tip may still be being written or be reorged, so they are
+  /// always fetched over RPC, and blk files are not read at all if the index
+  /// is already within that distance of the tip.
+  fn open_blk_files(
+    chain_source: &dyn ChainSource,
+    dir: &Path,
+    network: Network,
     height: u32,
-    first_rune_height: u32,
+  ) -> Result<Option<(BlkFiles, Vec<BlockHash>)>> {
+    let block_count = u32::try_from(chain_source.block_count()?)?;
+
+    let Some(tip) = block_count
+      .checked_sub(BLK_FILES_TIP_DISTANCE)
+      .filter(|tip| *tip >= height)
+    else {
+      return Ok(None);
+    };
+
+    let tip_hash = chain_source
+      .block_hash(tip)?
+      .with_context(|| format!("no block at height {tip}"))?;
+
+    let blk_files = BlkFiles::open(dir, network)?;
+
+    let Some(chain) = blk_files.chain(tip_hash) else {
+      bail!("block {tip_hash} at height {tip} or one of its ancestors is missing");
+    };
+
+    ensure!(
+      chain.len() == tip.into_usize() + 1,
+      "block {tip_hash} at height {tip} has {} ancestors",
+      chain.len() - 1,
+    );
+
+    log::info!(
+      "Reading blocks {height} through {tip} from blk files in {}, which contain {} blocks",
+      dir.display(),
+      blk_files.len(),
+    );
+
+    Ok(Some((blk_files, chain)))
+  }
+
+  fn get_block_from_blk_files(
+    blk_files: &BlkFiles,
+    hash: BlockHash,
+    height: u32,
+    headers_only_until: u32,
   ) -> Result<Option<Block>> {
+    if height >= headers_only_until {
+      blk_files.block(hash)
+    } else {
+      Ok(blk_files.header(hash)?.map(|header| Block {
+        header,
+        txdata: Vec::new(),
+      }))
+    }
+  }
+
+  fn with_retries<T>(height: u32, mut fetch: impl FnMut() -> Result<T>) -> Result<T> {
     let mut errors = 0;
     loop {
-      match client
-        .get_block_hash(height.into())
-        .into_option()
-        .and_then(|option| {
-          option
-            .map(|hash| {
-              if height >= first_rune_height {
-                Ok(client.get_block(&hash)?)
-              } else {
-                Ok(Block {
-                  header: client.get_block_header(&hash)?,
-                  txdata: Vec::new(),
-                })
-              }
-            })
-            .transpose()
-        }) {
+      match fetch() {
         Err(err) => {
           if cfg!(test) {
             return Err(err);
@@ -372,6 +496,10 @@ impl<'index> Updater<'index> {
       }
     }
 
+    // script pubkeys of outputs spent in this block, used to debit address
+    // rune balances after the spent outputs have been removed from the cache
+    let mut spent_script_pubkeys = HashMap::new();
+
     if let Some(address_txout_receiver) = address_txout_receiver {
       let mut script_pubkey_to_outpoint = wtx.open_multimap_table(SCRIPT_PUBKEY_TO_OUTPOINT)?;
       for (tx, txid) in &block.txdata {
@@ -382,6 +510,7 @@ impl<'index> Updater<'index> {
           utxo_cache,
           &mut script_pubkey_to_outpoint,
           &mut outpoint_to_txout,
+          &mut spent_script_pubkeys,
         )?;
       }
     };
@@ -389,22 +518,49 @@ impl<'index> Updater<'index> {
     let mut height_to_block_header = wtx.open_table(HEIGHT_TO_BLOCK_HEADER)?;
 
     if self.height >= self.index.settings.first_rune_height() {
+      let mut conversion_number_to_txid = wtx.open_table(CONVERSION_NUMBER_TO_TXID)?;
+      let mut height_to_mint = wtx.open_table(HEIGHT_TO_MINT)?;
       let mut outpoint_to_rune_balances = wtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?;
       let mut rune_id_to_rune_entry = wtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?;
+      let mut script_pubkey_to_rune_balance = self
+        .index
+        .index_addresses
+        .then(|| wtx.open_table(SCRIPT_PUBKEY_TO_RUNE_BALANCE))
+        .transpose()?;
       let mut state_change_to_last_outpoint = wtx.open_table(STATE_CHANGE_TO_LAST_OUTPOINT)?;
       let mut state_change_to_last_txout_value =
         wtx.open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)?;
+      let mut txid_to_conversion = wtx.open_table(TXID_TO_CONVERSION)?;
+      let mut txid_to_failed_conversion = wtx.open_table(TXID_TO_FAILED_CONVERSION)?;
+      let mut unclaimed_reward = wtx.open_table(UNCLAIMED_REWARD)?;
       let mut util_entry_table = wtx.open_table(UTIL_ENTRY)?;
 
+      let ledger = RuneUpdater::load_ledger(
+        self.index.rules(self.height),
+        &rune_id_to_rune_entry,
+        &state_change_to_last_outpoint,
+        &state_change_to_last_txout_value,
+        &unclaimed_reward,
+      )?;
+
       let mut rune_updater = RuneUpdater {
+        balance_changes: BalanceChanges::default(),
+        conversion_number_to_txid: &mut conversion_number_to_txid,
         event_sender: self.index.event_sender.as_ref(),
-        burned: HashMap::new(),
         height: self.height,
+        height_to_mint: &mut height_to_mint,
         id_to_entry: &mut rune_id_to_rune_entry,
+        ledger,
         outpoint_to_balances: &mut outpoint_to_rune_balances,
+        script_pubkey_to_balance: script_pubkey_to_rune_balance
+          .as_mut()
+          .map(|table| table as _),
+        spent_script_pubkeys: &spent_script_pubkeys,
         state_change_to_last_outpoint: &mut state_change_to_last_outpoint,
         state_change_to_last_txout_value: &mut state_change_to_last_txout_value,
-        require_conversion_outpoint: true,
+        txid_to_conversion: &mut txid_to_conversion,
+        txid_to_failed_conversion: &mut txid_to_failed_conversion,
+        unclaimed_reward: &mut unclaimed_reward,
       };
 
       rune_updater.update_supply()?;
@@ -413,13 +569,76 @@ impl<'index> Updater<'index> {
         rune_updater.index_runes(tx, *txid)?;
       }
 
-      rune_updater.update_burned()?;
+      rune_updater.update_entries()?;
+
+      let state = rune_updater.ledger.state;
+      let mut util_entry = UtilEntry::load(util_entry_table.get(0)?.unwrap().value());
+
+      let rate = util_entry.update(state.supply0, state.supply1);
+
+      if let Some(rate) = rate {
+        wtx.open_table(HEIGHT_TO_RATE)?.insert(self.height, rate)?;
+      }
+
+      if let Some(sender) = &self.index.event_sender {
+        sender.send(Event::UtilStateUpdated {
+          block_height: self.height,
+          rate,
+          supply_state: state,
+          util_state: util_entry.state(),
+        })?;
+      }
+
+      wtx
+        .open_table(HEIGHT_TO_SUPPLY_STATE)?
+        .insert(self.height, state.store())?;
+      wtx
+        .open_table(HEIGHT_TO_UTIL_STATE)?
+        .insert(self.height, util_entry.state().store())?;
+
+      let util_entry = util_entry.store();
+
+      let mut height_to_state_commitment = wtx.open_table(HEIGHT_TO_STATE_COMMITMENT)?;
+
+      let previous = self
+        .height
+        .checked_sub(1)
+        .map(|height| height_to_state_commitment.get(height))
+        .transpose()?
+        .flatten()
+        .map(|commitment| sha256::Hash::from_byte_array(*commitment.value()));
+
+      // commitments are only meaningful if they chain back to the first
+      // rune height, which is not the case for migrated indexes
+      if previous.is_some() || self.height == self.index.settings.first_rune_height() {
+        let balance_changes = rune_updater.balance_changes.finish();
+
+        let commitment = StateCommitment {
+          balance_changes,
+          conversion_chain: (
+            rune_updater.ledger.conversion_outpoint,
+            rune_updater.ledger.conversion_value,
+          ),
+          height: self.height,
+          mint_chain: (
+            rune_updater.ledger.mint_outpoint,
+            rune_updater.ledger.mint_value,
+          ),
+          previous,
+          supply_state: state,
+          util_entry: &util_entry,
+        }
+        .hash();
+
+        height_to_state_commitment.insert(self.height, &commitment.to_byte_array())?;
 
-      if let Some(state) = rune_updater.get_state()? {
-        let mut util_entry = UtilEntry::load(util_entry_table.get(0)?.unwrap().value());
-        util_entry.update(state.supply0, state.supply1);
-        util_entry_table.insert(0, util_entry.store())?;
+        // stored so that snapshots can carry everything needed to recompute
+        // their commitment
+        wtx
+          .open_table(HEIGHT_TO_BALANCE_CHANGES)?
+          .insert(self.height, &balance_changes.to_byte_array())?;
       }
+      util_entry_table.insert(0, util_entry)?;
     }
 
     height_to_block_header.insert(&self.height, &block.header.store())?;
@@ -437,6 +656,7 @@ impl<'index> Updater<'index> {
     utxo_cache: &mut HashMap<OutPoint, TxOut>,
     script_pubkey_to_outpoint: &mut MultimapTable<&[u8], OutPointValue>,
     outpoint_to_txout: &mut Table<&OutPointValue, TxOutValue>,
+    spent_script_pubkeys: &mut HashMap<OutPoint, ScriptBuf>,
   ) -> Result {
     for txin in &tx.input {
       let output = txin.previous_output;
@@ -461,6 +681,7 @@ impl<'index> Updater<'index> {
       utxo_cache.remove(&output);
       outpoint_to_txout.remove(&output.store())?;
       script_pubkey_to_outpoint.remove(&txout.script_pubkey.as_bytes(), output.store())?;
+      spent_script_pubkeys.insert(output, txout.script_pubkey);
     }
 
     for (vout, txout) in tx.output.iter().enumerate() {
@@ -493,59 +714,141 @@ impl<'index> Updater<'index> {
       }
     }
 
+    let block_height = self.height.saturating_sub(1);
+
+    let block_hash = wtx
+      .open_table(HEIGHT_TO_BLOCK_HEADER)?
+      .get(block_height)?
+      .map(|header| Header::load(*header.value()).block_hash());
+
+    let block_committed = block_hash.map(|block_hash| Event::BlockCommitted {
+      block_hash,
+      block_height,
+    });
+
+    // events are written to the webhook outbox before the blocks which
+    // emitted them are committed, so they are delivered even if the process
+    // stops right after committing, at the cost of delivering them again if
+    // it stops right before
+    if let Some(webhooks) = self.index.webhooks() {
+      if let Some(event) = &block_committed {
+        webhooks.push(event.clone());
+      }
+
+      webhooks.enqueue()?;
+    }
+
     Index::increment_statistic(&wtx, Statistic::Commits, 1)?;
     wtx.commit()?;
 
+    if let (Some(channel), Some(event)) = (
+      self
+        .index
+        .event_sender
+        .as_ref()
+        .and_then(|sender| sender.channel.as_ref()),
+      block_committed,
+    ) {
+      channel.blocking_send(event)?;
+    }
+
     Reorg::update_savepoints(self.index, self.height)?;
 
     Ok(())
   }
 
   pub fn simulate(
-    wtx: WriteTransaction,
+    rtx: ReadTransaction,
     index: &'index Index,
     height: u32,
     transactions: Vec<Transaction>,
-  ) -> Result<Vec<api::SupplyState>> {
+  ) -> Result<Option<(api::SupplyState, Vec<api::Simulation>)>> {
     if height < index.settings.first_rune_height() {
-      return Ok(Vec::new());
+      return Ok(None);
     }
 
-    let mut id_to_entry = wtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?;
-    let mut outpoint_to_balances = wtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?;
-    let mut state_change_to_last_outpoint = wtx.open_table(STATE_CHANGE_TO_LAST_OUTPOINT)?;
-    let mut state_change_to_last_txout_value = wtx.open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)?;
+    let util_entry = UtilEntry::load(rtx.open_table(UTIL_ENTRY)?.get(0)?.unwrap().value());
+
+    let mut conversion_number_to_txid = Overlay::new(rtx.open_table(CONVERSION_NUMBER_TO_TXID)?)?;
+    let mut height_to_mint = Overlay::new(rtx.open_table(HEIGHT_TO_MINT)?)?;
+    let mut id_to_entry = Overlay::new(rtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?)?;
+    let mut outpoint_to_balances = Overlay::new(rtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?)?;
+    let mut state_change_to_last_outpoint =
+      Overlay::new(rtx.open_table(STATE_CHANGE_TO_LAST_OUTPOINT)?)?;
+    let mut state_change_to_last_txout_value =
+      Overlay::new(rtx.open_table(STATE_CHANGE_TO_LAST_TXOUT_VALUE)?)?;
+    let mut txid_to_conversion = Overlay::new(rtx.open_table(TXID_TO_CONVERSION)?)?;
+    let mut txid_to_failed_conversion = Overlay::new(rtx.open_table(TXID_TO_FAILED_CONVERSION)?)?;
+    let mut unclaimed_reward = Overlay::new(rtx.open_table(UNCLAIMED_REWARD)?)?;
+
+    let ledger = RuneUpdater::load_ledger(
+      index.rules(height),
+      &id_to_entry,
+      &state_change_to_last_outpoint,
+      &state_change_to_last_txout_value,
+      &unclaimed_reward,
+    )?;
 
     let mut rune_updater = RuneUpdater {
+      balance_changes: BalanceChanges::default(),
+      conversion_number_to_txid: &mut conversion_number_to_txid,
       event_sender: None,
-      burned: HashMap::new(),
       height,
+      height_to_mint: &mut height_to_mint,
       id_to_entry: &mut id_to_entry,
+      ledger,
       outpoint_to_balances: &mut outpoint_to_balances,
+      script_pubkey_to_balance: None,
+      spent_script_pubkeys: &HashMap::new(),
       state_change_to_last_outpoint: &mut state_change_to_last_outpoint,
       state_change_to_last_txout_value: &mut state_change_to_last_txout_value,
-      require_conversion_outpoint: true,
+      txid_to_conversion: &mut txid_to_conversion,
+      txid_to_failed_conversion: &mut txid_to_failed_conversion,
+      unclaimed_reward: &mut unclaimed_reward,
     };
 
     rune_updater.update_supply()?;
 
-    let mut states = Vec::new();
-    for tx in &transactions {
-      rune_updater.index_runes(tx, tx.txid())?;
-
-      rune_updater.update_burned()?;
+    let initial = rune_updater.ledger.state;
 
-      if let Some(state) = rune_updater.get_state()? {
-        states.push(state);
-      }
-    }
-
-    if transactions.is_empty() {
-      if let Some(state) = rune_updater.get_state()? {
-        states.push(state);
-      }
+    let mut simulations = Vec::new();
+    for tx in &transactions {
+      let txid = tx.txid();
+
+      let outcome = rune_updater.index_runes(tx, txid)?;
+
+      let supply_state = rune_updater.ledger.state;
+
+      let mut util_entry = util_entry.clone();
+      util_entry.update(supply_state.supply0, supply_state.supply1);
+
+      simulations.push(api::Simulation {
+        txid,
+        artifact: outcome.artifact,
+        allocations: outcome
+          .allocations
+          .iter()
+          .enumerate()
+          .filter(|(_, balances)| !balances.is_empty())
+          .map(|(vout, balances)| (u32::try_from(vout).unwrap(), balances.into()))
+          .collect(),
+        burned: (&outcome.burned).into(),
+        minted: outcome
+          .minted
+          .map(|(tighten, ease)| api::RuneAmounts { tighten, ease }),
+        conversion: rune_updater
+          .txid_to_conversion
+          .get(&txid.store())?
+          .map(|entry| ConversionEntry::load(entry.value())),
+        failed_conversion: rune_updater
+          .txid_to_failed_conversion
+          .get(&txid.store())?
+          .map(|entry| FailedConversionEntry::load(entry.value())),
+        supply_state,
+        util_state: util_entry.state(),
+      });
     }
 
-    Ok(states)
+    Ok(Some((initial, simulations)))
   }
 }
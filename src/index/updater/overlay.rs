@@ -0,0 +1,293 @@
+use {
+  super::*,
+  redb::{AccessGuard, Key, Value},
+  std::{marker::PhantomData, ops::RangeInclusive},
+};
+
+/// The table operations `RuneUpdater` performs, implemented both by redb
+/// tables, when indexing blocks, and by overlays, when simulating
+/// transactions.
+pub(super) trait RuneTable<K: Key + 'static, V: Value + 'static> {
+  fn get(&self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>>;
+
+  fn insert(&mut self, key: K::SelfType<'_>, value: V::SelfType<'_>) -> Result<Option<Stored<V>>>;
+
+  fn last(&self, range: RangeInclusive<K::SelfType<'_>>) -> Result<Option<(Stored<K>, Stored<V>)>>;
+
+  fn len(&self) -> Result<u64>;
+
+  fn remove(&mut self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>>;
+}
+
+/// An owned copy of a key or value read from a table
+pub(super) struct Stored<V: Value + 'static> {
+  bytes: Vec<u8>,
+  value: PhantomData<V>,
+}
+
+impl<V: Value + 'static> Stored<V> {
+  fn new(bytes: Vec<u8>) -> Self {
+    Self {
+      bytes,
+      value: PhantomData,
+    }
+  }
+
+  pub(super) fn value(&self) -> V::SelfType<'_> {
+    V::from_bytes(&self.bytes)
+  }
+}
+
+impl<V: Value + 'static> From<AccessGuard<'_, V>> for Stored<V> {
+  fn from(guard: AccessGuard<V>) -> Self {
+    Self::new(V::as_bytes(&guard.value()).as_ref().to_vec())
+  }
+}
+
+impl<K: Key + 'static, V: Value + 'static> RuneTable<K, V> for Table<'_, K, V> {
+  fn get(&self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    Ok(ReadableTable::get(self, key)?.map(Stored::from))
+  }
+
+  fn insert(&mut self, key: K::SelfType<'_>, value: V::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    Ok(Table::insert(self, key, value)?.map(Stored::from))
+  }
+
+  fn last(&self, range: RangeInclusive<K::SelfType<'_>>) -> Result<Option<(Stored<K>, Stored<V>)>> {
+    Ok(
+      ReadableTable::range(self, range)?
+        .next_back()
+        .transpose()?
+        .map(|(key, value)| (key.into(), value.into())),
+    )
+  }
+
+  fn len(&self) -> Result<u64> {
+    Ok(ReadableTableMetadata::len(self)?)
+  }
+
+  fn remove(&mut self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    Ok(Table::remove(self, key)?.map(Stored::from))
+  }
+}
+
+/// Key bytes ordered the way the table orders keys
+struct OrderedKey<K: Key + 'static> {
+  bytes: Vec<u8>,
+  key: PhantomData<K>,
+}
+
+impl<K: Key + 'static> OrderedKey<K> {
+  fn new(key: &K::SelfType<'_>) -> Self {
+    Self {
+      bytes: K::as_bytes(key).as_ref().to_vec(),
+      key: PhantomData,
+    }
+  }
+}
+
+impl<K: Key + 'static> Ord for OrderedKey<K> {
+  fn cmp(&self, other: &Self) -> cmp::Ordering {
+    K::compare(&self.bytes, &other.bytes)
+  }
+}
+
+impl<K: Key + 'static> PartialOrd for OrderedKey<K> {
+  fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<K: Key + 'static> PartialEq for OrderedKey<K> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other).is_eq()
+  }
+}
+
+impl<K: Key + 'static> Eq for OrderedKey<K> {}
+
+/// A copy-on-write view of a table in a read transaction. Reads fall through
+/// to the table, unless the key has been written, and writes are kept in
+/// memory, so simulations neither take the write lock nor block each other.
+pub(super) struct Overlay<K: Key + 'static, V: Value + 'static> {
+  changes: BTreeMap<OrderedKey<K>, Option<Vec<u8>>>,
+  len: u64,
+  table: ReadOnlyTable<K, V>,
+}
+
+impl<K: Key + 'static, V: Value + 'static> Overlay<K, V> {
+  pub(super) fn new(table: ReadOnlyTable<K, V>) -> Result<Self> {
+    Ok(Self {
+      changes: BTreeMap::new(),
+      len: table.len()?,
+      table,
+    })
+  }
+
+  fn write(&mut self, key: K::SelfType<'_>, value: Option<Vec<u8>>) -> Result<Option<Stored<V>>> {
+    let ordered = OrderedKey::new(&key);
+
+    let previous = self.get(key)?;
+
+    match (&previous, &value) {
+      (None, Some(_)) => self.len += 1,
+      (Some(_), None) => self.len -= 1,
+      _ => {}
+    }
+
+    self.changes.insert(ordered, value);
+
+    Ok(previous)
+  }
+}
+
+impl<K: Key + 'static, V: Value + 'static> RuneTable<K, V> for Overlay<K, V> {
+  fn get(&self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    match self.changes.get(&OrderedKey::new(&key)) {
+      Some(value) => Ok(value.clone().map(Stored::new)),
+      None => Ok(self.table.get(key)?.map(Stored::from)),
+    }
+  }
+
+  fn insert(&mut self, key: K::SelfType<'_>, value: V::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    let value = V::as_bytes(&value).as_ref().to_vec();
+    self.write(key, Some(value))
+  }
+
+  fn last(&self, range: RangeInclusive<K::SelfType<'_>>) -> Result<Option<(Stored<K>, Stored<V>)>> {
+    let bounds = OrderedKey::new(range.start())..=OrderedKey::new(range.end());
+
+    let changed = self
+      .changes
+      .range(bounds)
+      .rev()
+      .find_map(|(key, value)| Some((key, value.as_ref()?)));
+
+    // entries which have been written or removed are superseded by the
+    // overlay, so the last committed entry is the last one not changed
+    let mut committed = None;
+
+    for entry in self.table.range(range)?.rev() {
+      let (key, value) = entry?;
+
+      if !self.changes.contains_key(&OrderedKey::new(&key.value())) {
+        committed = Some((Stored::<K>::from(key), Stored::from(value)));
+        break;
+      }
+    }
+
+    Ok(match (changed, committed) {
+      (Some((changed_key, changed_value)), Some((committed_key, committed_value))) => {
+        if K::compare(&changed_key.bytes, &committed_key.bytes).is_gt() {
+          Some((
+            Stored::new(changed_key.bytes.clone()),
+            Stored::new(changed_value.clone()),
+          ))
+        } else {
+          Some((committed_key, committed_value))
+        }
+      }
+      (Some((key, value)), None) => {
+        Some((Stored::new(key.bytes.clone()), Stored::new(value.clone())))
+      }
+      (None, committed) => committed,
+    })
+  }
+
+  fn len(&self) -> Result<u64> {
+    Ok(self.len)
+  }
+
+  fn remove(&mut self, key: K::SelfType<'_>) -> Result<Option<Stored<V>>> {
+    self.write(key, None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, redb::backends::InMemoryBackend};
+
+  define_table! { TEST, (u32, u32), u64 }
+
+  fn database(entries: &[((u32, u32), u64)]) -> Database {
+    let database = Database::builder()
+      .create_with_backend(InMemoryBackend::new())
+      .unwrap();
+
+    let wtx = database.begin_write().unwrap();
+
+    {
+      let mut table = wtx.open_table(TEST).unwrap();
+
+      for (key, value) in entries {
+        table.insert(key, value).unwrap();
+      }
+    }
+
+    wtx.commit().unwrap();
+
+    database
+  }
+
+  fn overlay(database: &Database) -> Overlay<(u32, u32), u64> {
+    Overlay::new(database.begin_read().unwrap().open_table(TEST).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn writes_are_kept_in_memory() {
+    let database = database(&[((0, 0), 1), ((0, 1), 2)]);
+
+    let mut overlay = overlay(&database);
+
+    assert_eq!(overlay.len().unwrap(), 2);
+    assert_eq!(overlay.get((0, 0)).unwrap().unwrap().value(), 1);
+
+    assert_eq!(overlay.insert((0, 0), 3).unwrap().unwrap().value(), 1);
+    assert_eq!(overlay.remove((0, 1)).unwrap().unwrap().value(), 2);
+    assert!(overlay.insert((1, 0), 4).unwrap().is_none());
+    assert!(overlay.remove((2, 0)).unwrap().is_none());
+
+    assert_eq!(overlay.get((0, 0)).unwrap().unwrap().value(), 3);
+    assert!(overlay.get((0, 1)).unwrap().is_none());
+    assert_eq!(overlay.get((1, 0)).unwrap().unwrap().value(), 4);
+    assert_eq!(overlay.len().unwrap(), 2);
+
+    let rtx = database.begin_read().unwrap();
+    let table = rtx.open_table(TEST).unwrap();
+
+    assert_eq!(table.len().unwrap(), 2);
+    assert_eq!(table.get((0, 0)).unwrap().unwrap().value(), 1);
+    assert_eq!(table.get((0, 1)).unwrap().unwrap().value(), 2);
+    assert!(table.get((1, 0)).unwrap().is_none());
+  }
+
+  #[test]
+  fn last_entry_in_range_includes_writes() {
+    let database = database(&[((1, 0), 1), ((1, 1), 2), ((2, 0), 3)]);
+
+    let mut overlay = overlay(&database);
+
+    let last = |overlay: &Overlay<(u32, u32), u64>, height| {
+      overlay
+        .last((height, 0)..=(height, u32::MAX))
+        .unwrap()
+        .map(|(key, value)| (key.value(), value.value()))
+    };
+
+    assert_eq!(last(&overlay, 0), None);
+    assert_eq!(last(&overlay, 1), Some(((1, 1), 2)));
+
+    overlay.remove((1, 1)).unwrap();
+
+    assert_eq!(last(&overlay, 1), Some(((1, 0), 1)));
+
+    overlay.insert((1, 2), 4).unwrap();
+
+    assert_eq!(last(&overlay, 1), Some(((1, 2), 4)));
+
+    overlay.insert((0, 256), 5).unwrap();
+
+    assert_eq!(last(&overlay, 0), Some(((0, 256), 5)));
+    assert_eq!(last(&overlay, 2), Some(((2, 0), 3)));
+  }
+}
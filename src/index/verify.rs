@@ -0,0 +1,253 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Discrepancy {
+  ChainOutputScript {
+    state_change: StateChange,
+    outpoint: OutPoint,
+    expected: ScriptBuf,
+    actual: ScriptBuf,
+  },
+  ChainOutputSpent {
+    state_change: StateChange,
+    outpoint: OutPoint,
+  },
+  ChainOutputValue {
+    state_change: StateChange,
+    outpoint: OutPoint,
+    expected: u64,
+    actual: u64,
+  },
+  HeaderGap {
+    expected: u32,
+    actual: u32,
+  },
+  HeaderLink {
+    height: u32,
+    expected: BlockHash,
+    actual: BlockHash,
+  },
+  Rate {
+    height: u32,
+    expected: Option<u128>,
+    actual: Option<u128>,
+  },
+  Supply {
+    rune: RuneId,
+    supply: u128,
+    balances: u128,
+    burned: u128,
+  },
+  SupplyState {
+    height: u32,
+    expected: api::SupplyState,
+    actual: Option<api::SupplyState>,
+  },
+  UtilState {
+    height: u32,
+    expected: api::UtilState,
+    actual: Option<api::UtilState>,
+  },
+}
+
+impl Index {
+  /// Check the index for internal inconsistencies, and for chain outputs
+  /// which disagree with bitcoind, returning every discrepancy found
+  pub(crate) fn verify(&self) -> Result<Vec<Discrepancy>> {
+    let rtx = self.database.begin_read()?;
+
+    let mut discrepancies = Vec::new();
+
+    let height = Self::verify_headers(&rtx, &mut discrepancies)?;
+
+    let mut supply_state = api::SupplyState {
+      supply0: 0,
+      supply1: 0,
+      burned0: 0,
+      burned1: 0,
+    };
+
+    let mut balances = HashMap::<RuneId, u128>::new();
+
+    for result in rtx.open_table(OUTPOINT_TO_RUNE_BALANCES)?.iter()? {
+      let (_outpoint, buffer) = result?;
+      let buffer = buffer.value();
+      let mut i = 0;
+      while i < buffer.len() {
+        let ((id, balance), len) = Self::decode_rune_balance(&buffer[i..])?;
+        i += len;
+        *balances.entry(id).or_default() += balance;
+      }
+    }
+
+    for result in rtx.open_table(RUNE_ID_TO_RUNE_ENTRY)?.iter()? {
+      let (id, entry) = result?;
+      let rune = RuneId::load(id.value());
+      let entry = RuneEntry::load(entry.value());
+      let balances = balances.get(&rune).copied().unwrap_or_default();
+
+      if balances.checked_add(entry.burned) != Some(entry.supply) {
+        discrepancies.push(Discrepancy::Supply {
+          rune,
+          supply: entry.supply,
+          balances,
+          burned: entry.burned,
+        });
+      }
+
+      if rune == ID0 {
+        supply_state.supply0 = entry.supply;
+        supply_state.burned0 = entry.burned;
+      } else if rune == ID1 {
+        supply_state.supply1 = entry.supply;
+        supply_state.burned1 = entry.burned;
+      }
+    }
+
+    if let Some(height) = height.filter(|height| *height >= self.settings.first_rune_height()) {
+      let actual = rtx
+        .open_table(HEIGHT_TO_SUPPLY_STATE)?
+        .get(height)?
+        .map(|state| api::SupplyState::load(state.value()));
+
+      if actual != Some(supply_state) {
+        discrepancies.push(Discrepancy::SupplyState {
+          height,
+          expected: supply_state,
+          actual,
+        });
+      }
+
+      let expected = UtilEntry::load(rtx.open_table(UTIL_ENTRY)?.get(0)?.unwrap().value()).state();
+
+      let actual = rtx
+        .open_table(HEIGHT_TO_UTIL_STATE)?
+        .get(height)?
+        .map(|state| api::UtilState::load(state.value()));
+
+      if actual.as_ref() != Some(&expected) {
+        discrepancies.push(Discrepancy::UtilState {
+          height,
+          expected,
+          actual,
+        });
+      }
+
+      let expected = UtilEntry::rate(supply_state.supply0, supply_state.supply1);
+
+      let actual = rtx
+        .open_table(HEIGHT_TO_RATE)?
+        .get(height)?
+        .map(|rate| rate.value());
+
+      if actual != expected {
+        discrepancies.push(Discrepancy::Rate {
+          height,
+          expected,
+          actual,
+        });
+      }
+    }
+
+    // chain outputs may be spent by blocks which haven't been indexed yet, so
+    // spends are only checked if the index has caught up with the chain
+    let caught_up = match height {
+      Some(height) => u64::from(height) == self.chain_source.block_count()?,
+      None => false,
+    };
+
+    for (state_change, script_pubkey) in [
+      (StateChange::Mint, Ledger::mint_script_pubkey()),
+      (StateChange::Convert, Ledger::conversion_script_pubkey()),
+    ] {
+      self.verify_chain_output(state_change, script_pubkey, caught_up, &mut discrepancies)?;
+    }
+
+    Ok(discrepancies)
+  }
+
+  /// Check that block headers are stored at every height from the first,
+  /// which is zero unless the index was imported from a snapshot, and that
+  /// each links to the one before it, returning the last height
+  fn verify_headers(
+    rtx: &redb::ReadTransaction,
+    discrepancies: &mut Vec<Discrepancy>,
+  ) -> Result<Option<u32>> {
+    let mut previous: Option<(u32, BlockHash)> = None;
+
+    for result in rtx.open_table(HEIGHT_TO_BLOCK_HEADER)?.iter()? {
+      let (height, header) = result?;
+      let height = height.value();
+      let header = Header::load(*header.value());
+
+      let expected = previous.map(|(height, _hash)| height + 1).unwrap_or(height);
+
+      if height != expected {
+        discrepancies.push(Discrepancy::HeaderGap {
+          expected,
+          actual: height,
+        });
+      } else if let Some((_height, hash)) = previous {
+        if header.prev_blockhash != hash {
+          discrepancies.push(Discrepancy::HeaderLink {
+            height,
+            expected: hash,
+            actual: header.prev_blockhash,
+          });
+        }
+      }
+
+      previous = Some((height, header.block_hash()));
+    }
+
+    Ok(previous.map(|(height, _hash)| height))
+  }
+
+  fn verify_chain_output(
+    &self,
+    state_change: StateChange,
+    script_pubkey: ScriptBuf,
+    caught_up: bool,
+    discrepancies: &mut Vec<Discrepancy>,
+  ) -> Result {
+    let (outpoint, value) = self.get_last_outpoint_txout_for_state_change(state_change)?;
+
+    if outpoint == OutPoint::null() {
+      return Ok(());
+    }
+
+    // looked up in bitcoind's UTXO set, which doesn't need `-txindex`.
+    // Spends in the mempool, such as a pending mint, are ignored, since the
+    // index only follows confirmed spends.
+    let Some(txout) = self.chain_source.tx_out(outpoint, false)? else {
+      if caught_up {
+        discrepancies.push(Discrepancy::ChainOutputSpent {
+          state_change,
+          outpoint,
+        });
+      }
+      return Ok(());
+    };
+
+    if txout.script_pubkey != script_pubkey {
+      discrepancies.push(Discrepancy::ChainOutputScript {
+        state_change,
+        outpoint,
+        expected: script_pubkey,
+        actual: txout.script_pubkey,
+      });
+    }
+
+    if txout.value != value {
+      discrepancies.push(Discrepancy::ChainOutputValue {
+        state_change,
+        outpoint,
+        expected: value,
+        actual: txout.value,
+      });
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,811 @@
+use {
+  super::*,
+  crate::settings::Webhook,
+  bitcoin::hashes::{hmac, sha256, HashEngine},
+  event::Filter,
+  read_only::CopyOnWriteFile,
+  reqwest::{blocking::Client, header::CONTENT_TYPE},
+  std::{
+    fs::File,
+    mem,
+    sync::mpsc::{self, RecvTimeoutError},
+  },
+};
+
+define_table! { DEAD_LETTERS, u64, &[u8] }
+define_table! { OUTBOX, u64, &[u8] }
+define_table! { STATISTICS, &str, u64 }
+
+const NEXT_DELIVERY_ID: &str = "next_delivery_id";
+
+/// A batch of events for a webhook, which is waiting in the outbox to be
+/// delivered, or which was moved to the dead-letter queue after failing to
+/// be delivered too many times
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+  /// Index of the webhook in the settings
+  pub target: usize,
+  pub url: String,
+  pub events: Vec<Event>,
+  pub attempts: u32,
+  /// Time of the next attempt, in milliseconds since the Unix epoch
+  pub next_attempt: u64,
+  /// Error of the last attempt
+  pub error: Option<String>,
+}
+
+/// Delivery of index events to the webhooks configured in the settings.
+///
+/// Events are buffered as they are emitted, and written to an outbox stored
+/// next to the index before the blocks which emitted them are committed, so
+/// deliveries survive restarts. Events are POSTed in batches, with an
+/// HMAC-SHA256 signature of the body keyed by the webhook's secret, and failed
+/// deliveries are retried with exponential backoff, until they are moved to
+/// the dead-letter queue listed by `bitomc webhooks status`.
+pub(crate) struct Webhooks {
+  outbox: Arc<Outbox>,
+  pending: Mutex<Vec<Event>>,
+  worker: Mutex<Option<Worker>>,
+}
+
+struct Outbox {
+  database: Database,
+  targets: Vec<Target>,
+}
+
+struct Target {
+  filter: Filter,
+  webhook: Webhook,
+}
+
+struct Worker {
+  thread: thread::JoinHandle<()>,
+  wake: mpsc::SyncSender<()>,
+}
+
+impl Webhooks {
+  const BACKOFF: Duration = Duration::from_secs(1);
+  const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+  const MAX_ATTEMPTS: u32 = 20;
+  const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+  const MAX_BATCH_SIZE: usize = 1000;
+  const TIMEOUT: Duration = Duration::from_secs(10);
+
+  /// Path of the outbox of the index at `index`
+  pub(crate) fn path(index: &Path) -> PathBuf {
+    index.with_extension("webhooks.redb")
+  }
+
+  pub(crate) fn open(settings: &Settings) -> Result<Option<Self>> {
+    if settings.webhooks().is_empty() {
+      return Ok(None);
+    }
+
+    let targets = settings
+      .webhooks()
+      .iter()
+      .map(|webhook| Target::new(webhook, settings.chain()))
+      .collect::<Result<Vec<Target>>>()?;
+
+    let path = Self::path(settings.index());
+
+    let database = Database::create(&path)
+      .with_context(|| format!("failed to open webhook outbox `{}`", path.display()))?;
+
+    let wtx = database.begin_write()?;
+    wtx.open_table(DEAD_LETTERS)?;
+    wtx.open_table(OUTBOX)?;
+    wtx.open_table(STATISTICS)?;
+    wtx.commit()?;
+
+    Ok(Some(Self {
+      outbox: Arc::new(Outbox { database, targets }),
+      pending: Mutex::new(Vec::new()),
+      worker: Mutex::new(None),
+    }))
+  }
+
+  /// Deliveries waiting in the outbox, and deliveries in the dead-letter
+  /// queue, of the index at `index`, which may be open in another process
+  pub(crate) fn status(index: &Path) -> Result<(Vec<(u64, Delivery)>, Vec<(u64, Delivery)>)> {
+    let path = Self::path(index);
+
+    let file = match File::open(&path) {
+      Ok(file) => file,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        bail!("no webhook outbox at `{}`", path.display())
+      }
+      Err(err) => return Err(err).with_context(|| format!("failed to open `{}`", path.display())),
+    };
+
+    // the outbox is locked while it's open in another process, so it's read
+    // through a backend which leaves the file untouched
+    let database = Database::builder().create_with_backend(CopyOnWriteFile::new(file)?)?;
+
+    let rtx = database.begin_read()?;
+
+    Ok((
+      Outbox::deliveries(&rtx.open_table(OUTBOX)?)?,
+      Outbox::deliveries(&rtx.open_table(DEAD_LETTERS)?)?,
+    ))
+  }
+
+  pub(crate) fn push(&self, event: Event) {
+    self.pending.lock().unwrap().push(event);
+  }
+
+  /// Drop events which have not been written to the outbox
+  pub(crate) fn discard(&self) {
+    self.pending.lock().unwrap().clear();
+  }
+
+  /// Write pending events to the outbox, and wake the worker delivering them
+  pub(crate) fn enqueue(&self) -> Result {
+    let events = mem::take(&mut *self.pending.lock().unwrap());
+
+    if !self.outbox.enqueue(&events)? {
+      return Ok(());
+    }
+
+    if let Some(worker) = self.worker.lock().unwrap().as_ref() {
+      // a full channel means the worker will run again anyways
+      worker.wake.try_send(()).ok();
+    }
+
+    Ok(())
+  }
+
+  /// Spawn a worker which delivers events from the outbox until the webhooks
+  /// are dropped
+  pub(crate) fn deliver(&self) {
+    let mut worker = self.worker.lock().unwrap();
+
+    if worker.is_some() {
+      return;
+    }
+
+    let (wake, receiver) = mpsc::sync_channel(1);
+
+    let outbox = self.outbox.clone();
+
+    let thread = thread::spawn(move || {
+      let client = match Client::builder().timeout(Self::TIMEOUT).build() {
+        Ok(client) => client,
+        Err(err) => {
+          log::error!("Failed to create webhook client: {err}");
+          return;
+        }
+      };
+
+      loop {
+        let timeout = match outbox.deliver(&client, Self::now()) {
+          Ok(Some(next_attempt)) => {
+            Duration::from_millis(next_attempt.saturating_sub(Self::now())).min(Self::IDLE_INTERVAL)
+          }
+          Ok(None) => Self::IDLE_INTERVAL,
+          Err(err) => {
+            log::error!("Failed to deliver webhooks: {err}");
+            Self::BACKOFF
+          }
+        };
+
+        if SHUTTING_DOWN.load(atomic::Ordering::Relaxed) {
+          break;
+        }
+
+        match receiver.recv_timeout(timeout) {
+          Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+          Err(RecvTimeoutError::Disconnected) => break,
+        }
+      }
+    });
+
+    *worker = Some(Worker { thread, wake });
+  }
+
+  fn backoff(attempts: u32) -> Duration {
+    Self::BACKOFF
+      .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+      .min(Self::MAX_BACKOFF)
+  }
+
+  fn now() -> u64 {
+    SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map(|duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+      .unwrap_or_default()
+  }
+
+  /// Hex-encoded HMAC-SHA256 of `body` keyed by `secret`, sent in the
+  /// `X-BitOMC-Signature` header as `sha256=<SIGNATURE>`
+  pub(crate) fn signature(secret: &str, body: &[u8]) -> String {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(body);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).to_string()
+  }
+}
+
+impl Drop for Webhooks {
+  fn drop(&mut self) {
+    if let Some(Worker { thread, wake }) = self.worker.lock().unwrap().take() {
+      drop(wake);
+      thread.join().ok();
+    }
+  }
+}
+
+impl Outbox {
+  fn deliveries(table: &impl ReadableTable<u64, &'static [u8]>) -> Result<Vec<(u64, Delivery)>> {
+    table
+      .iter()?
+      .map(|entry| {
+        let (id, delivery) = entry?;
+        Ok((id.value(), serde_json::from_slice(delivery.value())?))
+      })
+      .collect()
+  }
+
+  /// Write a delivery for each batch of `events` matching each target,
+  /// returning whether any were written
+  fn enqueue(&self, events: &[Event]) -> Result<bool> {
+    let mut enqueued = false;
+
+    if events.is_empty() {
+      return Ok(enqueued);
+    }
+
+    let wtx = self.database.begin_write()?;
+
+    {
+      let mut outbox = wtx.open_table(OUTBOX)?;
+      let mut statistics = wtx.open_table(STATISTICS)?;
+
+      let mut id = statistics
+        .get(NEXT_DELIVERY_ID)?
+        .map(|id| id.value())
+        .unwrap_or_default();
+
+      for (index, target) in self.targets.iter().enumerate() {
+        let events = events
+          .iter()
+          .filter(|event| target.filter.matches(event))
+          .cloned()
+          .collect::<Vec<Event>>();
+
+        for batch in events.chunks(Webhooks::MAX_BATCH_SIZE) {
+          let delivery = Delivery {
+            target: index,
+            url: target.webhook.url.clone(),
+            events: batch.to_vec(),
+            attempts: 0,
+            next_attempt: 0,
+            error: None,
+          };
+
+          outbox.insert(id, serde_json::to_vec(&delivery)?.as_slice())?;
+
+          id += 1;
+          enqueued = true;
+        }
+      }
+
+      statistics.insert(NEXT_DELIVERY_ID, id)?;
+    }
+
+    wtx.commit()?;
+
+    Ok(enqueued)
+  }
+
+  /// Attempt the oldest delivery to each webhook which is due at `now`,
+  /// returning when the next delivery is due.
+  ///
+  /// Deliveries to a webhook are attempted one at a time, in the order they
+  /// were enqueued, so a failing delivery holds back later ones until it
+  /// succeeds or is moved to the dead-letter queue.
+  fn deliver(&self, client: &Client, now: u64) -> Result<Option<u64>> {
+    let mut heads = BTreeMap::<usize, (u64, Delivery)>::new();
+
+    for (id, delivery) in Self::deliveries(&self.database.begin_read()?.open_table(OUTBOX)?)? {
+      heads.entry(delivery.target).or_insert((id, delivery));
+    }
+
+    let mut next = None;
+
+    for (id, mut delivery) in heads.into_values() {
+      if delivery.next_attempt > now {
+        next = Some(next.unwrap_or(u64::MAX).min(delivery.next_attempt));
+        continue;
+      }
+
+      // webhooks are identified by their position in the settings, which
+      // may have changed since the delivery was enqueued
+      let target = self
+        .targets
+        .get(delivery.target)
+        .filter(|target| target.webhook.url == delivery.url);
+
+      let result = match target {
+        Some(target) => target.post(client, id, &delivery.events),
+        None => Err(anyhow!("webhook is no longer configured")),
+      };
+
+      let wtx = self.database.begin_write()?;
+
+      {
+        let mut outbox = wtx.open_table(OUTBOX)?;
+
+        match result {
+          Ok(()) => {
+            outbox.remove(id)?;
+            next = Some(now);
+          }
+          Err(err) => {
+            log::warn!("Delivery {id} to webhook `{}` failed: {err}", delivery.url);
+
+            delivery.attempts += 1;
+            delivery.error = Some(err.to_string());
+
+            let max_attempts = target
+              .map(|target| {
+                target
+                  .webhook
+                  .max_attempts
+                  .unwrap_or(Webhooks::MAX_ATTEMPTS)
+              })
+              .unwrap_or_default();
+
+            if delivery.attempts >= max_attempts {
+              outbox.remove(id)?;
+              wtx
+                .open_table(DEAD_LETTERS)?
+                .insert(id, serde_json::to_vec(&delivery)?.as_slice())?;
+              next = Some(now);
+            } else {
+              delivery.next_attempt = now.saturating_add(
+                u64::try_from(Webhooks::backoff(delivery.attempts).as_millis()).unwrap(),
+              );
+              outbox.insert(id, serde_json::to_vec(&delivery)?.as_slice())?;
+              next = Some(next.unwrap_or(u64::MAX).min(delivery.next_attempt));
+            }
+          }
+        }
+      }
+
+      wtx.commit()?;
+    }
+
+    Ok(next)
+  }
+}
+
+impl Target {
+  fn new(webhook: &Webhook, chain: Chain) -> Result<Self> {
+    Url::parse(&webhook.url).with_context(|| format!("invalid webhook URL `{}`", webhook.url))?;
+
+    if let Some(types) = &webhook.types {
+      for name in types {
+        ensure!(
+          Event::TYPES.contains(&name.as_str()),
+          "unknown event type `{name}` for webhook `{}`, expected one of {}",
+          webhook.url,
+          Event::TYPES.join(", "),
+        );
+      }
+    }
+
+    let script_pubkey = webhook
+      .address
+      .clone()
+      .map(|address| {
+        address
+          .require_network(chain.network())
+          .map(|address| address.script_pubkey())
+          .with_context(|| format!("invalid address for webhook `{}`", webhook.url))
+      })
+      .transpose()?;
+
+    Ok(Self {
+      filter: Filter {
+        outpoint: webhook.outpoint,
+        rune: webhook.rune,
+        script_pubkey,
+        types: webhook
+          .types
+          .as_ref()
+          .map(|types| types.iter().cloned().collect()),
+      },
+      webhook: webhook.clone(),
+    })
+  }
+
+  fn post(&self, client: &Client, id: u64, events: &[Event]) -> Result {
+    let body = serde_json::to_vec(&api::WebhookBatch {
+      id,
+      events: events.to_vec(),
+    })?;
+
+    let response = client
+      .post(&self.webhook.url)
+      .header(CONTENT_TYPE, "application/json")
+      .header("X-BitOMC-Delivery", id.to_string())
+      .header(
+        "X-BitOMC-Signature",
+        format!(
+          "sha256={}",
+          Webhooks::signature(&self.webhook.secret, &body)
+        ),
+      )
+      .body(body)
+      .send()?;
+
+    ensure!(
+      response.status().is_success(),
+      "webhook responded with {}",
+      response.status()
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    crate::index::testing::Context,
+    axum::{
+      body::Bytes,
+      extract::State,
+      http::{HeaderMap, StatusCode},
+      response::IntoResponse,
+      routing::post,
+      Router,
+    },
+    std::{net::TcpListener, sync::atomic::AtomicU16},
+  };
+
+  /// A stand-in webhook receiver, which records the requests it receives, and
+  /// responds with `status`
+  struct Receiver {
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    status: Arc<AtomicU16>,
+    url: String,
+  }
+
+  impl Receiver {
+    fn spawn() -> Self {
+      let requests = Arc::new(Mutex::new(Vec::new()));
+      let status = Arc::new(AtomicU16::new(200));
+
+      let router = Router::new()
+        .route(
+          "/hook",
+          post(
+            |State((requests, status)): State<(
+              Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+              Arc<AtomicU16>,
+            )>,
+             headers: HeaderMap,
+             body: Bytes| async move {
+              requests.lock().unwrap().push((headers, body));
+              StatusCode::from_u16(status.load(atomic::Ordering::Relaxed))
+                .unwrap()
+                .into_response()
+            },
+          ),
+        )
+        .with_state((requests.clone(), status.clone()));
+
+      let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+      let port = listener.local_addr().unwrap().port();
+
+      thread::spawn(move || {
+        Runtime::new().unwrap().block_on(async {
+          axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router.into_make_service())
+            .await
+            .unwrap()
+        })
+      });
+
+      Self {
+        requests,
+        status,
+        url: format!("http://127.0.0.1:{port}/hook"),
+      }
+    }
+
+    fn batches(&self) -> Vec<api::WebhookBatch> {
+      self
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(headers, body)| {
+          assert_eq!(
+            headers["x-bitomc-signature"],
+            format!("sha256={}", Webhooks::signature("secret", body)),
+          );
+
+          let batch = serde_json::from_slice::<api::WebhookBatch>(body).unwrap();
+
+          assert_eq!(headers["x-bitomc-delivery"], batch.id.to_string());
+
+          batch
+        })
+        .collect()
+    }
+  }
+
+  fn open_index(context: &Context, config: &str) -> Index {
+    let path = context.tempdir.path().join("webhooks.yaml");
+
+    fs::write(&path, config).unwrap();
+
+    context
+      .open_index([
+        "--config".into(),
+        path.into_os_string(),
+        "--index".into(),
+        context
+          .tempdir
+          .path()
+          .join("webhooks.redb")
+          .into_os_string(),
+      ])
+      .unwrap()
+  }
+
+  fn deliver(index: &Index, now: u64) -> Option<u64> {
+    index
+      .webhooks()
+      .unwrap()
+      .outbox
+      .deliver(&Client::new(), now)
+      .unwrap()
+  }
+
+  #[test]
+  fn signature() {
+    assert_eq!(
+      Webhooks::signature("Jefe", b"what do ya want for nothing?"),
+      "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+    );
+  }
+
+  #[test]
+  fn committed_events_are_delivered_in_signed_batches() {
+    let context = Context::builder().build();
+
+    let receiver = Receiver::spawn();
+
+    let index = open_index(
+      &context,
+      &format!(
+        "webhooks:\n- url: {}\n  secret: secret\n  types: [block_committed, rune_minted]\n",
+        receiver.url
+      ),
+    );
+
+    index.update().unwrap();
+
+    context.mine_blocks(1);
+
+    let txid = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    index.update().unwrap();
+
+    let (pending, dead_letters) = Webhooks::status(index.settings.index()).unwrap();
+
+    assert_eq!(pending.len(), 2);
+    assert!(dead_letters.is_empty());
+
+    let now = Webhooks::now();
+
+    assert_eq!(deliver(&index, now), Some(now));
+    assert_eq!(deliver(&index, now), Some(now));
+    assert_eq!(deliver(&index, now), None);
+
+    let batches = receiver.batches();
+
+    assert_eq!(
+      batches
+        .iter()
+        .map(|batch| (
+          batch.id,
+          batch
+            .events
+            .iter()
+            .map(Event::type_name)
+            .collect::<Vec<&str>>()
+        ))
+        .collect::<Vec<(u64, Vec<&str>)>>(),
+      [
+        (0, vec!["block_committed"]),
+        (1, vec!["rune_minted", "block_committed"]),
+      ],
+    );
+
+    assert!(matches!(
+      batches[1].events[0],
+      Event::RuneMinted { txid: minted, .. } if minted == txid
+    ));
+
+    assert_eq!(
+      Webhooks::status(index.settings.index()).unwrap(),
+      (Vec::new(), Vec::new())
+    );
+  }
+
+  #[test]
+  fn failed_deliveries_are_retried_with_backoff_and_dead_lettered() {
+    let context = Context::builder().build();
+
+    let receiver = Receiver::spawn();
+
+    receiver.status.store(500, atomic::Ordering::Relaxed);
+
+    let config = format!(
+      "webhooks:\n- url: {}\n  secret: secret\n  max_attempts: 2\n",
+      receiver.url
+    );
+
+    let index = open_index(&context, &config);
+
+    index.update().unwrap();
+
+    // deliveries survive restarts
+    drop(index);
+
+    let index = open_index(&context, &config);
+
+    let now = Webhooks::now();
+
+    let retry = now + 1000;
+
+    assert_eq!(deliver(&index, now), Some(retry));
+    assert_eq!(deliver(&index, now), Some(retry));
+    assert_eq!(receiver.requests.lock().unwrap().len(), 1);
+
+    let (pending, dead_letters) = Webhooks::status(index.settings.index()).unwrap();
+
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].1.attempts, 1);
+    assert_eq!(pending[0].1.next_attempt, retry);
+    assert_eq!(
+      pending[0].1.error.as_deref(),
+      Some("webhook responded with 500 Internal Server Error"),
+    );
+    assert!(dead_letters.is_empty());
+
+    assert_eq!(deliver(&index, retry), Some(retry));
+    assert_eq!(deliver(&index, retry), None);
+    assert_eq!(receiver.requests.lock().unwrap().len(), 2);
+
+    let (pending, dead_letters) = Webhooks::status(index.settings.index()).unwrap();
+
+    assert!(pending.is_empty());
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].1.attempts, 2);
+
+    assert_eq!(Webhooks::backoff(1), Duration::from_secs(1));
+    assert_eq!(Webhooks::backoff(2), Duration::from_secs(2));
+    assert_eq!(Webhooks::backoff(5), Duration::from_secs(16));
+    assert_eq!(Webhooks::backoff(100), Webhooks::MAX_BACKOFF);
+  }
+
+  #[test]
+  fn webhooks_with_the_same_url_are_delivered_separately() {
+    let context = Context::builder().build();
+
+    let receiver = Receiver::spawn();
+
+    let index = open_index(
+      &context,
+      &format!(
+        "webhooks:\n- url: {0}\n  secret: secret\n- url: {0}\n  secret: other\n  types: [block_committed]\n",
+        receiver.url
+      ),
+    );
+
+    index.update().unwrap();
+
+    let (pending, _dead_letters) = Webhooks::status(index.settings.index()).unwrap();
+
+    assert_eq!(
+      pending
+        .iter()
+        .map(|(_id, delivery)| delivery.target)
+        .collect::<Vec<usize>>(),
+      [0, 1],
+    );
+
+    let now = Webhooks::now();
+
+    assert_eq!(deliver(&index, now), Some(now));
+    assert_eq!(deliver(&index, now), None);
+
+    let requests = receiver.requests.lock().unwrap();
+
+    assert_eq!(requests.len(), 2);
+
+    for ((headers, body), secret) in requests.iter().zip(["secret", "other"]) {
+      assert_eq!(
+        headers["x-bitomc-signature"],
+        format!("sha256={}", Webhooks::signature(secret, body)),
+      );
+    }
+  }
+
+  #[test]
+  fn deliveries_to_webhooks_which_are_no_longer_configured_are_dead_lettered() {
+    let context = Context::builder().build();
+
+    let index = open_index(
+      &context,
+      "webhooks:\n- url: http://127.0.0.1:1/hook\n  secret: secret\n",
+    );
+
+    index.update().unwrap();
+
+    drop(index);
+
+    let index = open_index(
+      &context,
+      "webhooks:\n- url: http://127.0.0.1:1/other\n  secret: secret\n",
+    );
+
+    deliver(&index, Webhooks::now());
+
+    let (pending, dead_letters) = Webhooks::status(index.settings.index()).unwrap();
+
+    assert!(pending.is_empty());
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(
+      dead_letters[0].1.error.as_deref(),
+      Some("webhook is no longer configured"),
+    );
+  }
+
+  #[test]
+  fn invalid_webhooks_are_rejected() {
+    let context = Context::builder().build();
+
+    let path = context.tempdir.path().join("webhooks.yaml");
+
+    for (webhook, error) in [
+      (
+        "url: foo\n  secret: secret",
+        "invalid webhook URL `foo`",
+      ),
+      (
+        "url: http://127.0.0.1:1\n  secret: secret\n  types: [block_mined]",
+        "unknown event type `block_mined` for webhook `http://127.0.0.1:1`",
+      ),
+      (
+        "url: http://127.0.0.1:1\n  secret: secret\n  address: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        "invalid address for webhook `http://127.0.0.1:1`",
+      ),
+    ] {
+      fs::write(&path, format!("webhooks:\n- {webhook}\n")).unwrap();
+
+      let err = context
+        .open_index(["--config".into(), path.clone().into_os_string()])
+        .err()
+        .unwrap()
+        .to_string();
+
+      assert!(err.starts_with(error), "{err}");
+    }
+  }
+}
@@ -14,11 +14,14 @@
 use {
   self::{
     arguments::Arguments,
+    block_notifications::BlockNotifications,
     blocktime::Blocktime,
+    chain_source::ChainSource,
     decimal::Decimal,
     deserialize_from_str::DeserializeFromStr,
     into_usize::IntoUsize,
     representation::Representation,
+    schedule::Schedule,
     settings::Settings,
     subcommand::{OutputFormat, Subcommand, SubcommandResult},
     tally::Tally,
@@ -33,7 +36,7 @@ use {
     },
     consensus::{self, Decodable, Encodable},
     hash_types::{BlockHash, TxMerkleNode},
-    hashes::Hash,
+    hashes::{sha256, Hash},
     Amount, Block, Network, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
     Witness,
   },
@@ -46,8 +49,9 @@ use {
   regex::Regex,
   reqwest::Url,
   runes_bitomc::{
-    varint, Artifact, Charm, Edict, Epoch, Height, Pile, Rarity, Rune, RuneId, Runestone, Sat,
-    SatPoint, SpacedRune, Terms,
+    varint, Artifact, Charm, ConversionFailure, Diagnostics, Edict, Epoch, Height, Ledger, Outcome,
+    Pile, Rarity, Rules, Rune, RuneId, Runestone, RunestoneBuilder, Sat, SatPoint, SpacedRune,
+    Terms, Version, ID0, ID1,
   },
   serde::{Deserialize, Deserializer, Serialize},
   serde_with::{DeserializeFromStr, SerializeDisplay},
@@ -76,7 +80,9 @@ use {
 pub use self::{
   chain::Chain,
   fee_rate::FeeRate,
-  index::{Index, RuneEntry},
+  index::{
+    ConversionEntry, Direction, Discrepancy, FailedConversionEntry, Index, MintEntry, RuneEntry,
+  },
   inscriptions::InscriptionId,
   object::Object,
   options::Options,
@@ -91,8 +97,10 @@ use self::test::*;
 
 pub mod api;
 pub mod arguments;
+mod block_notifications;
 mod blocktime;
 pub mod chain;
+mod chain_source;
 pub mod decimal;
 mod deserialize_from_str;
 mod fee_rate;
@@ -106,6 +114,7 @@ pub mod outgoing;
 mod re;
 mod representation;
 pub mod runes;
+mod schedule;
 pub mod settings;
 pub mod subcommand;
 mod tally;
@@ -114,9 +123,6 @@ pub mod wallet;
 
 type Result<T = (), E = Error> = std::result::Result<T, E>;
 
-const ID0: RuneId = RuneId { block: 1, tx: 0 };
-const ID1: RuneId = RuneId { block: 1, tx: 1 };
-
 const DESCENDANT_COUNT_LIMIT: u64 = 25;
 const ANCESTOR_COUNT_LIMIT: u64 = 25;
 const DESCENDANT_SIZE_LIMIT: u64 = 101_000;
@@ -23,6 +23,11 @@ pub struct Options {
   pub(crate) bitcoin_rpc_username: Option<String>,
   #[arg(long, help = "Max <N> requests in flight. [default: 12]")]
   pub(crate) bitcoin_rpc_limit: Option<u32>,
+  #[arg(
+    long,
+    help = "Read blocks from Bitcoin Core blk*.dat files in <BITCOIN_DATA_DIR> during initial sync."
+  )]
+  pub(crate) blk_files: bool,
   #[arg(long = "chain", value_enum, help = "Use <CHAIN>. [default: mainnet]")]
   pub(crate) chain_argument: Option<Chain>,
   #[arg(
@@ -38,6 +43,11 @@ pub struct Options {
   pub(crate) cookie_file: Option<PathBuf>,
   #[arg(long, alias = "datadir", help = "Store index in <DATA_DIR>.")]
   pub(crate) data_dir: Option<PathBuf>,
+  #[arg(
+    long,
+    help = "Fetch blocks and transactions from the Esplora REST API at <ESPLORA_URL> instead of Bitcoin Core RPC."
+  )]
+  pub(crate) esplora_url: Option<String>,
   #[arg(long, help = "Don't look for runes below <FIRST_RUNE_HEIGHT>.")]
   pub(crate) first_rune_height: Option<u32>,
   #[arg(long, help = "Limit index to <HEIGHT_LIMIT> blocks.")]
@@ -73,4 +83,14 @@ pub struct Options {
   pub(crate) signet: bool,
   #[arg(long, short, help = "Use testnet. Equivalent to `--chain testnet`.")]
   pub(crate) testnet: bool,
+  #[arg(
+    long,
+    help = "Activate versioned runestone payloads at <VERSIONED_PAYLOAD_HEIGHT>. Only allowed on regtest."
+  )]
+  pub(crate) versioned_payload_height: Option<u32>,
+  #[arg(
+    long,
+    help = "Update the index as soon as Bitcoin Core publishes a block on ZMQ endpoint <ZMQ_BLOCK>, polling if it is unreachable."
+  )]
+  pub(crate) zmq_block: Option<String>,
 }
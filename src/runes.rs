@@ -168,6 +168,111 @@ mod tests {
     );
   }
 
+  #[test]
+  fn versioned_payloads_are_deciphered_after_activation() {
+    let context = Context::builder()
+      .arg("--versioned-payload-height=3")
+      .build();
+
+    context.mine_blocks(1);
+
+    context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone { ..default() }.encipher()),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    let txid1 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(context.get_block_count() - 1, 1, 1, Witness::new())],
+      outputs: 2,
+      op_return: Some(
+        Runestone {
+          edicts: vec![Edict {
+            id: ID0,
+            amount: 50 * COIN_VALUE,
+            output: 1,
+          }],
+          ..default()
+        }
+        .encipher_with(Version::V1),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    context.assert_runes(
+      [
+        (
+          ID0,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(TIGHTEN),
+              spacers: 0,
+            },
+            mints: 2,
+            supply: 2 * REWARD,
+            burned: REWARD,
+            ..default()
+          },
+        ),
+        (
+          ID1,
+          RuneEntry {
+            spaced_rune: SpacedRune {
+              rune: Rune(EASE),
+              spacers: 0,
+            },
+            mints: 2,
+            supply: 0,
+            ..default()
+          },
+        ),
+      ],
+      [(
+        OutPoint {
+          txid: txid1,
+          vout: 1,
+        },
+        vec![(ID0, 50 * COIN_VALUE)],
+      )],
+    );
+
+    let txid2 = context.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(context.get_block_count() - 1, 1, 1, Witness::new())],
+      outputs: 2,
+      op_return: Some(
+        Runestone {
+          edicts: vec![Edict {
+            id: ID0,
+            amount: 50 * COIN_VALUE,
+            output: 0,
+          }],
+          ..default()
+        }
+        .encipher(),
+      ),
+      ..default()
+    });
+
+    context.mine_blocks(1);
+
+    assert_eq!(
+      context
+        .index
+        .get_rune_balances_for_output(OutPoint {
+          txid: txid2,
+          vout: 0,
+        })
+        .unwrap(),
+      BTreeMap::new(),
+    );
+  }
+
   #[test]
   fn unallocated_runes_are_assigned_to_first_non_op_return_output() {
     let context = Context::builder().build();
@@ -0,0 +1,95 @@
+use super::*;
+
+/// Consensus rules by activation height.
+///
+/// Each set of rules takes effect at its activation height and remains in
+/// effect until the next activation. No rule changes are currently scheduled
+/// on any public chain; upgrades can be activated early on regtest with
+/// `--versioned-payload-height` to test them before scheduling them elsewhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+  activations: Vec<(u32, Rules)>,
+}
+
+impl Schedule {
+  pub fn new(chain: Chain) -> Self {
+    let activations = match chain {
+      Chain::Mainnet | Chain::Testnet | Chain::Signet | Chain::Regtest => {
+        vec![(0, Rules::default())]
+      }
+    };
+
+    Self { activations }
+  }
+
+  /// Activates `rules` at `height`, replacing any rules scheduled at or after
+  /// `height`.
+  pub fn activate(mut self, height: u32, rules: Rules) -> Self {
+    self
+      .activations
+      .retain(|(activation, _)| *activation < height);
+    self.activations.push((height, rules));
+    self
+  }
+
+  /// Rules in effect for the block at `height`
+  pub fn rules(&self, height: u32) -> Rules {
+    self
+      .activations
+      .iter()
+      .rev()
+      .find(|(activation, _)| *activation <= height)
+      .map(|(_, rules)| *rules)
+      .unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn no_upgrades_are_scheduled() {
+    for chain in [
+      Chain::Mainnet,
+      Chain::Testnet,
+      Chain::Signet,
+      Chain::Regtest,
+    ] {
+      let schedule = Schedule::new(chain);
+      assert_eq!(schedule.rules(0), Rules::default());
+      assert_eq!(schedule.rules(u32::MAX), Rules::default());
+    }
+  }
+
+  #[test]
+  fn rules_take_effect_at_activation_height() {
+    let v1 = Rules {
+      version: Version::V1,
+    };
+
+    let schedule = Schedule::new(Chain::Regtest).activate(100, v1);
+
+    assert_eq!(schedule.rules(0), Rules::default());
+    assert_eq!(schedule.rules(99), Rules::default());
+    assert_eq!(schedule.rules(100), v1);
+    assert_eq!(schedule.rules(u32::MAX), v1);
+  }
+
+  #[test]
+  fn activation_replaces_later_activations() {
+    let v1 = Rules {
+      version: Version::V1,
+    };
+
+    let schedule = Schedule::new(Chain::Regtest)
+      .activate(100, v1)
+      .activate(50, Rules::default());
+
+    assert_eq!(schedule.rules(100), Rules::default());
+
+    let schedule = Schedule::new(Chain::Regtest).activate(0, v1);
+
+    assert_eq!(schedule.rules(0), v1);
+  }
+}
@@ -8,12 +8,14 @@ pub struct Settings {
   bitcoin_rpc_password: Option<String>,
   bitcoin_rpc_url: Option<String>,
   bitcoin_rpc_username: Option<String>,
+  blk_files: bool,
   chain: Option<Chain>,
   commit_interval: Option<usize>,
   config: Option<PathBuf>,
   config_dir: Option<PathBuf>,
   cookie_file: Option<PathBuf>,
   data_dir: Option<PathBuf>,
+  esplora_url: Option<String>,
   first_rune_height: Option<u32>,
   height_limit: Option<u32>,
   http_port: Option<u16>,
@@ -25,6 +27,31 @@ pub struct Settings {
   server_password: Option<String>,
   server_url: Option<String>,
   server_username: Option<String>,
+  versioned_payload_height: Option<u32>,
+  webhooks: Vec<Webhook>,
+  zmq_block: Option<String>,
+}
+
+/// A URL which index events are POSTed to, configured in the `webhooks`
+/// section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+  pub url: String,
+  /// Key for the HMAC-SHA256 signature of each request body
+  pub secret: String,
+  #[serde(default)]
+  pub address: Option<Address<NetworkUnchecked>>,
+  /// Deliveries are moved to the dead-letter queue after this many failed
+  /// attempts
+  #[serde(default)]
+  pub max_attempts: Option<u32>,
+  #[serde(default)]
+  pub outpoint: Option<OutPoint>,
+  #[serde(default)]
+  pub rune: Option<RuneId>,
+  #[serde(default)]
+  pub types: Option<BTreeSet<String>>,
 }
 
 impl Settings {
@@ -100,6 +127,11 @@ impl Settings {
       _ => {}
     };
 
+    ensure!(
+      settings.versioned_payload_height.is_none() || settings.chain() == Chain::Regtest,
+      "versioned payload height may only be set on regtest"
+    );
+
     Ok(settings)
   }
 
@@ -110,12 +142,14 @@ impl Settings {
       bitcoin_rpc_password: self.bitcoin_rpc_password.or(source.bitcoin_rpc_password),
       bitcoin_rpc_url: self.bitcoin_rpc_url.or(source.bitcoin_rpc_url),
       bitcoin_rpc_username: self.bitcoin_rpc_username.or(source.bitcoin_rpc_username),
+      blk_files: self.blk_files || source.blk_files,
       chain: self.chain.or(source.chain),
       commit_interval: self.commit_interval.or(source.commit_interval),
       config: self.config.or(source.config),
       config_dir: self.config_dir.or(source.config_dir),
       cookie_file: self.cookie_file.or(source.cookie_file),
       data_dir: self.data_dir.or(source.data_dir),
+      esplora_url: self.esplora_url.or(source.esplora_url),
       first_rune_height: self.first_rune_height.or(source.first_rune_height),
       height_limit: self.height_limit.or(source.height_limit),
       http_port: self.http_port.or(source.http_port),
@@ -127,6 +161,15 @@ impl Settings {
       server_password: self.server_password.or(source.server_password),
       server_url: self.server_url.or(source.server_url),
       server_username: self.server_username.or(source.server_username),
+      versioned_payload_height: self
+        .versioned_payload_height
+        .or(source.versioned_payload_height),
+      webhooks: if self.webhooks.is_empty() {
+        source.webhooks
+      } else {
+        self.webhooks
+      },
+      zmq_block: self.zmq_block.or(source.zmq_block),
     }
   }
 
@@ -137,6 +180,7 @@ impl Settings {
       bitcoin_rpc_password: options.bitcoin_rpc_password,
       bitcoin_rpc_url: options.bitcoin_rpc_url,
       bitcoin_rpc_username: options.bitcoin_rpc_username,
+      blk_files: options.blk_files,
       chain: options
         .signet
         .then_some(Chain::Signet)
@@ -148,6 +192,7 @@ impl Settings {
       config_dir: options.config_dir,
       cookie_file: options.cookie_file,
       data_dir: options.data_dir,
+      esplora_url: options.esplora_url,
       first_rune_height: options.first_rune_height,
       height_limit: options.height_limit,
       http_port: None,
@@ -159,6 +204,9 @@ impl Settings {
       server_password: options.server_password,
       server_url: None,
       server_username: options.server_username,
+      versioned_payload_height: options.versioned_payload_height,
+      webhooks: Vec::new(),
+      zmq_block: options.zmq_block,
     }
   }
 
@@ -212,12 +260,14 @@ impl Settings {
       bitcoin_rpc_password: get_string("BITCOIN_RPC_PASSWORD"),
       bitcoin_rpc_url: get_string("BITCOIN_RPC_URL"),
       bitcoin_rpc_username: get_string("BITCOIN_RPC_USERNAME"),
+      blk_files: get_bool("BLK_FILES"),
       chain: get_chain("CHAIN")?,
       commit_interval: get_usize("COMMIT_INTERVAL")?,
       config: get_path("CONFIG"),
       config_dir: get_path("CONFIG_DIR"),
       cookie_file: get_path("COOKIE_FILE"),
       data_dir: get_path("DATA_DIR"),
+      esplora_url: get_string("ESPLORA_URL"),
       first_rune_height: get_u32("FIRST_RUNE_HEIGHT")?,
       height_limit: get_u32("HEIGHT_LIMIT")?,
       http_port: get_u16("HTTP_PORT")?,
@@ -229,6 +279,9 @@ impl Settings {
       server_password: get_string("SERVER_PASSWORD"),
       server_url: get_string("SERVER_URL"),
       server_username: get_string("SERVER_USERNAME"),
+      versioned_payload_height: get_u32("VERSIONED_PAYLOAD_HEIGHT")?,
+      webhooks: Vec::new(),
+      zmq_block: get_string("ZMQ_BLOCK"),
     })
   }
 
@@ -239,12 +292,14 @@ impl Settings {
       bitcoin_rpc_url: Some(rpc_url.into()),
       bitcoin_rpc_username: None,
       bitcoin_rpc_limit: None,
+      blk_files: false,
       chain: Some(Chain::Regtest),
       commit_interval: None,
       config: None,
       config_dir: None,
       cookie_file: None,
       data_dir: Some(dir.into()),
+      esplora_url: None,
       first_rune_height: None,
       height_limit: None,
       http_port: None,
@@ -256,6 +311,9 @@ impl Settings {
       server_password: None,
       server_url: Some(server_url.into()),
       server_username: None,
+      versioned_payload_height: None,
+      webhooks: Vec::new(),
+      zmq_block: None,
     }
   }
 
@@ -303,12 +361,14 @@ impl Settings {
           .unwrap_or_else(|| format!("127.0.0.1:{}", chain.default_rpc_port())),
       ),
       bitcoin_rpc_username: self.bitcoin_rpc_username,
+      blk_files: self.blk_files,
       chain: Some(chain),
       commit_interval: Some(self.commit_interval.unwrap_or(5000)),
       config: None,
       config_dir: None,
       cookie_file: Some(cookie_file),
       data_dir: Some(data_dir),
+      esplora_url: self.esplora_url,
       first_rune_height: Some(if self.integration_test {
         2
       } else {
@@ -333,6 +393,9 @@ impl Settings {
       server_password: self.server_password,
       server_url: self.server_url,
       server_username: self.server_username,
+      versioned_payload_height: self.versioned_payload_height,
+      webhooks: self.webhooks,
+      zmq_block: self.zmq_block,
     })
   }
 
@@ -417,6 +480,15 @@ impl Settings {
     Ok(client)
   }
 
+  pub fn blk_files_dir(&self) -> Option<PathBuf> {
+    self.blk_files.then(|| {
+      self
+        .chain()
+        .join_with_data_dir(self.bitcoin_data_dir.as_ref().unwrap())
+        .join("blocks")
+    })
+  }
+
   pub fn chain(&self) -> Chain {
     self.chain.unwrap()
   }
@@ -447,6 +519,16 @@ impl Settings {
     Ok(path.join(".cookie"))
   }
 
+  pub(crate) fn chain_source(&self) -> Result<Box<dyn ChainSource>> {
+    match &self.esplora_url {
+      Some(esplora_url) => Ok(Box::new(chain_source::esplora::Esplora::new(
+        esplora_url,
+        self.chain(),
+      )?)),
+      None => Ok(Box::new(self.bitcoin_rpc_client(None)?)),
+    }
+  }
+
   pub fn credentials(&self) -> Option<(&str, &str)> {
     self
       .server_username
@@ -458,6 +540,10 @@ impl Settings {
     self.data_dir.as_ref().unwrap().into()
   }
 
+  pub fn esplora_url(&self) -> Option<&str> {
+    self.esplora_url.as_deref()
+  }
+
   pub fn first_rune_height(&self) -> u32 {
     if self.integration_test {
       2
@@ -504,9 +590,30 @@ impl Settings {
     self.bitcoin_rpc_limit.unwrap()
   }
 
+  pub fn schedule(&self) -> Schedule {
+    let schedule = Schedule::new(self.chain());
+
+    match self.versioned_payload_height {
+      Some(height) => {
+        let mut rules = schedule.rules(height);
+        rules.version = Version::V1;
+        schedule.activate(height, rules)
+      }
+      None => schedule,
+    }
+  }
+
   pub fn server_url(&self) -> Option<&str> {
     self.server_url.as_deref()
   }
+
+  pub fn webhooks(&self) -> &[Webhook] {
+    &self.webhooks
+  }
+
+  pub fn zmq_block(&self) -> Option<&str> {
+    self.zmq_block.as_deref()
+  }
 }
 
 #[cfg(test)]
@@ -569,6 +676,36 @@ mod tests {
     );
   }
 
+  #[test]
+  fn versioned_payload_height_requires_regtest() {
+    assert_eq!(
+      Settings::merge(
+        Options {
+          versioned_payload_height: Some(100),
+          ..default()
+        },
+        Default::default(),
+      )
+      .unwrap_err()
+      .to_string(),
+      "versioned payload height may only be set on regtest"
+    );
+
+    let schedule = Settings::merge(
+      Options {
+        regtest: true,
+        versioned_payload_height: Some(100),
+        ..default()
+      },
+      Default::default(),
+    )
+    .unwrap()
+    .schedule();
+
+    assert_eq!(schedule.rules(99).version, Version::Unversioned);
+    assert_eq!(schedule.rules(100).version, Version::V1);
+  }
+
   #[test]
   fn auth_with_user_and_pass() {
     assert_eq!(
@@ -943,12 +1080,14 @@ mod tests {
       ("BITCOIN_RPC_PASSWORD", "bitcoin password"),
       ("BITCOIN_RPC_URL", "url"),
       ("BITCOIN_RPC_USERNAME", "bitcoin username"),
+      ("BLK_FILES", "1"),
       ("CHAIN", "signet"),
       ("COMMIT_INTERVAL", "1"),
       ("CONFIG", "config"),
       ("CONFIG_DIR", "config dir"),
       ("COOKIE_FILE", "cookie file"),
       ("DATA_DIR", "/data/dir"),
+      ("ESPLORA_URL", "esplora url"),
       ("FIRST_RUNE_HEIGHT", "2"),
       ("HEIGHT_LIMIT", "3"),
       ("HTTP_PORT", "8080"),
@@ -961,6 +1100,8 @@ mod tests {
       ("SERVER_PASSWORD", "server password"),
       ("SERVER_URL", "server url"),
       ("SERVER_USERNAME", "server username"),
+      ("VERSIONED_PAYLOAD_HEIGHT", "5"),
+      ("ZMQ_BLOCK", "tcp://127.0.0.1:28332"),
     ]
     .into_iter()
     .map(|(key, value)| (key.into(), value.into()))
@@ -974,12 +1115,14 @@ mod tests {
         bitcoin_rpc_password: Some("bitcoin password".into()),
         bitcoin_rpc_url: Some("url".into()),
         bitcoin_rpc_username: Some("bitcoin username".into()),
+        blk_files: true,
         chain: Some(Chain::Signet),
         commit_interval: Some(1),
         config: Some("config".into()),
         config_dir: Some("config dir".into()),
         cookie_file: Some("cookie file".into()),
         data_dir: Some("/data/dir".into()),
+        esplora_url: Some("esplora url".into()),
         first_rune_height: Some(2),
         height_limit: Some(3),
         http_port: Some(8080),
@@ -991,6 +1134,9 @@ mod tests {
         server_password: Some("server password".into()),
         server_url: Some("server url".into()),
         server_username: Some("server username".into()),
+        versioned_payload_height: Some(5),
+        webhooks: Vec::new(),
+        zmq_block: Some("tcp://127.0.0.1:28332".into()),
       }
     );
   }
@@ -1006,12 +1152,14 @@ mod tests {
           "--bitcoin-rpc-password=bitcoin password",
           "--bitcoin-rpc-url=url",
           "--bitcoin-rpc-username=bitcoin username",
+          "--blk-files",
           "--chain=signet",
           "--commit-interval=1",
           "--config=config",
           "--config-dir=config dir",
           "--cookie-file=cookie file",
           "--datadir=/data/dir",
+          "--esplora-url=esplora url",
           "--first-rune-height=2",
           "--height-limit=3",
           "--index-addresses",
@@ -1021,6 +1169,8 @@ mod tests {
           "--integration-test",
           "--server-password=server password",
           "--server-username=server username",
+          "--versioned-payload-height=5",
+          "--zmq-block=tcp://127.0.0.1:28332",
         ])
         .unwrap()
       ),
@@ -1030,12 +1180,14 @@ mod tests {
         bitcoin_rpc_password: Some("bitcoin password".into()),
         bitcoin_rpc_url: Some("url".into()),
         bitcoin_rpc_username: Some("bitcoin username".into()),
+        blk_files: true,
         chain: Some(Chain::Signet),
         commit_interval: Some(1),
         config: Some("config".into()),
         config_dir: Some("config dir".into()),
         cookie_file: Some("cookie file".into()),
         data_dir: Some("/data/dir".into()),
+        esplora_url: Some("esplora url".into()),
         first_rune_height: Some(2),
         height_limit: Some(3),
         http_port: None,
@@ -1047,6 +1199,9 @@ mod tests {
         server_password: Some("server password".into()),
         server_url: None,
         server_username: Some("server username".into()),
+        versioned_payload_height: Some(5),
+        webhooks: Vec::new(),
+        zmq_block: Some("tcp://127.0.0.1:28332".into()),
       }
     );
   }
@@ -10,6 +10,7 @@ pub(crate) mod server;
 mod settings;
 pub mod util;
 pub mod wallet;
+pub mod webhooks;
 
 #[derive(Debug, Parser)]
 pub(crate) enum Subcommand {
@@ -27,6 +28,8 @@ pub(crate) enum Subcommand {
   Settings,
   #[command(about = "Wallet commands")]
   Wallet(wallet::WalletCommand),
+  #[command(subcommand, about = "Webhook commands")]
+  Webhooks(webhooks::WebhooksSubcommand),
   #[command(about = "Display current monetary policy")]
   MonetaryPolicy,
   #[command(about = "Display recent interest rates")]
@@ -45,13 +48,14 @@ impl Subcommand {
       Self::Env(env) => env.run(),
       Self::Index(index) => index.run(settings),
       Self::Server(server) => {
-        let index = Arc::new(Index::open(&settings)?);
+        let (index, event_receiver) = server.open_index(&settings)?;
         let handle = axum_server::Handle::new();
         LISTENERS.lock().unwrap().push(handle.clone());
-        server.run(settings, index, handle)
+        server.run(settings, Arc::new(index), event_receiver, handle)
       }
       Self::Settings => settings::run(settings),
       Self::Wallet(wallet) => wallet.run(settings),
+      Self::Webhooks(webhooks) => webhooks.run(settings),
       Self::MonetaryPolicy => util::run(settings),
       Self::RateHistory => history::run(settings),
       Self::UtilToSat(util_to_sat) => util_to_sat.run(settings),
@@ -5,32 +5,157 @@ pub(crate) struct Decode {
   #[arg(
     long,
     conflicts_with = "file",
-    help = "Fetch transaction with <TXID> from Bitcoin Core."
+    help = "Fetch transaction with <TXID> from Bitcoin Core, or from Esplora if --esplora-url is set."
   )]
   txid: Option<Txid>,
   #[arg(long, conflicts_with = "txid", help = "Load transaction from <FILE>.")]
   file: Option<PathBuf>,
+  #[arg(
+    long,
+    help = "Include diagnostics explaining how the runestone was decoded."
+  )]
+  debug: bool,
+  #[arg(
+    long,
+    help = "Decode with the consensus rules in effect at <HEIGHT>. [default: confirmation height of --txid, or latest scheduled rules]"
+  )]
+  height: Option<u32>,
+  #[arg(
+    long,
+    requires = "txid",
+    help = "Look up why the conversion in the transaction failed in the read-only copy of the index published by `bitomc index update --follow`."
+  )]
+  failed_conversion: bool,
 }
 
 #[derive(Serialize, Eq, PartialEq, Deserialize, Debug)]
 pub struct Output {
   pub runestone: Option<Artifact>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Diagnostics>,
+  /// Why the conversion in this transaction failed, if it was indexed
+  /// and attempted one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub failed_conversion: Option<FailedConversionEntry>,
+}
+
+impl Output {
+  pub(crate) fn new(transaction: &Transaction, version: Version, debug: bool) -> Self {
+    if debug {
+      let diagnostics = Runestone::diagnose_with(transaction, version);
+      Self {
+        runestone: diagnostics.clone().map(Artifact::from),
+        diagnostics,
+        failed_conversion: None,
+      }
+    } else {
+      Self {
+        runestone: Runestone::decipher_with(transaction, version),
+        diagnostics: None,
+        failed_conversion: None,
+      }
+    }
+  }
 }
 
 impl Decode {
   pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    let mut failed_conversion = None;
+    let mut height = None;
+
     let transaction = if let Some(txid) = self.txid {
-      settings
-        .bitcoin_rpc_client(None)?
-        .get_raw_transaction(&txid, None)?
+      if self.failed_conversion {
+        // the published read-only copy can be read without taking the write
+        // lock held by a running server or indexer
+        failed_conversion = Index::open_read_only(&settings)?.get_failed_conversion(txid)?;
+      }
+
+      let chain_source = settings.chain_source()?;
+
+      let transaction = chain_source
+        .raw_transaction(txid)?
+        .with_context(|| format!("transaction {txid} not found"))?;
+
+      // unconfirmed transactions are decoded with the rules of the next block
+      height = Some(match chain_source.transaction_height(txid)? {
+        Some(height) => height,
+        None => u32::try_from(chain_source.block_count()?)? + 1,
+      });
+
+      transaction
     } else if let Some(file) = self.file {
       Transaction::consensus_decode(&mut fs::File::open(file)?)?
     } else {
       Transaction::consensus_decode(&mut io::stdin())?
     };
 
-    let runestone = Runestone::decipher(&transaction);
+    let rules = settings
+      .schedule()
+      .rules(self.height.or(height).unwrap_or(u32::MAX));
+
+    let mut output = Output::new(&transaction, rules.version, self.debug);
+    output.failed_conversion = failed_conversion;
+
+    Ok(Some(Box::new(output)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, crate::chain_source::esplora::tests::StandIn, mockcore::TransactionTemplate};
+
+  #[test]
+  fn txid_is_fetched_from_esplora() {
+    let core = mockcore::builder().network(Network::Regtest).build();
+
+    core.mine_blocks(1);
+
+    let txid = core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    core.mine_blocks(1);
+
+    let stand_in = StandIn::spawn(crate::chain_source::esplora::tests::blocks(&core));
+
+    let tempdir = TempDir::new().unwrap();
+
+    // bitcoind is unreachable, so the transaction can only come from Esplora
+    let settings = Settings::merge(
+      Options::try_parse_from([
+        "bitomc",
+        "--regtest",
+        "--bitcoin-rpc-url",
+        "127.0.0.1:1",
+        "--datadir",
+        tempdir.path().to_str().unwrap(),
+        "--esplora-url",
+        &stand_in.url,
+      ])
+      .unwrap(),
+      BTreeMap::new(),
+    )
+    .unwrap();
+
+    let decode = |txid| Decode {
+      txid: Some(txid),
+      file: None,
+      debug: false,
+      height: None,
+      failed_conversion: false,
+    };
+
+    decode(txid).run(settings.clone()).unwrap();
 
-    Ok(Some(Box::new(Output { runestone })))
+    assert_eq!(
+      decode(Txid::all_zeros())
+        .run(settings)
+        .err()
+        .unwrap()
+        .to_string(),
+      format!("transaction {} not found", Txid::all_zeros()),
+    );
   }
 }
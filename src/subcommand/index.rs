@@ -1,21 +1,37 @@
 use super::*;
 
+pub mod export;
+mod import;
 pub mod info;
+pub mod migrate;
 mod update;
+pub mod verify;
 
 #[derive(Debug, Parser)]
 pub(crate) enum IndexSubcommand {
+  #[command(about = "Write a snapshot of the index at a height")]
+  Export(export::Export),
+  #[command(about = "Bootstrap an empty index from a snapshot")]
+  Import(import::Import),
   #[command(about = "Print index statistics")]
   Info(info::Info),
+  #[command(about = "Migrate the index to the current schema")]
+  Migrate(migrate::Migrate),
   #[command(about = "Update the index", alias = "run")]
-  Update,
+  Update(update::Update),
+  #[command(about = "Check the index for inconsistencies, without updating it")]
+  Verify,
 }
 
 impl IndexSubcommand {
   pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
     match self {
+      Self::Export(export) => export.run(settings),
+      Self::Import(import) => import.run(settings),
       Self::Info(info) => info.run(settings),
-      Self::Update => update::run(settings),
+      Self::Migrate(migrate) => migrate.run(settings),
+      Self::Update(update) => update.run(settings),
+      Self::Verify => verify::run(settings),
     }
   }
 }
@@ -0,0 +1,36 @@
+use super::*;
+
+#[derive(Debug, Parser)]
+pub(crate) struct Export {
+  #[arg(long, help = "Export index state at <HEIGHT>.")]
+  height: u32,
+  #[arg(help = "Write snapshot to <PATH>.")]
+  path: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub height: u32,
+  pub block_hash: BlockHash,
+  pub state_commitment: Option<sha256::Hash>,
+}
+
+impl Export {
+  pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    let snapshot = Index::open(&settings)?.export(self.height)?;
+
+    serde_json::to_writer(
+      io::BufWriter::new(
+        fs::File::create(&self.path)
+          .with_context(|| format!("failed to create `{}`", self.path.display()))?,
+      ),
+      &snapshot,
+    )?;
+
+    Ok(Some(Box::new(Output {
+      height: snapshot.height,
+      block_hash: snapshot.block_hash,
+      state_commitment: snapshot.state_commitment,
+    })))
+  }
+}
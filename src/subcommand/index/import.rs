@@ -0,0 +1,27 @@
+use {super::*, crate::index::Snapshot, export::Output};
+
+#[derive(Debug, Parser)]
+pub(crate) struct Import {
+  #[arg(help = "Read snapshot from <PATH>.")]
+  path: PathBuf,
+}
+
+impl Import {
+  pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    let snapshot: Snapshot = serde_json::from_reader(io::BufReader::new(
+      fs::File::open(&self.path)
+        .with_context(|| format!("failed to open `{}`", self.path.display()))?,
+    ))
+    .with_context(|| format!("failed to parse snapshot `{}`", self.path.display()))?;
+
+    let output = Output {
+      height: snapshot.height,
+      block_hash: snapshot.block_hash,
+      state_commitment: snapshot.state_commitment,
+    };
+
+    Index::open(&settings)?.import(snapshot)?;
+
+    Ok(Some(Box::new(output)))
+  }
+}
@@ -0,0 +1,30 @@
+use {super::*, crate::index::MigrationStep};
+
+#[derive(Debug, Parser)]
+pub(crate) struct Migrate {
+  #[arg(long, help = "List pending migrations without running them.")]
+  dry_run: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub dry_run: bool,
+  pub from: u64,
+  pub migrations: Vec<MigrationStep>,
+}
+
+impl Migrate {
+  pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    let (from, migrations) = Index::pending_migrations(&settings)?;
+
+    if !self.dry_run {
+      Index::open(&settings)?;
+    }
+
+    Ok(Some(Box::new(Output {
+      dry_run: self.dry_run,
+      from,
+      migrations,
+    })))
+  }
+}
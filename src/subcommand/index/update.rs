@@ -1,9 +1,68 @@
 use super::*;
 
-pub(crate) fn run(settings: Settings) -> SubcommandResult {
-  let index = Index::open(&settings)?;
+#[derive(Debug, Parser)]
+pub(crate) struct Update {
+  #[arg(
+    long,
+    help = "Keep updating the index, and publish a read-only copy for `bitomc server --read-only` when the tip changes."
+  )]
+  follow: bool,
+  #[arg(
+    long,
+    default_value = "5s",
+    help = "When following, poll Bitcoin Core every <POLLING_INTERVAL>."
+  )]
+  polling_interval: humantime::Duration,
+  #[arg(
+    long,
+    default_value = "10m",
+    help = "When following, publish at most once every <PUBLISH_INTERVAL>. Each publish copies the whole index."
+  )]
+  publish_interval: humantime::Duration,
+}
+
+impl Update {
+  pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    let mut index = Index::open(&settings)?;
+
+    if !self.follow {
+      index.update()?;
+      return Ok(None);
+    }
+
+    index.deliver_webhooks();
+
+    let block_notifications = BlockNotifications::subscribe(settings.zmq_block())?;
+
+    let mut published = None;
+    let mut last_published: Option<Instant> = None;
+
+    loop {
+      if let Err(error) = index.update() {
+        log::warn!("Updating index: {error}");
+      }
+
+      if SHUTTING_DOWN.load(atomic::Ordering::Relaxed) {
+        break;
+      }
+
+      let tip = index.block_hash(None)?;
+
+      let due = last_published.map_or(true, |instant| instant.elapsed() >= *self.publish_interval);
+
+      if published != Some(tip) && due {
+        let block_count = index.block_count()?;
+        index.publish()?;
+        index = Index::open(&settings)?;
+        index.deliver_webhooks();
+        log::info!("Published read-only index with {block_count} blocks");
+        published = Some(tip);
+        last_published = Some(Instant::now());
+      }
 
-  index.update()?;
+      block_notifications.wait(self.polling_interval.into());
+    }
 
-  Ok(None)
+    Ok(None)
+  }
 }
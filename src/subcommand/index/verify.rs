@@ -0,0 +1,14 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub discrepancies: Vec<Discrepancy>,
+}
+
+pub(crate) fn run(settings: Settings) -> SubcommandResult {
+  let index = Index::open(&settings)?;
+
+  Ok(Some(Box::new(Output {
+    discrepancies: index.verify()?,
+  })))
+}
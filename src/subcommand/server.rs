@@ -2,17 +2,28 @@ use {
   self::{
     accept_json::AcceptJson,
     error::{OptionExt, ServerError, ServerResult},
+    events::Events,
   },
   super::*,
-  crate::templates::{
-    AddressHtml, BlockHtml, BlocksHtml, ClockSvg, HomeHtml, InputHtml, OutputHtml, PageContent,
-    PageHtml, RuneHtml, RunesHtml, TransactionHtml,
+  crate::{
+    index::event::{Event, EventId, Filter},
+    templates::{
+      AddressHtml, BlockHtml, BlocksHtml, ClockSvg, HomeHtml, InputHtml, MintsHtml, OutputHtml,
+      PageContent, PageHtml, RuneHtml, RunesHtml, TransactionHtml,
+    },
   },
   axum::{
     body,
-    extract::{DefaultBodyLimit, Extension, Json, Path, Query},
-    http::{header, HeaderValue, StatusCode, Uri},
-    response::{IntoResponse, Redirect, Response},
+    extract::{
+      ws::{Message, WebSocket, WebSocketUpgrade},
+      DefaultBodyLimit, Extension, Json, Path, Query, State,
+    },
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{
+      sse::{KeepAlive, Sse},
+      IntoResponse, Redirect, Response,
+    },
     routing::{get, post},
     Router,
   },
@@ -24,10 +35,17 @@ use {
     caches::DirCache,
     AcmeConfig,
   },
-  std::{str, sync::Arc},
+  std::{
+    convert::Infallible,
+    str,
+    sync::{Arc, RwLock},
+  },
   tokio_stream::StreamExt,
   tower_http::{
-    compression::CompressionLayer,
+    compression::{
+      predicate::{DefaultPredicate, NotForContentType, Predicate},
+      CompressionLayer,
+    },
     cors::{Any, CorsLayer},
     set_header::SetResponseHeaderLayer,
     validate_request::ValidateRequestHeaderLayer,
@@ -38,6 +56,7 @@ pub(crate) use server_config::ServerConfig;
 
 mod accept_json;
 mod error;
+mod events;
 pub mod query;
 mod server_config;
 
@@ -52,6 +71,33 @@ struct Search {
   query: String,
 }
 
+#[derive(Deserialize)]
+struct DecodeQuery {
+  #[serde(default)]
+  debug: bool,
+}
+
+#[derive(Deserialize)]
+struct HeightQuery {
+  height: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SimulateQuery {
+  #[serde(default)]
+  verbose: bool,
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+  address: Option<Address<NetworkUnchecked>>,
+  last_event_id: Option<EventId>,
+  outpoint: Option<OutPoint>,
+  rune: Option<DeserializeFromStr<query::Rune>>,
+  #[serde(rename = "type")]
+  types: Option<String>,
+}
+
 #[derive(RustEmbed)]
 #[folder = "static"]
 struct StaticAssets;
@@ -99,26 +145,75 @@ pub struct Server {
     help = "Poll Bitcoin Core every <POLLING_INTERVAL>."
   )]
   pub(crate) polling_interval: humantime::Duration,
+  #[arg(
+    long,
+    help = "Serve the read-only copy of the index published by `bitomc index update --follow`, reloading it when a new copy is published."
+  )]
+  pub(crate) read_only: bool,
 }
 
 impl Server {
-  pub fn run(self, settings: Settings, index: Arc<Index>, handle: Handle) -> SubcommandResult {
+  /// Open the index to serve, along with a receiver for the events it sends
+  /// while updating, unless serving a read-only copy, which is never updated
+  pub fn open_index(
+    &self,
+    settings: &Settings,
+  ) -> Result<(Index, Option<tokio::sync::mpsc::Receiver<Event>>)> {
+    if self.read_only {
+      Ok((Index::open_read_only(settings)?, None))
+    } else {
+      let (sender, receiver) = tokio::sync::mpsc::channel(Events::CHANNEL_CAPACITY);
+      Ok((
+        Index::open_with_event_sender(settings, Some(sender))?,
+        Some(receiver),
+      ))
+    }
+  }
+
+  pub fn run(
+    self,
+    settings: Settings,
+    index: Arc<Index>,
+    event_receiver: Option<tokio::sync::mpsc::Receiver<Event>>,
+    handle: Handle,
+  ) -> SubcommandResult {
     Runtime::new()?.block_on(async {
-      let index_clone = index.clone();
+      let events = event_receiver.map(|receiver| {
+        let events = Arc::new(Events::new());
+        tokio::spawn(events.clone().relay(receiver));
+        events
+      });
+
+      index.deliver_webhooks();
+
+      let current = Arc::new(RwLock::new(index));
+      let current_clone = current.clone();
+      let settings_clone = settings.clone();
       let integration_test = settings.integration_test();
 
+      let block_notifications = BlockNotifications::subscribe(if self.no_sync || self.read_only {
+        None
+      } else {
+        settings.zmq_block()
+      })?;
+
       let index_thread = thread::spawn(move || loop {
         if SHUTTING_DOWN.load(atomic::Ordering::Relaxed) {
           break;
         }
 
-        if !self.no_sync {
-          if let Err(error) = index_clone.update() {
+        if self.read_only {
+          if let Err(error) = Self::reload(&settings_clone, &current_clone) {
+            log::warn!("Reloading read-only index: {error}");
+          }
+        } else if !self.no_sync {
+          let index = current_clone.read().unwrap().clone();
+          if let Err(error) = index.update() {
             log::warn!("Updating index: {error}");
           }
         }
 
-        thread::sleep(if integration_test {
+        block_notifications.wait(if integration_test {
           Duration::from_millis(100)
         } else {
           self.polling_interval.into()
@@ -147,11 +242,15 @@ impl Server {
         .route("/blocks", get(Self::blocks))
         .route("/blocktime", get(Self::block_time))
         .route("/clock", get(Self::clock))
+        .route("/conversions", get(Self::conversions))
+        .route("/conversions/:page", get(Self::conversions_paginated))
         .route("/faq", get(Self::faq))
         .route("/favicon.ico", get(Self::favicon))
         .route("/feed.xml", get(Self::feed))
         .route("/input/:block/:transaction/:input", get(Self::input))
         .route("/install.sh", get(Self::install_script))
+        .route("/mints", get(Self::mints))
+        .route("/mints/:page", get(Self::mints_paginated))
         .route("/output/:output", get(Self::output))
         .route("/outputs", post(Self::outputs))
         .route("/r/blockhash", get(Self::block_hash_json))
@@ -174,10 +273,12 @@ impl Server {
         .route("/status", get(Self::status))
         .route("/tx/:txid", get(Self::transaction))
         .route("/decode/:txid", get(Self::decode))
+        .route("/events", get(Self::events))
         .route("/update", get(Self::update))
         .route("/util", get(Self::util))
         .fallback(Self::fallback)
-        .layer(Extension(index))
+        .layer(middleware::from_fn_with_state(current, Self::current_index))
+        .layer(Extension(events))
         .layer(Extension(server_config.clone()))
         .layer(Extension(settings.clone()))
         .layer(SetResponseHeaderLayer::if_not_present(
@@ -193,7 +294,13 @@ impl Server {
             .allow_methods([http::Method::GET])
             .allow_origin(Any),
         )
-        .layer(CompressionLayer::new())
+        .layer(
+          // compressing server-sent events would buffer them until the
+          // compressor flushes
+          CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream")),
+          ),
+        )
         .with_state(server_config.clone());
 
       let router = if server_config.json_api_enabled {
@@ -393,10 +500,53 @@ impl Server {
     Ok(acceptor)
   }
 
+  /// Replace a read-only index with the latest published copy, if a newer
+  /// copy has been published since it was opened. Requests which are already
+  /// being served keep using the copy they started with.
+  fn reload(settings: &Settings, current: &RwLock<Arc<Index>>) -> Result {
+    if !current.read().unwrap().is_stale()? {
+      return Ok(());
+    }
+
+    let index = Arc::new(Index::open_read_only(settings)?);
+
+    log::info!(
+      "Reloaded read-only index at height {}",
+      index
+        .block_height()?
+        .map(|height| height.to_string())
+        .unwrap_or_else(|| "none".into())
+    );
+
+    *current.write().unwrap() = index;
+
+    Ok(())
+  }
+
+  async fn current_index<B>(
+    State(current): State<Arc<RwLock<Arc<Index>>>>,
+    mut request: Request<B>,
+    next: Next<B>,
+  ) -> Response {
+    let index = current.read().unwrap().clone();
+    request.extensions_mut().insert(index);
+    next.run(request).await
+  }
+
   fn index_height(index: &Index) -> ServerResult<Height> {
     index.block_height()?.ok_or_not_found(|| "genesis block")
   }
 
+  fn ensure_not_pruned(index: &Index, height: u32) -> ServerResult<()> {
+    if index.is_pruned(height) {
+      return Err(ServerError::NotFound(format!(
+        "block {height} has been pruned by bitcoind"
+      )));
+    }
+
+    Ok(())
+  }
+
   async fn clock(Extension(index): Extension<Arc<Index>>) -> ServerResult {
     task::block_in_place(|| {
       Ok(
@@ -490,12 +640,16 @@ impl Server {
   async fn simulate(
     Extension(index): Extension<Arc<Index>>,
     AcceptJson(accept_json): AcceptJson,
+    Query(query): Query<SimulateQuery>,
     Json(transactions): Json<Vec<Transaction>>,
   ) -> ServerResult {
     task::block_in_place(|| {
       Ok(if accept_json {
-        let simulation = index.simulate(transactions)?;
-        Json(simulation).into_response()
+        if query.verbose {
+          Json(index.simulate_verbose(transactions)?).into_response()
+        } else {
+          Json(index.simulate(transactions)?).into_response()
+        }
       } else {
         StatusCode::NOT_FOUND.into_response()
       })
@@ -509,19 +663,7 @@ impl Server {
     AcceptJson(accept_json): AcceptJson,
   ) -> ServerResult {
     task::block_in_place(|| {
-      let rune = match rune_query {
-        query::Rune::Spaced(spaced_rune) => spaced_rune.rune,
-        query::Rune::Id(rune_id) => index
-          .get_rune_by_id(rune_id)?
-          .ok_or_not_found(|| format!("rune {rune_id}"))?,
-        query::Rune::Number(number) => index
-          .get_rune_by_number(usize::try_from(number).unwrap())?
-          .ok_or_not_found(|| format!("rune number {number}"))?,
-      };
-
-      let (id, entry) = index
-        .rune(rune)?
-        .ok_or_not_found(|| format!("rune {rune}"))?;
+      let (id, entry) = Self::find_rune(&index, rune_query)?;
 
       let mintable = true;
 
@@ -546,6 +688,209 @@ impl Server {
     })
   }
 
+  fn find_rune(index: &Index, rune_query: query::Rune) -> ServerResult<(RuneId, RuneEntry)> {
+    let rune = match rune_query {
+      query::Rune::Spaced(spaced_rune) => spaced_rune.rune,
+      query::Rune::Id(rune_id) => index
+        .get_rune_by_id(rune_id)?
+        .ok_or_not_found(|| format!("rune {rune_id}"))?,
+      query::Rune::Number(number) => index
+        .get_rune_by_number(usize::try_from(number).unwrap())?
+        .ok_or_not_found(|| format!("rune number {number}"))?,
+    };
+
+    index.rune(rune)?.ok_or_not_found(|| format!("rune {rune}"))
+  }
+
+  async fn events(
+    Extension(server_config): Extension<Arc<ServerConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    Extension(events): Extension<Option<Arc<Events>>>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+    websocket: Option<WebSocketUpgrade>,
+  ) -> ServerResult {
+    task::block_in_place(|| {
+      if !server_config.json_api_enabled {
+        return Ok((StatusCode::NOT_ACCEPTABLE, "JSON API disabled").into_response());
+      }
+
+      let events = events.ok_or_else(|| {
+        ServerError::NotFound("read-only servers do not have an event stream".into())
+      })?;
+
+      let types = query
+        .types
+        .map(|types| {
+          types
+            .split(',')
+            .map(|name| {
+              if Event::TYPES.contains(&name) {
+                Ok(name.to_string())
+              } else {
+                Err(ServerError::BadRequest(format!(
+                  "unknown event type `{name}`, expected one of {}",
+                  Event::TYPES.join(", ")
+                )))
+              }
+            })
+            .collect::<ServerResult<HashSet<String>>>()
+        })
+        .transpose()?;
+
+      let rune = query
+        .rune
+        .map(|DeserializeFromStr(rune_query)| Self::find_rune(&index, rune_query))
+        .transpose()?
+        .map(|(id, _entry)| id);
+
+      let script_pubkey = query
+        .address
+        .map(|address| {
+          address
+            .require_network(server_config.chain.network())
+            .map(|address| address.script_pubkey())
+            .map_err(|err| ServerError::BadRequest(err.to_string()))
+        })
+        .transpose()?;
+
+      // browsers set the `Last-Event-ID` header when an `EventSource`
+      // reconnects, but `WebSocket`s can't set headers, so the cursor may
+      // also be passed as a query parameter
+      let last_event_id = match headers.get("last-event-id") {
+        Some(value) => Some(
+          value
+            .to_str()
+            .map_err(|err| ServerError::BadRequest(err.to_string()))?
+            .parse::<EventId>()
+            .map_err(|err| ServerError::BadRequest(err.to_string()))?,
+        ),
+        None => query.last_event_id,
+      };
+
+      let stream = events.subscribe(
+        Filter {
+          outpoint: query.outpoint,
+          rune,
+          script_pubkey,
+          types,
+        },
+        last_event_id,
+      );
+
+      Ok(match websocket {
+        Some(websocket) => websocket
+          .on_upgrade(|socket| Self::send_events(socket, stream))
+          .into_response(),
+        None => Sse::new(stream.map(|item| Ok::<_, Infallible>(item.sse())))
+          .keep_alive(KeepAlive::default())
+          .into_response(),
+      })
+    })
+  }
+
+  async fn send_events(
+    mut socket: WebSocket,
+    stream: impl tokio_stream::Stream<Item = events::Item> + Send + 'static,
+  ) {
+    tokio::pin!(stream);
+
+    loop {
+      tokio::select! {
+        item = stream.next() => {
+          let Some(item) = item else {
+            break;
+          };
+
+          if socket.send(Message::Text(item.json())).await.is_err() {
+            break;
+          }
+        }
+        message = socket.recv() => match message {
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+          Some(Ok(_)) => {}
+        },
+      }
+    }
+  }
+
+  async fn mints(
+    Extension(server_config): Extension<Arc<ServerConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    accept_json: AcceptJson,
+  ) -> ServerResult {
+    Self::mints_paginated(
+      Extension(server_config),
+      Extension(index),
+      Path(0),
+      accept_json,
+    )
+    .await
+  }
+
+  async fn mints_paginated(
+    Extension(server_config): Extension<Arc<ServerConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    Path(page_index): Path<usize>,
+    AcceptJson(accept_json): AcceptJson,
+  ) -> ServerResult {
+    task::block_in_place(|| {
+      let (entries, more) = index.mints_paginated(50, page_index)?;
+
+      let prev = page_index.checked_sub(1);
+
+      let next = more.then_some(page_index + 1);
+
+      Ok(if accept_json {
+        Json(MintsHtml {
+          entries,
+          more,
+          prev,
+          next,
+        })
+        .into_response()
+      } else {
+        MintsHtml {
+          entries,
+          more,
+          prev,
+          next,
+        }
+        .page(server_config)
+        .into_response()
+      })
+    })
+  }
+
+  async fn conversions(
+    Extension(index): Extension<Arc<Index>>,
+    accept_json: AcceptJson,
+  ) -> ServerResult {
+    Self::conversions_paginated(Extension(index), Path(0), accept_json).await
+  }
+
+  async fn conversions_paginated(
+    Extension(index): Extension<Arc<Index>>,
+    Path(page_index): Path<usize>,
+    AcceptJson(accept_json): AcceptJson,
+  ) -> ServerResult {
+    task::block_in_place(|| {
+      Ok(if accept_json {
+        let (entries, more) = index.conversions_paginated(50, page_index)?;
+
+        Json(api::Conversions {
+          entries,
+          more,
+          prev: page_index.checked_sub(1),
+          next: more.then_some(page_index + 1),
+        })
+        .into_response()
+      } else {
+        StatusCode::NOT_FOUND.into_response()
+      })
+    })
+  }
+
   async fn runes(
     Extension(server_config): Extension<Arc<ServerConfig>>,
     Extension(index): Extension<Arc<Index>>,
@@ -682,15 +1027,19 @@ impl Server {
 
       outputs.sort();
 
-      let sat_balance = index.get_sat_balances_for_outputs(&outputs)?;
+      let info = index.get_address_balances(&address, outputs)?;
 
       Ok(if accept_json {
-        Json(outputs).into_response()
+        Json(info).into_response()
       } else {
         AddressHtml {
           address,
-          outputs,
-          sat_balance,
+          outputs: info.outputs,
+          sat_balance: info.sat_balance,
+          tighten_balance: info.tighten_balance,
+          ease_balance: info.ease_balance,
+          invariant_balance: info.invariant_balance,
+          util_balance: info.util_balance,
         }
         .page(server_config)
         .into_response()
@@ -707,6 +1056,8 @@ impl Server {
     task::block_in_place(|| {
       let (block, height) = match query {
         query::Block::Height(height) => {
+          Self::ensure_not_pruned(&index, height)?;
+
           let block = index
             .get_block_by_height(height)?
             .ok_or_not_found(|| format!("block {height}"))?;
@@ -718,6 +1069,8 @@ impl Server {
             .block_header_info(hash)?
             .ok_or_not_found(|| format!("block {hash}"))?;
 
+          Self::ensure_not_pruned(&index, u32::try_from(info.height).unwrap())?;
+
           let block = index
             .get_block_by_hash(hash)?
             .ok_or_not_found(|| format!("block {hash}"))?;
@@ -733,6 +1086,7 @@ impl Server {
           Height(height),
           Self::index_height(&index)?,
           runes,
+          index.get_state_commitment_at(height)?,
         ))
         .into_response()
       } else {
@@ -763,10 +1117,13 @@ impl Server {
 
       let inscription_count = 0;
 
+      let failed_conversion = index.get_failed_conversion(txid)?;
+
       Ok(if accept_json {
         Json(api::Transaction {
           chain: server_config.chain,
           etching: None,
+          failed_conversion,
           inscription_count,
           transaction,
           txid,
@@ -776,6 +1133,7 @@ impl Server {
         TransactionHtml {
           chain: server_config.chain,
           etching: None,
+          failed_conversion,
           inscription_count,
           transaction,
           txid,
@@ -789,6 +1147,7 @@ impl Server {
   async fn decode(
     Extension(index): Extension<Arc<Index>>,
     Path(txid): Path<Txid>,
+    Query(query): Query<DecodeQuery>,
     AcceptJson(accept_json): AcceptJson,
   ) -> ServerResult {
     task::block_in_place(|| {
@@ -796,10 +1155,11 @@ impl Server {
         .get_transaction(txid)?
         .ok_or_not_found(|| format!("transaction {txid}"))?;
 
-      let runestone = Runestone::decipher(&transaction);
-
       Ok(if accept_json {
-        Json(api::Decode { runestone }).into_response()
+        let rules = index.transaction_rules(txid)?;
+        let mut decode = api::Decode::new(&transaction, rules.version, query.debug);
+        decode.failed_conversion = index.get_failed_conversion(txid)?;
+        Json(decode).into_response()
       } else {
         StatusCode::NOT_FOUND.into_response()
       })
@@ -823,10 +1183,18 @@ impl Server {
   async fn util(
     Extension(index): Extension<Arc<Index>>,
     AcceptJson(accept_json): AcceptJson,
+    Query(query): Query<HeightQuery>,
   ) -> ServerResult {
     task::block_in_place(|| {
       Ok(if accept_json {
-        Json(index.get_util_state()?).into_response()
+        let util_state = match query.height {
+          Some(height) => index
+            .get_util_state_at(height)?
+            .ok_or_not_found(|| format!("util state at height {height}"))?,
+          None => index.get_util_state()?,
+        };
+
+        Json(util_state).into_response()
       } else {
         StatusCode::NOT_FOUND.into_response()
       })
@@ -836,10 +1204,18 @@ impl Server {
   async fn rate_history(
     Extension(index): Extension<Arc<Index>>,
     AcceptJson(accept_json): AcceptJson,
+    Query(query): Query<HeightQuery>,
   ) -> ServerResult {
     task::block_in_place(|| {
       Ok(if accept_json {
-        Json(index.get_rate_history()?).into_response()
+        let rate_history = match query.height {
+          Some(height) => index
+            .get_rate_history_at(height)?
+            .ok_or_not_found(|| format!("rate history at height {height}"))?,
+          None => index.get_rate_history()?,
+        };
+
+        Json(rate_history).into_response()
       } else {
         StatusCode::NOT_FOUND.into_response()
       })
@@ -1113,6 +1489,8 @@ impl Server {
     task::block_in_place(|| {
       let not_found = || format!("input /{}/{}/{}", path.0, path.1, path.2);
 
+      Self::ensure_not_pruned(&index, path.0)?;
+
       let block = index
         .get_block_by_height(path.0)?
         .ok_or_not_found(not_found)?;
@@ -1151,7 +1529,12 @@ impl Server {
 
 #[cfg(test)]
 mod tests {
-  use {super::*, reqwest::Url, std::net::TcpListener, tempfile::TempDir};
+  use {
+    super::*,
+    reqwest::Url,
+    std::{io::BufRead, mem, net::TcpListener},
+    tempfile::TempDir,
+  };
 
   #[derive(Default)]
   struct Builder {
@@ -1256,13 +1639,18 @@ mod tests {
         .or_defaults()
         .unwrap();
 
-      let index = Arc::new(Index::open(&settings).unwrap());
+      let (index, event_receiver) = server.open_index(&settings).unwrap();
+      let index = Arc::new(index);
       let ord_server_handle = Handle::new();
 
       {
         let index = index.clone();
         let ord_server_handle = ord_server_handle.clone();
-        thread::spawn(|| server.run(settings, index, ord_server_handle).unwrap());
+        thread::spawn(|| {
+          server
+            .run(settings, index, event_receiver, ord_server_handle)
+            .unwrap()
+        });
       }
 
       while index.statistic(crate::index::Statistic::Commits) == 0 {
@@ -1985,6 +2373,44 @@ mod tests {
     );
   }
 
+  #[test]
+  fn pruned_blocks_are_not_found() {
+    let test_server = TestServer::builder()
+      .chain(Chain::Regtest)
+      .ord_option("--first-rune-height", "10")
+      .build();
+
+    test_server.mine_blocks(5);
+
+    test_server.core.prune(3);
+
+    test_server.assert_response(
+      "/block/2",
+      StatusCode::NOT_FOUND,
+      "block 2 has been pruned by bitcoind",
+    );
+
+    test_server.assert_response(
+      format!("/block/{}", test_server.core.state().hashes[2]),
+      StatusCode::NOT_FOUND,
+      "block 2 has been pruned by bitcoind",
+    );
+
+    test_server.assert_response(
+      "/input/2/0/0",
+      StatusCode::NOT_FOUND,
+      "block 2 has been pruned by bitcoind",
+    );
+
+    assert_eq!(test_server.get("/block/3").status(), StatusCode::OK);
+
+    test_server.assert_response_regex(
+      "/status",
+      StatusCode::OK,
+      ".*<dt>prune height</dt>\n  <dd>3</dd>\n  <dt>unavailable</dt>\n  <dd>blocks below height 3, address index</dd>.*",
+    );
+  }
+
   #[test]
   fn detect_unrecoverable_reorg() {
     let test_server = TestServer::new();
@@ -2054,4 +2480,195 @@ mod tests {
       "br"
     );
   }
+
+  /// Read server-sent events from `response` until `n` have been read,
+  /// returning their IDs and data
+  fn server_sent_events(
+    response: &mut io::BufReader<reqwest::blocking::Response>,
+    n: usize,
+  ) -> Vec<(Option<String>, String)> {
+    let mut events = Vec::new();
+    let (mut id, mut data) = (None, String::new());
+
+    while events.len() < n {
+      let mut line = String::new();
+      response.read_line(&mut line).unwrap();
+
+      match line.trim_end().split_once(':') {
+        Some(("id", value)) => id = Some(value.trim().to_string()),
+        Some(("data", value)) => data = value.trim().to_string(),
+        None if !data.is_empty() => events.push((id.take(), mem::take(&mut data))),
+        _ => {}
+      }
+    }
+
+    events
+  }
+
+  fn mint(server: &TestServer) -> Txid {
+    let txid = server.core.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0, Witness::new())],
+      mint: true,
+      outputs: 2,
+      op_return: Some(Runestone::default().encipher()),
+      ..default()
+    });
+
+    server.mine_blocks(1);
+
+    txid
+  }
+
+  #[test]
+  fn events_are_streamed_over_sse() {
+    let server = TestServer::builder()
+      .chain(Chain::Regtest)
+      .ord_option("--first-rune-height", "2")
+      .build();
+
+    server.mine_blocks(1);
+
+    let response = reqwest::blocking::get(server.join_url("/events")).unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers().get(header::CONTENT_TYPE).unwrap(),
+      "text/event-stream"
+    );
+
+    let mut response = io::BufReader::new(response);
+
+    let txid = mint(&server);
+
+    let events = server_sent_events(&mut response, 2);
+
+    assert_eq!(events[0].0.as_deref(), Some("2:0"));
+    assert_eq!(events[1].0.as_deref(), Some("2:1"));
+
+    let event = serde_json::from_str::<api::Event>(&events[0].1).unwrap();
+
+    assert_eq!(
+      event.id,
+      EventId {
+        height: 2,
+        sequence: 0
+      }
+    );
+
+    assert!(matches!(
+      event.event,
+      Event::RuneMinted {
+        block_height: 2,
+        txid: minted,
+        ..
+      } if minted == txid
+    ));
+  }
+
+  #[test]
+  fn events_resume_after_last_event_id() {
+    let server = TestServer::builder()
+      .chain(Chain::Regtest)
+      .ord_option("--first-rune-height", "2")
+      .build();
+
+    server.mine_blocks(1);
+
+    let mut stream =
+      io::BufReader::new(reqwest::blocking::get(server.join_url("/events")).unwrap());
+
+    mint(&server);
+
+    let events = server_sent_events(&mut stream, 2);
+
+    let last_event_id = events[0].0.clone().unwrap();
+
+    for response in [
+      reqwest::blocking::Client::new()
+        .get(server.join_url("/events"))
+        .header("Last-Event-ID", &last_event_id)
+        .send()
+        .unwrap(),
+      reqwest::blocking::get(server.join_url(&format!("/events?last_event_id={last_event_id}")))
+        .unwrap(),
+    ] {
+      assert_eq!(
+        server_sent_events(&mut io::BufReader::new(response), 1),
+        events[1..],
+      );
+    }
+  }
+
+  #[test]
+  fn events_are_streamed_over_websocket() {
+    let server = TestServer::builder()
+      .chain(Chain::Regtest)
+      .ord_option("--first-rune-height", "2")
+      .build();
+
+    server.mine_blocks(1);
+
+    let mut url = server.join_url("/events?type=rune_minted");
+    url.set_scheme("ws").unwrap();
+
+    let (mut socket, _response) = tungstenite::connect(url).unwrap();
+
+    let txid = mint(&server);
+
+    let tungstenite::Message::Text(text) = socket.read().unwrap() else {
+      panic!("expected text message");
+    };
+
+    let event = serde_json::from_str::<api::Event>(&text).unwrap();
+
+    assert!(matches!(
+      event.event,
+      Event::RuneMinted { txid: minted, .. } if minted == txid
+    ));
+
+    socket.close(None).unwrap();
+  }
+
+  #[test]
+  fn events_with_invalid_filters_are_rejected() {
+    let server = TestServer::builder().chain(Chain::Regtest).build();
+
+    server.assert_response(
+      "/events?type=rune_minted,foo",
+      StatusCode::BAD_REQUEST,
+      &format!(
+        "unknown event type `foo`, expected one of {}",
+        Event::TYPES.join(", ")
+      ),
+    );
+
+    server.assert_response(
+      "/events?rune=9:9",
+      StatusCode::NOT_FOUND,
+      "rune 9:9 not found",
+    );
+
+    server.assert_response(
+      "/events?address=bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+      StatusCode::BAD_REQUEST,
+      "address bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 belongs to network bitcoin which is different from required regtest",
+    );
+
+    let response = reqwest::blocking::Client::new()
+      .get(server.join_url("/events"))
+      .header("Last-Event-ID", "foo")
+      .send()
+      .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  }
+
+  #[test]
+  fn events_require_json_api() {
+    let server = TestServer::builder()
+      .server_flag("--disable-json-api")
+      .build();
+
+    server.assert_response("/events", StatusCode::NOT_ACCEPTABLE, "JSON API disabled");
+  }
 }
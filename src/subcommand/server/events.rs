@@ -0,0 +1,444 @@
+use {
+  super::*,
+  crate::index::event::{Event, EventId, Filter},
+  axum::response::sse,
+  futures::stream::{self, Stream},
+  std::collections::VecDeque,
+  tokio::sync::{broadcast, mpsc},
+};
+
+/// Index events, broadcast to `/events` subscribers.
+///
+/// Events are numbered as they arrive from the index, and the most recent are
+/// retained, so that clients which reconnect with the ID of the last event
+/// they received are sent the events they missed.
+pub(crate) struct Events {
+  retained: Mutex<Retained>,
+  sender: broadcast::Sender<Arc<api::Event>>,
+}
+
+#[derive(Default)]
+struct Retained {
+  events: VecDeque<Arc<api::Event>>,
+  evicted: Option<EventId>,
+  last: Option<EventId>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Item {
+  Event(Arc<api::Event>),
+  /// Events were dropped before they could be sent, either because the
+  /// client fell behind, or because it resumed from an event which is no
+  /// longer retained
+  Lagged,
+}
+
+impl Item {
+  pub(crate) fn json(&self) -> String {
+    match self {
+      Self::Event(event) => serde_json::to_string(&**event).unwrap(),
+      Self::Lagged => serde_json::json!({ "lagged": true }).to_string(),
+    }
+  }
+
+  pub(crate) fn sse(&self) -> sse::Event {
+    let event = sse::Event::default().data(self.json());
+
+    match self {
+      Self::Event(api_event) => event.id(api_event.id.to_string()),
+      Self::Lagged => event,
+    }
+  }
+}
+
+impl Events {
+  pub(crate) const CHANNEL_CAPACITY: usize = 1024;
+  const RETAINED: usize = 10_000;
+
+  pub(crate) fn new() -> Self {
+    Self {
+      retained: Default::default(),
+      sender: broadcast::channel(Self::RETAINED).0,
+    }
+  }
+
+  /// Publish events sent by the index until it is dropped
+  pub(crate) async fn relay(self: Arc<Self>, mut receiver: mpsc::Receiver<Event>) {
+    while let Some(event) = receiver.recv().await {
+      self.publish(event);
+    }
+  }
+
+  fn publish(&self, event: Event) {
+    let mut retained = self.retained.lock().unwrap();
+
+    let height = event.block_height();
+
+    // events from blocks which were rolled back by a reorg are dropped, and
+    // their IDs are reused. Blocks are also only indexed below the height of
+    // the last event after a reorg, in case the rollback event itself was
+    // missed.
+    let rolled_back = matches!(event, Event::ReorgRolledBack { .. })
+      || retained.last.is_some_and(|last| height < last.height);
+
+    if rolled_back {
+      while retained
+        .events
+        .back()
+        .is_some_and(|event| event.id.height >= height)
+      {
+        retained.events.pop_back();
+      }
+
+      retained.last = retained.events.back().map(|event| event.id);
+    }
+
+    let id = EventId {
+      height,
+      sequence: match retained.last {
+        Some(last) if last.height == height => last.sequence + 1,
+        _ => 0,
+      },
+    };
+
+    if retained.events.len() == Self::RETAINED {
+      retained.evicted = retained.events.pop_front().map(|event| event.id);
+    }
+
+    let event = Arc::new(api::Event { id, event });
+
+    retained.events.push_back(event.clone());
+    retained.last = Some(id);
+
+    // sending only fails if there are no subscribers
+    self.sender.send(event).ok();
+  }
+
+  /// Events matching `filter`, starting after the event with ID `after` if
+  /// given, and with the next event published otherwise
+  pub(crate) fn subscribe(
+    &self,
+    filter: Filter,
+    after: Option<EventId>,
+  ) -> impl Stream<Item = Item> + Send + 'static {
+    let retained = self.retained.lock().unwrap();
+
+    // subscribe while holding the lock, so no events are published between
+    // collecting retained events and subscribing to new ones
+    let receiver = self.sender.subscribe();
+
+    let mut backlog = Vec::new();
+
+    if let Some(after) = after {
+      // events before the oldest retained event may have been missed, either
+      // because they were evicted, or because they were published before the
+      // server restarted
+      let lagged = match retained.evicted {
+        Some(evicted) => after < evicted,
+        None => retained
+          .events
+          .front()
+          .map_or(true, |oldest| after < oldest.id),
+      };
+
+      if lagged {
+        backlog.push(Item::Lagged);
+      }
+
+      backlog.extend(
+        retained
+          .events
+          .iter()
+          .filter(|event| event.id > after)
+          .cloned()
+          .map(Item::Event),
+      );
+    }
+
+    drop(retained);
+
+    stream::iter(backlog)
+      .chain(stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+          Ok(event) => Some((Item::Event(event), receiver)),
+          Err(broadcast::error::RecvError::Lagged(_)) => Some((Item::Lagged, receiver)),
+          Err(broadcast::error::RecvError::Closed) => None,
+        }
+      }))
+      .filter(move |item| match item {
+        Item::Event(event) => filter.matches(&event.event),
+        Item::Lagged => true,
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, futures::FutureExt};
+
+  fn transfer(block_height: u32, rune_id: RuneId, vout: u32) -> Event {
+    Event::RuneTransferred {
+      amount: 1,
+      block_height,
+      outpoint: OutPoint {
+        txid: Txid::all_zeros(),
+        vout,
+      },
+      rune_id,
+      script_pubkey: ScriptBuf::from_bytes(vec![0x51, u8::try_from(vout).unwrap()]),
+      txid: Txid::all_zeros(),
+    }
+  }
+
+  fn id(s: &str) -> Option<EventId> {
+    Some(s.parse().unwrap())
+  }
+
+  /// IDs of the items which are ready to be received from `stream`
+  fn ready(stream: &mut (impl Stream<Item = Item> + Unpin)) -> Vec<String> {
+    let mut ids = Vec::new();
+
+    while let Some(Some(item)) = stream.next().now_or_never() {
+      ids.push(match item {
+        Item::Event(event) => event.id.to_string(),
+        Item::Lagged => "lagged".into(),
+      });
+    }
+
+    ids
+  }
+
+  #[test]
+  fn events_are_numbered_by_height_and_sequence() {
+    let events = Events::new();
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), None));
+
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer(5, ID1, 1));
+    events.publish(transfer(6, ID0, 0));
+
+    assert_eq!(ready(&mut stream), ["5:0", "5:1", "6:0"]);
+  }
+
+  #[test]
+  fn subscribers_without_last_event_id_only_receive_new_events() {
+    let events = Events::new();
+
+    events.publish(transfer(5, ID0, 0));
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), None));
+
+    assert_eq!(ready(&mut stream), Vec::<String>::new());
+
+    events.publish(transfer(6, ID0, 0));
+
+    assert_eq!(ready(&mut stream), ["6:0"]);
+  }
+
+  #[test]
+  fn subscribers_resume_after_last_event_id() {
+    let events = Events::new();
+
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer(5, ID1, 1));
+    events.publish(transfer(6, ID0, 0));
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("5:0")));
+
+    events.publish(transfer(7, ID0, 0));
+
+    assert_eq!(ready(&mut stream), ["5:1", "6:0", "7:0"]);
+  }
+
+  #[test]
+  fn resuming_from_evicted_event_reports_lag() {
+    let events = Events::new();
+
+    for height in 0..u32::try_from(Events::RETAINED).unwrap() + 2 {
+      events.publish(transfer(height, ID0, 0));
+    }
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("0:0")));
+
+    assert_eq!(ready(&mut stream)[..2], ["lagged", "2:0"]);
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("1:0")));
+
+    assert_eq!(ready(&mut stream)[0], "2:0");
+  }
+
+  #[test]
+  fn resuming_from_before_oldest_retained_event_reports_lag() {
+    let events = Events::new();
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("4:0")));
+
+    assert_eq!(ready(&mut stream), ["lagged"]);
+
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer(6, ID0, 0));
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("4:0")));
+
+    assert_eq!(ready(&mut stream), ["lagged", "5:0", "6:0"]);
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("5:0")));
+
+    assert_eq!(ready(&mut stream), ["6:0"]);
+  }
+
+  #[test]
+  fn events_from_rolled_back_blocks_are_dropped() {
+    let events = Events::new();
+
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer(6, ID0, 0));
+    events.publish(transfer(7, ID0, 0));
+    events.publish(transfer(7, ID1, 1));
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("5:0")));
+
+    events.publish(transfer(6, ID1, 3));
+
+    assert_eq!(ready(&mut stream), ["6:0", "7:0", "7:1", "6:0"]);
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("5:0")));
+
+    assert_eq!(ready(&mut stream), ["6:0"]);
+  }
+
+  #[test]
+  fn events_from_blocks_rolled_back_at_the_same_height_are_dropped() {
+    let events = Events::new();
+
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer(6, ID0, 0));
+    events.publish(transfer(6, ID1, 1));
+
+    events.publish(Event::ReorgRolledBack {
+      block_height: 6,
+      depth: 1,
+    });
+
+    events.publish(transfer(6, ID1, 2));
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("5:0")));
+
+    assert_eq!(ready(&mut stream), ["6:0", "6:1"]);
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), id("6:0")));
+
+    assert_eq!(
+      stream.next().now_or_never(),
+      Some(Some(Item::Event(Arc::new(api::Event {
+        id: "6:1".parse().unwrap(),
+        event: transfer(6, ID1, 2),
+      }))))
+    );
+  }
+
+  #[test]
+  fn filters() {
+    let events = Events::new();
+
+    let transfer_to_1 = transfer(5, ID1, 1);
+
+    let Event::RuneTransferred {
+      outpoint,
+      script_pubkey,
+      ..
+    } = transfer_to_1.clone()
+    else {
+      unreachable!()
+    };
+
+    events.publish(transfer(4, ID0, 0));
+    events.publish(transfer(5, ID0, 0));
+    events.publish(transfer_to_1);
+    events.publish(Event::RuneMinted {
+      amount0: 0,
+      amount1: 10,
+      block_height: 5,
+      txid: Txid::all_zeros(),
+    });
+    events.publish(Event::RuneBurned {
+      amount: 1,
+      block_height: 5,
+      rune_id: ID0,
+      txid: Txid::all_zeros(),
+    });
+
+    for (filter, expected) in [
+      (Filter::default(), vec!["5:0", "5:1", "5:2", "5:3"]),
+      (
+        Filter {
+          types: Some(["rune_minted".into(), "rune_burned".into()].into()),
+          ..default()
+        },
+        vec!["5:2", "5:3"],
+      ),
+      (
+        Filter {
+          rune: Some(ID1),
+          ..default()
+        },
+        vec!["5:1", "5:2"],
+      ),
+      (
+        Filter {
+          rune: Some(ID0),
+          ..default()
+        },
+        vec!["5:0", "5:3"],
+      ),
+      (
+        Filter {
+          script_pubkey: Some(script_pubkey.clone()),
+          ..default()
+        },
+        vec!["5:1"],
+      ),
+      (
+        Filter {
+          outpoint: Some(outpoint),
+          ..default()
+        },
+        vec!["5:1"],
+      ),
+      (
+        Filter {
+          outpoint: Some(outpoint),
+          rune: Some(ID0),
+          ..default()
+        },
+        vec![],
+      ),
+    ] {
+      let mut stream = Box::pin(events.subscribe(filter, id("4:0")));
+      assert_eq!(ready(&mut stream), expected);
+    }
+  }
+
+  #[test]
+  fn items_are_serialized_as_json() {
+    let events = Events::new();
+
+    let mut stream = Box::pin(events.subscribe(Filter::default(), None));
+
+    events.publish(transfer(5, ID0, 0));
+
+    let item = stream.next().now_or_never().unwrap().unwrap();
+
+    let Item::Event(event) = &item else {
+      panic!("expected event");
+    };
+
+    assert_eq!(
+      serde_json::from_str::<api::Event>(&item.json()).unwrap(),
+      **event
+    );
+
+    assert_eq!(Item::Lagged.json(), r#"{"lagged":true}"#);
+  }
+}
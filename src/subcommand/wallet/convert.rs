@@ -638,7 +638,9 @@ fn create_unfunded_convert_transaction(
     }
   };
 
-  let unfunded_transaction = Transaction {
+  let version = wallet.get_payload_version()?;
+
+  let mut unfunded_transaction = Transaction {
     version: 2,
     lock_time: LockTime::ZERO,
     input: inputs
@@ -652,7 +654,7 @@ fn create_unfunded_convert_transaction(
       .collect(),
     output: vec![
       TxOut {
-        script_pubkey: runestone.encipher(),
+        script_pubkey: runestone.encipher_with(version),
         value: 0,
       },
       TxOut {
@@ -666,8 +668,15 @@ fn create_unfunded_convert_transaction(
     ],
   };
 
+  // Pointers to the runestone output burn the input rune for conversion
+  let runestone = RunestoneBuilder::from(runestone)
+    .burn_unallocated()
+    .build(&unfunded_transaction.output, version)?;
+
+  unfunded_transaction.output[0].script_pubkey = runestone.encipher_with(version);
+
   assert_eq!(
-    Runestone::decipher(&unfunded_transaction),
+    Runestone::decipher_with(&unfunded_transaction, version),
     Some(Artifact::Runestone(runestone)),
   );
 
@@ -1139,10 +1148,7 @@ fn get_convert_script_private_key() -> PrivateKey {
 }
 
 fn get_convert_script() -> ScriptBuf {
-  let secp = Secp256k1::new();
-  let pubkey = get_convert_script_private_key().public_key(&secp);
-  let wpubkey_hash = pubkey.wpubkey_hash().unwrap();
-  ScriptBuf::new_v0_p2wpkh(&wpubkey_hash)
+  Ledger::conversion_script_pubkey()
 }
 
 fn get_fee(wallet: &Wallet, tx: Transaction, prev_outpoint: Option<OutPointTxOut>) -> Amount {
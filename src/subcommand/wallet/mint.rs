@@ -85,17 +85,10 @@ impl Mint {
 
     let runestone = Runestone { ..default() };
 
-    let op_return_script_pubkey = runestone.encipher();
+    let version = wallet.get_payload_version()?;
 
-    ensure!(
-      op_return_script_pubkey.len() <= 82,
-      "runestone greater than maximum OP_RETURN size: {} > 82",
-      op_return_script_pubkey.len()
-    );
-
-    // 1 CHECKSEQUENCEVERIFY (anyone can spend after 1 block)
-    let mint_script = ScriptBuf::from_bytes(Vec::from(&[0x51, 0xb2]));
-    let mint_script_pubkey = ScriptBuf::new_v0_p2wsh(&mint_script.clone().wscript_hash());
+    let mint_script = Ledger::mint_script();
+    let mint_script_pubkey = Ledger::mint_script_pubkey();
 
     let input = TxIn {
       previous_output: last_mint_outpoint,
@@ -110,7 +103,7 @@ impl Mint {
       fee_for_input = self.fee_rate.fee(input_vb).to_sat();
     }
 
-    let unfunded_transaction = Transaction {
+    let mut unfunded_transaction = Transaction {
       version: 2,
       lock_time: LockTime::ZERO,
       input: Vec::new(),
@@ -124,12 +117,15 @@ impl Mint {
           value: postage.to_sat(),
         },
         TxOut {
-          script_pubkey: op_return_script_pubkey,
+          script_pubkey: runestone.encipher_with(version),
           value: 0,
         },
       ],
     };
 
+    unfunded_transaction.output[2].script_pubkey =
+      runestone.try_encipher_with(&unfunded_transaction.output, version)?;
+
     wallet.lock_non_cardinal_outputs()?;
 
     let fund_transaction_result =
@@ -154,7 +150,10 @@ impl Mint {
       bitcoin_client.sign_raw_transaction_with_wallet(&unsigned_transaction, None, None)?;
 
     assert_eq!(
-      Runestone::decipher(&consensus::encode::deserialize(&signed_transaction.hex)?),
+      Runestone::decipher_with(
+        &consensus::encode::deserialize(&signed_transaction.hex)?,
+        version
+      ),
       Some(Artifact::Runestone(runestone)),
     );
 
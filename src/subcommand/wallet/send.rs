@@ -229,7 +229,9 @@ impl Send {
       ..default()
     };
 
-    let unfunded_transaction = Transaction {
+    let version = wallet.get_payload_version()?;
+
+    let mut unfunded_transaction = Transaction {
       version: 2,
       lock_time: LockTime::ZERO,
       input: inputs
@@ -244,7 +246,7 @@ impl Send {
       output: if needs_runes_change_output {
         vec![
           TxOut {
-            script_pubkey: runestone.encipher(),
+            script_pubkey: runestone.encipher_with(version),
             value: 0,
           },
           TxOut {
@@ -264,6 +266,11 @@ impl Send {
       },
     };
 
+    if needs_runes_change_output {
+      unfunded_transaction.output[0].script_pubkey =
+        runestone.try_encipher_with(&unfunded_transaction.output, version)?;
+    }
+
     let unsigned_transaction =
       fund_raw_transaction(wallet.bitcoin_client(), fee_rate, &unfunded_transaction)?;
 
@@ -271,7 +278,7 @@ impl Send {
 
     if needs_runes_change_output {
       assert_eq!(
-        Runestone::decipher(&unsigned_transaction),
+        Runestone::decipher_with(&unsigned_transaction, version),
         Some(Artifact::Runestone(runestone)),
       );
     }
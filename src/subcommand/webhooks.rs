@@ -0,0 +1,17 @@
+use super::*;
+
+pub mod status;
+
+#[derive(Debug, Parser)]
+pub(crate) enum WebhooksSubcommand {
+  #[command(about = "List pending and dead-lettered webhook deliveries")]
+  Status,
+}
+
+impl WebhooksSubcommand {
+  pub(crate) fn run(self, settings: Settings) -> SubcommandResult {
+    match self {
+      Self::Status => status::run(settings),
+    }
+  }
+}
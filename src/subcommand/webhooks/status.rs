@@ -0,0 +1,48 @@
+use {super::*, crate::index::webhooks::Webhooks};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub pending: Vec<Delivery>,
+  pub dead_letters: Vec<Delivery>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+  pub id: u64,
+  pub url: String,
+  pub events: usize,
+  pub attempts: u32,
+  pub next_attempt: Option<DateTime<Utc>>,
+  pub error: Option<String>,
+}
+
+impl Delivery {
+  fn new(id: u64, delivery: crate::index::webhooks::Delivery, pending: bool) -> Self {
+    Self {
+      id,
+      url: delivery.url,
+      events: delivery.events.len(),
+      attempts: delivery.attempts,
+      next_attempt: pending
+        .then(|| i64::try_from(delivery.next_attempt).ok())
+        .flatten()
+        .and_then(DateTime::from_timestamp_millis),
+      error: delivery.error,
+    }
+  }
+}
+
+pub(crate) fn run(settings: Settings) -> SubcommandResult {
+  let (pending, dead_letters) = Webhooks::status(settings.index())?;
+
+  Ok(Some(Box::new(Output {
+    pending: pending
+      .into_iter()
+      .map(|(id, delivery)| Delivery::new(id, delivery, true))
+      .collect(),
+    dead_letters: dead_letters
+      .into_iter()
+      .map(|(id, delivery)| Delivery::new(id, delivery, false))
+      .collect(),
+  })))
+}
@@ -6,7 +6,7 @@ pub(crate) use {
 };
 
 pub use {
-  blocks::BlocksHtml, rune::RuneHtml, runes::RunesHtml, status::StatusHtml,
+  blocks::BlocksHtml, mints::MintsHtml, rune::RuneHtml, runes::RunesHtml, status::StatusHtml,
   transaction::TransactionHtml,
 };
 
@@ -17,6 +17,7 @@ mod clock;
 mod home;
 mod iframe;
 mod input;
+pub mod mints;
 pub mod output;
 pub mod rune;
 pub mod runes;
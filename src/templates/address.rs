@@ -5,6 +5,10 @@ pub(crate) struct AddressHtml {
   pub(crate) address: Address,
   pub(crate) outputs: Vec<OutPoint>,
   pub(crate) sat_balance: u64,
+  pub(crate) tighten_balance: u128,
+  pub(crate) ease_balance: u128,
+  pub(crate) invariant_balance: u128,
+  pub(crate) util_balance: u128,
 }
 
 impl PageContent for AddressHtml {
@@ -29,11 +33,23 @@ mod tests {
         .unwrap(),
         outputs: vec![outpoint(1), outpoint(2)],
         sat_balance: 99,
+        tighten_balance: 5,
+        ease_balance: 7,
+        invariant_balance: 8,
+        util_balance: 198,
       },
       "<h1>Address bc1phuq0vkls6w926zdaem6x9n02z2gg7j2xfudgwddyey7uyquarlgsh40ev8</h1>
 <dl>
   <dt>sat balance</dt>
   <dd>99</dd>
+  <dt>util balance</dt>
+  <dd>198</dd>
+  <dt>TIGHTEN balance</dt>
+  <dd>5</dd>
+  <dt>EASE balance</dt>
+  <dd>7</dd>
+  <dt>invariant balance</dt>
+  <dd>8</dd>
   <dt>outputs</dt>
   <dd>
     <ul>
@@ -0,0 +1,67 @@
+use super::*;
+
+#[derive(Boilerplate, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MintsHtml {
+  pub entries: Vec<MintEntry>,
+  pub more: bool,
+  pub prev: Option<usize>,
+  pub next: Option<usize>,
+}
+
+impl PageContent for MintsHtml {
+  fn title(&self) -> String {
+    "Mints".to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn html() {
+    let txid = Txid::all_zeros();
+
+    assert_regex_match!(
+      MintsHtml {
+        entries: vec![MintEntry {
+          txid,
+          height: 2,
+          amount0: 50,
+          amount1: 0,
+          recycled0: Some(4),
+          recycled1: Some(0),
+          recipient: Some(1),
+        }],
+        more: false,
+        prev: None,
+        next: Some(1),
+      },
+      format!(
+        "<h1>Mints</h1>
+<table>
+  <tr>
+    <th>height</th>
+    <th>transaction</th>
+    <th>TIGHTEN</th>
+    <th>EASE</th>
+    <th>recycled TIGHTEN</th>
+    <th>recycled EASE</th>
+    <th>recipient</th>
+  </tr>
+  <tr>
+    <td><a href=/block/2>2</a></td>
+    <td><a class=monospace href=/tx/{txid}>{txid}</a></td>
+    <td>50</td>
+    <td>0</td>
+    <td>4</td>
+    <td>0</td>
+    <td><a class=monospace href=/output/{txid}:1>{txid}:1</a></td>
+  </tr>
+</table>
+<div class=center>.*prev.*<a class=next href=/mints/1>next</a>.*</div>
+"
+      ),
+    );
+  }
+}
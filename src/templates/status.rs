@@ -8,13 +8,18 @@ pub struct StatusHtml {
   pub initial_sync_time: Duration,
   pub inscriptions: u64,
   pub lost_sats: u64,
+  pub prune_height: Option<u32>,
   pub runes: u64,
   pub started: DateTime<Utc>,
   pub transaction_index: bool,
+  pub unavailable_features: Vec<String>,
   pub unrecoverably_reorged: bool,
   pub uptime: Duration,
   pub last_mint_outpoint: (OutPoint, u64),
   pub last_conversion_outpoint: (OutPoint, u64),
+  pub state_commitment: Option<sha256::Hash>,
+  /// Runestone payload version required in the next block
+  pub payload_version: Version,
 }
 
 impl PageContent for StatusHtml {
@@ -4,6 +4,7 @@ use super::*;
 pub struct TransactionHtml {
   pub chain: Chain,
   pub etching: Option<SpacedRune>,
+  pub failed_conversion: Option<FailedConversionEntry>,
   pub inscription_count: u32,
   pub transaction: Transaction,
   pub txid: Txid,
@@ -48,6 +49,7 @@ mod tests {
       TransactionHtml {
         chain: Chain::Mainnet,
         etching: None,
+        failed_conversion: None,
         inscription_count: 0,
         txid: transaction.txid(),
         transaction,
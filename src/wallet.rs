@@ -131,6 +131,22 @@ impl Wallet {
     Ok(status_json.last_mint_outpoint)
   }
 
+  /// Runestone payload version the server requires in the next block
+  pub(crate) fn get_payload_version(&self) -> Result<Version> {
+    let response = self
+      .ord_client
+      .get(self.rpc_url.join("/status").unwrap())
+      .send()?;
+
+    if !response.status().is_success() {
+      bail!("wallet failed to fetch status: {}", response.text()?);
+    }
+
+    let status_json: api::Status = serde_json::from_str(&response.text()?)?;
+
+    Ok(status_json.payload_version)
+  }
+
   pub(crate) fn get_util_state(&self) -> Result<api::UtilState> {
     let response = self
       .ord_client
@@ -0,0 +1,371 @@
+use {
+  super::*,
+  bitcoin::{
+    absolute::LockTime, consensus::Encodable, opcodes, script, ScriptBuf, Sequence, Transaction,
+    TxIn, TxOut,
+  },
+  bitomc::subcommand::decode::Output,
+  runes_bitomc::{Cenotaph, Diagnostics, Flaw, Version},
+};
+
+fn transaction(script_pubkey: ScriptBuf) -> Vec<u8> {
+  let transaction = Transaction {
+    version: 2,
+    lock_time: LockTime::ZERO,
+    input: vec![TxIn {
+      previous_output: OutPoint::null(),
+      script_sig: ScriptBuf::new(),
+      sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+      witness: Witness::new(),
+    }],
+    output: vec![TxOut {
+      script_pubkey,
+      value: 0,
+    }],
+  };
+
+  let mut buffer = Vec::new();
+
+  transaction.consensus_encode(&mut buffer).unwrap();
+
+  buffer
+}
+
+fn cenotaph() -> Vec<u8> {
+  transaction(
+    script::Builder::new()
+      .push_opcode(opcodes::all::OP_RETURN)
+      .push_opcode(Runestone::MAGIC_NUMBER)
+      .push_slice([2, 0])
+      .push_opcode(opcodes::all::OP_VERIFY)
+      .into_script(),
+  )
+}
+
+#[test]
+fn from_file() {
+  pretty_assert_eq!(
+    CommandBuilder::new("decode --file transaction.bin")
+      .write(
+        "transaction.bin",
+        transaction(
+          Runestone {
+            edicts: vec![Edict {
+              id: ID1,
+              amount: 5,
+              output: 0,
+            }],
+            pointer: None,
+          }
+          .encipher()
+        )
+      )
+      .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Runestone(Runestone {
+        edicts: vec![Edict {
+          id: ID1,
+          amount: 5,
+          output: 0,
+        }],
+        pointer: None,
+      })),
+      diagnostics: None,
+      failed_conversion: None,
+    },
+  );
+}
+
+#[test]
+fn cenotaph_without_debug() {
+  pretty_assert_eq!(
+    CommandBuilder::new("decode --file transaction.bin")
+      .write("transaction.bin", cenotaph())
+      .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::Opcode),
+      })),
+      diagnostics: None,
+      failed_conversion: None,
+    },
+  );
+}
+
+#[test]
+fn diagnostics_are_omitted_without_debug() {
+  CommandBuilder::new("decode --file transaction.bin")
+    .write("transaction.bin", cenotaph())
+    .stdout_regex(
+      r#"\{
+  "runestone": \{
+    "Cenotaph": \{
+      "flaw": "opcode"
+    \}
+  \}
+\}
+"#,
+    )
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn cenotaph_with_debug() {
+  pretty_assert_eq!(
+    CommandBuilder::new("decode --debug --file transaction.bin")
+      .write("transaction.bin", cenotaph())
+      .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::Opcode),
+      })),
+      diagnostics: Some(Diagnostics {
+        output: 0,
+        flaw: Some(Flaw::Opcode),
+        offset: Some(5),
+        integers: vec![2, 0],
+        edicts: Vec::new(),
+        pointer: None,
+      }),
+      failed_conversion: None,
+    },
+  );
+}
+
+#[test]
+fn varint_cenotaph_with_debug() {
+  pretty_assert_eq!(
+    CommandBuilder::new("decode --debug --file transaction.bin")
+      .write(
+        "transaction.bin",
+        transaction(
+          script::Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_opcode(Runestone::MAGIC_NUMBER)
+            .push_slice([10, 0, 0x80])
+            .into_script()
+        )
+      )
+      .run_and_deserialize_output::<Output>()
+      .diagnostics,
+    Some(Diagnostics {
+      output: 0,
+      flaw: Some(Flaw::Varint),
+      offset: Some(5),
+      integers: vec![10, 0],
+      edicts: vec![Edict {
+        id: ID0,
+        amount: 5,
+        output: 0,
+      }],
+      pointer: None,
+    }),
+  );
+}
+
+#[test]
+fn versioned_payload_after_activation() {
+  let runestone = Runestone {
+    edicts: vec![Edict {
+      id: ID1,
+      amount: 5,
+      output: 0,
+    }],
+    pointer: None,
+  };
+
+  pretty_assert_eq!(
+    CommandBuilder::new(
+      "--regtest --versioned-payload-height 10 decode --file transaction.bin --height 10"
+    )
+    .write(
+      "transaction.bin",
+      transaction(runestone.encipher_with(Version::V1))
+    )
+    .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Runestone(runestone.clone())),
+      diagnostics: None,
+      failed_conversion: None,
+    },
+  );
+
+  pretty_assert_eq!(
+    CommandBuilder::new(
+      "--regtest --versioned-payload-height 10 decode --file transaction.bin --height 10"
+    )
+    .write("transaction.bin", transaction(runestone.encipher()))
+    .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Cenotaph(Cenotaph {
+        flaw: Some(Flaw::UnrecognizedVersion),
+      })),
+      diagnostics: None,
+      failed_conversion: None,
+    },
+  );
+
+  pretty_assert_eq!(
+    CommandBuilder::new(
+      "--regtest --versioned-payload-height 10 decode --file transaction.bin --height 9"
+    )
+    .write("transaction.bin", transaction(runestone.encipher()))
+    .run_and_deserialize_output::<Output>(),
+    Output {
+      runestone: Some(Artifact::Runestone(runestone)),
+      diagnostics: None,
+      failed_conversion: None,
+    },
+  );
+}
+
+#[test]
+fn versioned_payload_from_txid_uses_confirmation_height() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(1);
+
+  let runestone = Runestone {
+    edicts: vec![Edict {
+      id: ID1,
+      amount: 5,
+      output: 0,
+    }],
+    pointer: None,
+  };
+
+  let confirmed = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    op_return: Some(runestone.encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(3);
+
+  let unconfirmed = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(3, 0, 0, Witness::new())],
+    op_return: Some(runestone.encipher_with(Version::V1)),
+    ..default()
+  });
+
+  for txid in [confirmed, unconfirmed] {
+    pretty_assert_eq!(
+      CommandBuilder::new(format!(
+        "--regtest --versioned-payload-height 3 decode --txid {txid}"
+      ))
+      .core(&core)
+      .run_and_deserialize_output::<Output>(),
+      Output {
+        runestone: Some(Artifact::Runestone(runestone.clone())),
+        diagnostics: None,
+        failed_conversion: None,
+      },
+    );
+  }
+}
+
+#[test]
+fn failed_conversion_from_txid() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(1);
+
+  // Mint 50 TIGHTEN and try to convert 20 TIGHTEN to at least 1000 EASE,
+  // burning the input by pointing the refund at the OP_RETURN output
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    convert: true,
+    outputs: 2,
+    op_return: Some(
+      Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 30 * RUNE_COIN_VALUE,
+            output: 0,
+          },
+          Edict {
+            id: ID0,
+            amount: 0,
+            output: 2,
+          },
+          Edict {
+            id: ID1,
+            amount: 1000 * RUNE_COIN_VALUE,
+            output: 0,
+          },
+        ],
+        pointer: Some(2),
+      }
+      .encipher(),
+    ),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "--regtest decode --txid {txid} --failed-conversion"
+  ))
+  .core(&core)
+  .stderr_regex(
+    "error: no read-only copy of the index at `.*index.read-only.redb`, run `bitomc index update --follow` to publish one\n",
+  )
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+
+  let tempdir = Arc::new(TempDir::new().unwrap());
+
+  let mut writer = CommandBuilder::new(
+    "--regtest index update --follow --polling-interval 100ms --publish-interval 0s",
+  )
+  .core(&core)
+  .temp_dir(tempdir.clone())
+  .command()
+  .spawn()
+  .unwrap();
+
+  for attempt in 0.. {
+    if tempdir.path().join("regtest/index.read-only.redb").exists() {
+      break;
+    }
+
+    if attempt == 200 {
+      panic!("Writer did not publish read-only index");
+    }
+
+    thread::sleep(Duration::from_millis(50));
+  }
+
+  // the writer holds the write lock, so this only succeeds by reading the
+  // published copy
+  let output = CommandBuilder::new(format!(
+    "--regtest decode --txid {txid} --failed-conversion"
+  ))
+  .core(&core)
+  .temp_dir(tempdir.clone())
+  .command()
+  .output()
+  .unwrap();
+
+  assert!(output.status.success());
+
+  let output = serde_json::from_slice::<Output>(&output.stdout).unwrap();
+
+  writer.kill().unwrap();
+  writer.wait().unwrap();
+
+  pretty_assert_eq!(
+    output.failed_conversion,
+    Some(FailedConversionEntry {
+      height: 2,
+      direction: Direction::TightenToEase,
+      exact_input: true,
+      input: 20 * RUNE_COIN_VALUE,
+      output: 1000 * RUNE_COIN_VALUE,
+      failure: ConversionFailure::Slippage,
+      refund: None,
+    }),
+  );
+}
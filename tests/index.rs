@@ -1,4 +1,10 @@
-use super::*;
+use {
+  super::*,
+  bitomc::{
+    index::Snapshot,
+    subcommand::index::{export, migrate, verify::Output},
+  },
+};
 
 #[test]
 fn run_is_an_alias_for_update() {
@@ -51,3 +57,191 @@ fn re_opening_database_does_not_trigger_schema_check() {
     .core(&core)
     .run_and_extract_stdout();
 }
+
+#[test]
+fn verify_reports_no_discrepancies() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(1);
+
+  core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let tempdir = TempDir::new().unwrap();
+
+  let index_path = tempdir.path().join("index.redb");
+
+  CommandBuilder::new(format!(
+    "--regtest --index {} index update",
+    index_path.display()
+  ))
+  .core(&core)
+  .run_and_extract_stdout();
+
+  assert_eq!(
+    CommandBuilder::new(format!(
+      "--regtest --index {} index verify",
+      index_path.display()
+    ))
+    .core(&core)
+    .run_and_deserialize_output::<Output>(),
+    Output {
+      discrepancies: Vec::new(),
+    }
+  );
+}
+
+#[test]
+fn export_and_import_snapshot() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let tempdir = TempDir::new().unwrap();
+  let exported = tempdir.path().join("exported.redb");
+  let imported = tempdir.path().join("imported.redb");
+  let snapshot = tempdir.path().join("snapshot.json");
+
+  core.mine_blocks(1);
+
+  core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  core.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "--regtest --index {} --height-limit 3 index update",
+    exported.display(),
+  ))
+  .core(&core)
+  .run_and_extract_stdout();
+
+  let output = CommandBuilder::new(format!(
+    "--regtest --index {} index export --height 2 {}",
+    exported.display(),
+    snapshot.display(),
+  ))
+  .core(&core)
+  .run_and_deserialize_output::<export::Output>();
+
+  assert_eq!(output.height, 2);
+  assert_eq!(output.block_hash, core.state().hashes[2]);
+  assert!(output.state_commitment.is_some());
+
+  CommandBuilder::new(format!(
+    "--regtest --index {} index export --height 1 {}",
+    exported.display(),
+    snapshot.display(),
+  ))
+  .core(&core)
+  .expected_stderr(
+    "error: index is at height 2, not 1, export its tip or rebuild it with `--height-limit 2` to export height 1\n",
+  )
+  .expected_exit_code(1)
+  .run_and_extract_stdout();
+
+  assert_eq!(
+    CommandBuilder::new(format!(
+      "--regtest --index {} index import {}",
+      imported.display(),
+      snapshot.display(),
+    ))
+    .core(&core)
+    .run_and_deserialize_output::<export::Output>(),
+    output,
+  );
+
+  core.broadcast_tx(TransactionTemplate {
+    inputs: &[(2, 1, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let update = |index: &Path| {
+    CommandBuilder::new(format!(
+      "--regtest --index {} index update",
+      index.display()
+    ))
+    .core(&core)
+    .run_and_extract_stdout();
+  };
+
+  update(&imported);
+  update(&exported);
+
+  let export = |index: &Path, name: &str| {
+    let path = tempdir.path().join(name);
+
+    CommandBuilder::new(format!(
+      "--regtest --index {} index export --height 4 {}",
+      index.display(),
+      path.display(),
+    ))
+    .core(&core)
+    .run_and_deserialize_output::<export::Output>();
+
+    serde_json::from_str::<Snapshot>(&fs::read_to_string(path).unwrap()).unwrap()
+  };
+
+  pretty_assert_eq!(
+    export(&imported, "imported.json"),
+    export(&exported, "exported.json")
+  );
+
+  assert_eq!(
+    CommandBuilder::new(format!(
+      "--regtest --index {} index verify",
+      imported.display(),
+    ))
+    .core(&core)
+    .run_and_deserialize_output::<Output>(),
+    Output {
+      discrepancies: Vec::new(),
+    }
+  );
+}
+
+#[test]
+fn migrate_dry_run_lists_no_migrations_for_current_index() {
+  let core = mockcore::spawn();
+
+  let tempdir = TempDir::new().unwrap();
+  let index_path = tempdir.path().join("index.redb");
+
+  CommandBuilder::new(format!("--index {} index update", index_path.display()))
+    .core(&core)
+    .run_and_extract_stdout();
+
+  let output = CommandBuilder::new(format!(
+    "--index {} index migrate --dry-run",
+    index_path.display()
+  ))
+  .core(&core)
+  .run_and_deserialize_output::<migrate::Output>();
+
+  assert!(output.dry_run);
+  assert!(output.migrations.is_empty());
+
+  let output = CommandBuilder::new(format!("--index {} index migrate", index_path.display()))
+    .core(&core)
+    .run_and_deserialize_output::<migrate::Output>();
+
+  assert!(!output.dry_run);
+  assert!(output.migrations.is_empty());
+}
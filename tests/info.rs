@@ -16,7 +16,9 @@ fn json_without_satoshi_index() {
   "metadata_bytes": \d+,
   "outputs_traversed": 0,
   "page_size": \d+,
+  "prune_height": null,
   "sat_ranges": 0,
+  "state_commitment": null,
   "stored_bytes": \d+,
   "tables": .*,
   "total_bytes": \d+,
@@ -27,6 +29,7 @@ fn json_without_satoshi_index() {
     \}
   \],
   "tree_height": \d+,
+  "unavailable_features": \[\],
   "utxos_indexed": 0
 \}
 "#,
@@ -76,3 +79,34 @@ fn transactions() {
   assert_eq!(output[1].end, 11);
   assert_eq!(output[1].count, 10);
 }
+
+#[test]
+fn state_commitment() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(2);
+
+  CommandBuilder::new("--regtest index info")
+    .core(&core)
+    .stdout_regex(r#".*"state_commitment": "[[:xdigit:]]{64}",.*"#)
+    .run_and_extract_stdout();
+}
+
+#[test]
+fn pruned() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  core.mine_blocks(5);
+
+  core.prune(2);
+
+  CommandBuilder::new("--regtest index info")
+    .core(&core)
+    .stdout_regex(
+      r#".*"prune_height": 2,.*"unavailable_features": \[
+    "blocks below height 2",
+    "address index"
+  \],.*"#,
+    )
+    .run_and_extract_stdout();
+}
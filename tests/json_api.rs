@@ -34,6 +34,7 @@ fn get_block() {
       best_height: 1,
       height: 0,
       runes: Vec::new(),
+      state_commitment: None,
       transactions: block_json.transactions.clone(),
     }
   );
@@ -93,6 +94,7 @@ fn get_transaction() {
     api::Transaction {
       chain: Chain::Mainnet,
       etching: None,
+      failed_conversion: None,
       inscription_count: 0,
       transaction,
       txid,
@@ -100,6 +102,117 @@ fn get_transaction() {
   );
 }
 
+#[test]
+fn get_decode_uses_rules_at_confirmation_height() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(
+    &core,
+    &["--regtest", "--versioned-payload-height", "3"],
+    &[],
+  );
+
+  core.mine_blocks(1);
+
+  let runestone = Runestone {
+    edicts: vec![Edict {
+      id: ID1,
+      amount: 5,
+      output: 0,
+    }],
+    pointer: None,
+  };
+
+  let confirmed = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    op_return: Some(runestone.encipher()),
+    ..Default::default()
+  });
+
+  core.mine_blocks(3);
+
+  let unconfirmed = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(3, 0, 0, Witness::new())],
+    op_return: Some(runestone.encipher_with(Version::V1)),
+    ..Default::default()
+  });
+
+  bitomc.sync_server();
+
+  for txid in [confirmed, unconfirmed] {
+    let response = bitomc.json_request(format!("/decode/{txid}"));
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    pretty_assert_eq!(
+      serde_json::from_str::<api::Decode>(&response.text().unwrap())
+        .unwrap()
+        .runestone,
+      Some(Artifact::Runestone(runestone.clone())),
+    );
+  }
+}
+
+#[test]
+fn get_decode_with_debug() {
+  let core = mockcore::spawn();
+
+  let bitomc = TestServer::spawn(&core);
+
+  core.mine_blocks(1);
+
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    op_return: Some(
+      bitcoin::script::Builder::new()
+        .push_opcode(bitcoin::opcodes::all::OP_RETURN)
+        .push_opcode(Runestone::MAGIC_NUMBER)
+        .push_slice([10, 0, 0x80])
+        .into_script(),
+    ),
+    ..Default::default()
+  });
+
+  core.mine_blocks(1);
+
+  let response = bitomc.json_request(format!("/decode/{txid}"));
+
+  assert_eq!(response.status(), StatusCode::OK);
+
+  pretty_assert_eq!(
+    serde_json::from_str::<api::Decode>(&response.text().unwrap()).unwrap(),
+    api::Decode {
+      runestone: Some(Artifact::Cenotaph(runes_bitomc::Cenotaph {
+        flaw: Some(runes_bitomc::Flaw::Varint),
+      })),
+      diagnostics: None,
+      failed_conversion: None,
+    }
+  );
+
+  let response = bitomc.json_request(format!("/decode/{txid}?debug=true"));
+
+  assert_eq!(response.status(), StatusCode::OK);
+
+  let decode = serde_json::from_str::<api::Decode>(&response.text().unwrap()).unwrap();
+
+  pretty_assert_eq!(
+    decode.diagnostics,
+    Some(runes_bitomc::Diagnostics {
+      output: 1,
+      flaw: Some(runes_bitomc::Flaw::Varint),
+      offset: Some(5),
+      integers: vec![10, 0],
+      edicts: vec![Edict {
+        id: ID0,
+        amount: 5,
+        output: 0,
+      }],
+      pointer: None,
+    })
+  );
+}
+
 #[test]
 fn get_status() {
   let core = mockcore::builder().network(Network::Regtest).build();
@@ -134,13 +247,17 @@ fn get_status() {
       initial_sync_time: dummy_duration,
       inscriptions: 0,
       lost_sats: 0,
+      prune_height: None,
       runes: 2,
       started: dummy_started,
       transaction_index: false,
+      unavailable_features: Vec::new(),
       unrecoverably_reorged: false,
       uptime: dummy_duration,
       last_mint_outpoint: (OutPoint::null(), 0),
       last_conversion_outpoint: (OutPoint::null(), 0),
+      state_commitment: None,
+      payload_version: Version::Unversioned,
     }
   );
 }
@@ -251,3 +368,488 @@ fn get_runes_balances() {
 
   pretty_assert_eq!(runes_balance_json, rune_balances);
 }
+
+#[test]
+fn get_util_and_rate_history_at_height() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+
+  core.mine_blocks(3);
+
+  let response = bitomc.json_request("/util");
+  assert_eq!(response.status(), StatusCode::OK);
+  let util_state: api::UtilState = serde_json::from_str(&response.text().unwrap()).unwrap();
+
+  let response = bitomc.json_request("/util?height=3");
+  assert_eq!(response.status(), StatusCode::OK);
+  pretty_assert_eq!(
+    serde_json::from_str::<api::UtilState>(&response.text().unwrap()).unwrap(),
+    util_state,
+  );
+
+  let response = bitomc.json_request("/ratehistory");
+  assert_eq!(response.status(), StatusCode::OK);
+  let rate_history: api::RateHistory = serde_json::from_str(&response.text().unwrap()).unwrap();
+
+  let response = bitomc.json_request("/ratehistory?height=3");
+  assert_eq!(response.status(), StatusCode::OK);
+  pretty_assert_eq!(
+    serde_json::from_str::<api::RateHistory>(&response.text().unwrap()).unwrap(),
+    rate_history,
+  );
+
+  let response = bitomc.json_request("/ratehistory?height=2");
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(
+    serde_json::from_str::<api::RateHistory>(&response.text().unwrap())
+      .unwrap()
+      .history
+      .len(),
+    rate_history.history.len() - 1,
+  );
+
+  assert_eq!(
+    bitomc.json_request("/util?height=1").status(),
+    StatusCode::NOT_FOUND,
+  );
+  assert_eq!(
+    bitomc.json_request("/ratehistory?height=4").status(),
+    StatusCode::NOT_FOUND,
+  );
+}
+
+#[test]
+fn get_conversions() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+
+  core.mine_blocks(1);
+
+  // Mint 50 TIGHTEN and convert 20 TIGHTEN to 40 EASE
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    convert: true,
+    outputs: 2,
+    op_return: Some(
+      Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 30 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+          Edict {
+            id: ID1,
+            amount: 40 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+        ],
+        pointer: Some(2),
+      }
+      .encipher(),
+    ),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let response = bitomc.json_request("/conversions");
+  assert_eq!(response.status(), StatusCode::OK);
+
+  let conversions: api::Conversions = serde_json::from_str(&response.text().unwrap()).unwrap();
+
+  assert_eq!(conversions.entries.len(), 1);
+  assert!(!conversions.more);
+  assert_eq!(conversions.prev, None);
+  assert_eq!(conversions.next, None);
+
+  let (conversion_txid, conversion) = conversions.entries[0];
+
+  assert_eq!(conversion_txid, txid);
+  assert_eq!(conversion.height, 2);
+  assert_eq!(conversion.direction, Direction::TightenToEase);
+  assert!(conversion.exact_input);
+  assert_eq!(conversion.supply_before.supply0, 50 * RUNE_COIN_VALUE);
+  assert_eq!(conversion.supply_before.supply1, 0);
+  assert_eq!(conversion.supply_after.supply0, 30 * RUNE_COIN_VALUE);
+  assert_eq!(conversion.supply_after.supply1, 40 * RUNE_COIN_VALUE);
+
+  let response = bitomc.json_request("/conversions/1");
+  assert_eq!(response.status(), StatusCode::OK);
+
+  pretty_assert_eq!(
+    serde_json::from_str::<api::Conversions>(&response.text().unwrap()).unwrap(),
+    api::Conversions {
+      entries: Vec::new(),
+      more: false,
+      prev: Some(0),
+      next: None,
+    }
+  );
+}
+
+#[test]
+fn simulate_verbose() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+
+  core.mine_blocks(1);
+
+  // Mint 50 TIGHTEN and convert 20 TIGHTEN to 40 EASE
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    convert: true,
+    outputs: 2,
+    op_return: Some(
+      Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 30 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+          Edict {
+            id: ID1,
+            amount: 40 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+        ],
+        pointer: Some(2),
+      }
+      .encipher(),
+    ),
+    ..default()
+  });
+
+  bitomc.sync_server();
+
+  let simulate = |path: &str| {
+    let response = reqwest::blocking::Client::new()
+      .post(bitomc.url().join(path).unwrap())
+      .header(reqwest::header::ACCEPT, "application/json")
+      .json(&core.mempool())
+      .send()
+      .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.text().unwrap()
+  };
+
+  let states = serde_json::from_str::<Vec<api::SupplyState>>(&simulate("/simulate")).unwrap();
+
+  let simulations =
+    serde_json::from_str::<Vec<api::Simulation>>(&simulate("/simulate?verbose=true")).unwrap();
+
+  assert_eq!(simulations.len(), 1);
+
+  let simulation = &simulations[0];
+
+  assert_eq!(simulation.txid, txid);
+  assert_eq!(states, [simulation.supply_state]);
+  assert!(matches!(
+    simulation.artifact,
+    Some(Artifact::Runestone(Runestone {
+      pointer: Some(2),
+      ..
+    }))
+  ));
+  assert_eq!(
+    simulation.allocations,
+    [(
+      1,
+      api::RuneAmounts {
+        tighten: 30 * RUNE_COIN_VALUE,
+        ease: 40 * RUNE_COIN_VALUE,
+      }
+    )]
+    .into(),
+  );
+  assert_eq!(simulation.burned, api::RuneAmounts::default());
+  assert_eq!(
+    simulation.minted,
+    Some(api::RuneAmounts {
+      tighten: 50 * RUNE_COIN_VALUE,
+      ease: 0,
+    }),
+  );
+  assert_eq!(simulation.failed_conversion, None);
+
+  core.mine_blocks(1);
+
+  let response = bitomc.json_request("/conversions");
+  assert_eq!(response.status(), StatusCode::OK);
+
+  let conversions: api::Conversions = serde_json::from_str(&response.text().unwrap()).unwrap();
+
+  assert_eq!(
+    conversions.entries,
+    [(txid, simulation.conversion.unwrap())]
+  );
+
+  let response = bitomc.json_request("/util");
+  assert_eq!(response.status(), StatusCode::OK);
+
+  assert_eq!(
+    serde_json::from_str::<api::UtilState>(&response.text().unwrap()).unwrap(),
+    simulation.util_state,
+  );
+}
+
+#[test]
+fn failed_conversion_is_reported() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+
+  core.mine_blocks(1);
+
+  // Mint 50 TIGHTEN and try to convert 20 TIGHTEN to at least 1000 EASE
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    convert: true,
+    outputs: 2,
+    op_return: Some(
+      Runestone {
+        edicts: vec![
+          Edict {
+            id: ID0,
+            amount: 30 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+          Edict {
+            id: ID1,
+            amount: 1000 * RUNE_COIN_VALUE,
+            output: 1,
+          },
+        ],
+        pointer: Some(2),
+      }
+      .encipher(),
+    ),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let failed_conversion = Some(FailedConversionEntry {
+    height: 2,
+    direction: Direction::TightenToEase,
+    exact_input: true,
+    input: 20 * RUNE_COIN_VALUE,
+    output: 1000 * RUNE_COIN_VALUE,
+    failure: ConversionFailure::Slippage,
+    refund: Some(1),
+  });
+
+  let response = bitomc.json_request(format!("/decode/{txid}"));
+  assert_eq!(response.status(), StatusCode::OK);
+  pretty_assert_eq!(
+    serde_json::from_str::<api::Decode>(&response.text().unwrap())
+      .unwrap()
+      .failed_conversion,
+    failed_conversion,
+  );
+
+  let response = bitomc.json_request(format!("/tx/{txid}"));
+  assert_eq!(response.status(), StatusCode::OK);
+  pretty_assert_eq!(
+    serde_json::from_str::<api::Transaction>(&response.text().unwrap())
+      .unwrap()
+      .failed_conversion,
+    failed_conversion,
+  );
+
+  bitomc.assert_response_regex(
+    format!("/tx/{txid}"),
+    format!(
+      ".*<dt>failed conversion</dt>
+  <dd>TIGHTEN → EASE: output below minimum or input above maximum</dd>
+  <dt>conversion input</dt>
+  <dd>2000000000 returned to <a class=monospace href=/output/{txid}:1>{txid}:1</a></dd>.*"
+    ),
+  );
+
+  let balances = CommandBuilder::new("--regtest balances")
+    .core(&core)
+    .run_and_deserialize_output::<bitomc::subcommand::balances::Output>();
+
+  assert_eq!(
+    balances.runes[&SpacedRune {
+      rune: Rune(TIGHTEN),
+      spacers: 0
+    }][&OutPoint { txid, vout: 1 }]
+      .amount,
+    50 * RUNE_COIN_VALUE,
+  );
+}
+
+#[test]
+fn get_mints() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+
+  core.mine_blocks(1);
+
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let response = bitomc.json_request("/mints");
+  assert_eq!(response.status(), StatusCode::OK);
+
+  pretty_assert_eq!(
+    serde_json::from_str::<api::Mints>(&response.text().unwrap()).unwrap(),
+    api::Mints {
+      entries: vec![MintEntry {
+        txid,
+        height: 2,
+        amount0: 50 * RUNE_COIN_VALUE,
+        amount1: 0,
+        recycled0: Some(0),
+        recycled1: Some(0),
+        recipient: Some(1),
+      }],
+      more: false,
+      prev: None,
+      next: None,
+    }
+  );
+
+  bitomc.assert_response_regex(
+    "/mints",
+    format!(".*<h1>Mints</h1>.*<td><a class=monospace href=/tx/{txid}>{txid}</a></td>.*"),
+  );
+}
+
+#[test]
+fn get_address_rune_balances() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest", "--index-addresses"], &[]);
+
+  core.mine_blocks(1);
+
+  let txid = core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let output = bitomc.json_request(format!("/output/{txid}:1"));
+  assert_eq!(output.status(), StatusCode::OK);
+
+  let address = serde_json::from_str::<api::Output>(&output.text().unwrap())
+    .unwrap()
+    .address
+    .unwrap()
+    .assume_checked();
+
+  let address_info = || {
+    let response = bitomc.json_request(format!("/address/{address}"));
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_str::<api::AddressInfo>(&response.text().unwrap()).unwrap()
+  };
+
+  let info = address_info();
+
+  assert!(info.outputs.contains(&OutPoint { txid, vout: 1 }));
+
+  assert_eq!(info.tighten_balance, 50 * RUNE_COIN_VALUE);
+  assert_eq!(info.ease_balance, 0);
+
+  // with no Ease outstanding, a unit of Tighten is worth a unit of the
+  // invariant
+  assert_eq!(info.invariant_balance, 50 * RUNE_COIN_VALUE);
+
+  let util_state =
+    serde_json::from_str::<api::UtilState>(&bitomc.json_request("/util").text().unwrap()).unwrap();
+
+  assert_eq!(
+    info.util_balance,
+    u128::from(info.sat_balance) * util_state.utils_per_sat / util_state.decimals,
+  );
+
+  bitomc.assert_response_regex(
+    format!("/address/{address}"),
+    format!(
+      ".*<dt>TIGHTEN balance</dt>\n  <dd>{}</dd>\n  <dt>EASE balance</dt>\n  <dd>0</dd>.*",
+      50 * RUNE_COIN_VALUE
+    ),
+  );
+
+  core.broadcast_tx(TransactionTemplate {
+    inputs: &[(2, 1, 1, Witness::new())],
+    outputs: 0,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let info = address_info();
+
+  assert!(!info.outputs.contains(&OutPoint { txid, vout: 1 }));
+
+  assert_eq!(info.tighten_balance, 0);
+  assert_eq!(info.ease_balance, 0);
+  assert_eq!(info.invariant_balance, 0);
+}
+
+#[test]
+fn state_commitments_agree_across_indexes() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(&core, &["--regtest"], &[]);
+  let with_addresses =
+    TestServer::spawn_with_server_args(&core, &["--regtest", "--index-addresses"], &[]);
+
+  core.mine_blocks(1);
+
+  core.broadcast_tx(TransactionTemplate {
+    inputs: &[(1, 0, 0, Witness::new())],
+    mint: true,
+    outputs: 2,
+    op_return: Some(Runestone::default().encipher()),
+    ..default()
+  });
+
+  core.mine_blocks(1);
+
+  let block = |server: &TestServer| {
+    let response = server.json_request("/block/2");
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_str::<api::Block>(&response.text().unwrap()).unwrap()
+  };
+
+  let commitment = block(&bitomc).state_commitment;
+
+  assert!(commitment.is_some());
+  assert_eq!(block(&with_addresses).state_commitment, commitment);
+
+  let response = bitomc.json_request("/status");
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(
+    serde_json::from_str::<api::Status>(&response.text().unwrap())
+      .unwrap()
+      .state_commitment,
+    commitment,
+  );
+}
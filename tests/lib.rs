@@ -8,14 +8,18 @@ use {
     Network, OutPoint, Witness,
   },
   bitcoincore_rpc::bitcoincore_rpc_json::ListDescriptorsResult,
-  bitomc::{api, chain::Chain, outgoing::Outgoing, RuneEntry},
+  bitomc::{
+    api, chain::Chain, outgoing::Outgoing, Direction, FailedConversionEntry, MintEntry, RuneEntry,
+  },
   chrono::{DateTime, Utc},
   executable_path::executable_path,
   mockcore::TransactionTemplate,
   pretty_assertions::assert_eq as pretty_assert_eq,
   regex::Regex,
   reqwest::{StatusCode, Url},
-  runes_bitomc::{Artifact, Edict, Pile, Rune, RuneId, Runestone, SpacedRune},
+  runes_bitomc::{
+    Artifact, ConversionFailure, Edict, Pile, Rune, RuneId, Runestone, SpacedRune, Version,
+  },
   serde::de::DeserializeOwned,
   std::sync::Arc,
   std::{
@@ -49,6 +53,7 @@ mod expected;
 mod test_server;
 
 mod balances;
+mod decode;
 mod index;
 mod info;
 mod json_api;
@@ -56,6 +61,7 @@ mod server;
 mod settings;
 mod version;
 mod wallet;
+mod webhooks;
 
 const TIGHTEN: u128 = 0;
 const EASE: u128 = 1;
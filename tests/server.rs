@@ -324,3 +324,88 @@ fn ctrl_c() {
     thread::sleep(Duration::from_millis(50));
   }
 }
+
+#[test]
+fn read_only_servers_follow_published_index() {
+  let core = mockcore::spawn();
+
+  let tempdir = Arc::new(TempDir::new().unwrap());
+
+  let mut writer =
+    CommandBuilder::new("index update --follow --polling-interval 100ms --publish-interval 0s")
+      .core(&core)
+      .temp_dir(tempdir.clone())
+      .command()
+      .spawn()
+      .unwrap();
+
+  let ports = (0..2)
+    .map(|_| {
+      TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+    })
+    .collect::<Vec<u16>>();
+
+  let wait_for_block_count = |port: u16, expected: &str| {
+    for attempt in 0.. {
+      if let Ok(response) = reqwest::blocking::get(format!("http://localhost:{port}/blockcount")) {
+        if response.status() == 200 && response.text().unwrap() == expected {
+          break;
+        }
+      }
+
+      if attempt == 200 {
+        panic!("Server did not serve block count {expected}");
+      }
+
+      thread::sleep(Duration::from_millis(50));
+    }
+  };
+
+  for attempt in 0.. {
+    if tempdir.path().join("index.read-only.redb").exists() {
+      break;
+    }
+
+    if attempt == 200 {
+      panic!("Writer did not publish read-only index");
+    }
+
+    thread::sleep(Duration::from_millis(50));
+  }
+
+  let mut servers = ports
+    .iter()
+    .map(|port| {
+      CommandBuilder::new(format!(
+        "server --read-only --address 127.0.0.1 --http-port {port}"
+      ))
+      .core(&core)
+      .temp_dir(tempdir.clone())
+      .command()
+      .spawn()
+      .unwrap()
+    })
+    .collect::<Vec<Child>>();
+
+  for port in &ports {
+    wait_for_block_count(*port, "1");
+  }
+
+  core.mine_blocks(2);
+
+  for port in &ports {
+    wait_for_block_count(*port, "3");
+  }
+
+  writer.kill().unwrap();
+  writer.wait().unwrap();
+
+  for server in &mut servers {
+    server.kill().unwrap();
+    server.wait().unwrap();
+  }
+}
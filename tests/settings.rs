@@ -11,12 +11,14 @@ fn default() {
   "bitcoin_rpc_password": null,
   "bitcoin_rpc_url": "127.0.0.1:8332",
   "bitcoin_rpc_username": null,
+  "blk_files": false,
   "chain": "mainnet",
   "commit_interval": 5000,
   "config": null,
   "config_dir": null,
   "cookie_file": ".*\.cookie",
   "data_dir": ".*",
+  "esplora_url": null,
   "first_rune_height": 855000,
   "height_limit": null,
   "http_port": null,
@@ -27,7 +29,10 @@ fn default() {
   "integration_test": false,
   "server_password": null,
   "server_url": null,
-  "server_username": null
+  "server_username": null,
+  "versioned_payload_height": null,
+  "webhooks": \[\],
+  "zmq_block": null
 \}
 "#,
     )
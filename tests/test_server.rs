@@ -2,7 +2,7 @@ use {
   super::*,
   axum_server::Handle,
   bitcoincore_rpc::{Auth, Client, RpcApi},
-  bitomc::{parse_ord_server_args, Index},
+  bitomc::parse_ord_server_args,
   reqwest::blocking::Response,
 };
 
@@ -50,13 +50,18 @@ impl TestServer {
       ord_server_args.join(" "),
     ));
 
-    let index = Arc::new(Index::open(&settings).unwrap());
+    let (index, event_receiver) = server.open_index(&settings).unwrap();
+    let index = Arc::new(index);
     let ord_server_handle = Handle::new();
 
     {
       let index = index.clone();
       let ord_server_handle = ord_server_handle.clone();
-      thread::spawn(|| server.run(settings, index, ord_server_handle).unwrap());
+      thread::spawn(|| {
+        server
+          .run(settings, index, event_receiver, ord_server_handle)
+          .unwrap()
+      });
     }
 
     for i in 0.. {
@@ -743,3 +743,75 @@ fn error_messages_use_spaced_runes() {
   .expected_stderr("error: rune `FOO` has not been etched\n")
   .run_and_extract_stdout();
 }
+
+#[test]
+fn minting_sending_and_converting_after_versioned_payload_activation() {
+  let core = mockcore::builder().network(Network::Regtest).build();
+
+  let bitomc = TestServer::spawn_with_server_args(
+    &core,
+    &["--regtest", "--versioned-payload-height", "2"],
+    &[],
+  );
+
+  core.mine_blocks(2);
+
+  create_wallet(&core, &bitomc);
+
+  CommandBuilder::new("--chain regtest wallet mint --fee-rate 1")
+    .core(&core)
+    .bitomc(&bitomc)
+    .run_and_deserialize_output::<bitomc::subcommand::wallet::mint::Output>();
+
+  core.mine_blocks(1);
+
+  let send = CommandBuilder::new(format!(
+    "--chain regtest wallet send --fee-rate 1 bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw 5:{}",
+    Rune(TIGHTEN)
+  ))
+  .core(&core)
+  .bitomc(&bitomc)
+  .run_and_deserialize_output::<Send>();
+
+  core.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "--chain regtest wallet convert-exact-input --fee-rate 1 20:{} 1:{}",
+    Rune(TIGHTEN),
+    Rune(EASE)
+  ))
+  .core(&core)
+  .bitomc(&bitomc)
+  .run_and_deserialize_output::<bitomc::subcommand::wallet::convert::OutputForExactInput>();
+
+  core.mine_blocks(1);
+
+  let balances = CommandBuilder::new("--regtest balances")
+    .core(&core)
+    .bitomc(&bitomc)
+    .run_and_deserialize_output::<bitomc::subcommand::balances::Output>();
+
+  let total = |rune| {
+    balances
+      .runes
+      .get(&SpacedRune::new(Rune(rune), 0))
+      .map(|piles| piles.values().map(|pile| pile.amount).sum::<u128>())
+      .unwrap_or_default()
+  };
+
+  // two mints of 50 TIGHTEN, less the 20 converted, none burned by cenotaphs
+  assert_eq!(total(TIGHTEN), 80 * RUNE_COIN_VALUE);
+  assert!(total(EASE) > 0);
+
+  pretty_assert_eq!(
+    balances.runes[&SpacedRune::new(Rune(TIGHTEN), 0)][&OutPoint {
+      txid: send.txid,
+      vout: 2
+    }],
+    Pile {
+      amount: 5 * RUNE_COIN_VALUE,
+      divisibility: 8,
+      symbol: None
+    },
+  );
+}
@@ -0,0 +1,220 @@
+use {
+  super::*,
+  axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router},
+  bitcoin::hashes::{hmac, sha256, Hash, HashEngine},
+  bitomc::subcommand::webhooks::status::Output,
+  std::sync::Mutex,
+};
+
+type Requests = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+fn spawn_receiver() -> (String, Requests) {
+  let requests = Requests::default();
+
+  let router = Router::new()
+    .route(
+      "/hook",
+      post(
+        |State(requests): State<Requests>, headers: HeaderMap, body: Bytes| async move {
+          requests.lock().unwrap().push((headers, body));
+        },
+      ),
+    )
+    .with_state(requests.clone());
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+
+  listener.set_nonblocking(true).unwrap();
+
+  thread::spawn(move || {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+      axum::Server::from_tcp(listener)
+        .unwrap()
+        .serve(router.into_make_service())
+        .await
+        .unwrap()
+    })
+  });
+
+  (format!("http://127.0.0.1:{port}/hook"), requests)
+}
+
+fn free_port() -> u16 {
+  TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port()
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+  let mut engine = hmac::HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+  engine.input(body);
+  hmac::Hmac::<sha256::Hash>::from_engine(engine).to_string()
+}
+
+fn status(core: &mockcore::Handle, tempdir: &Arc<TempDir>) -> Output {
+  let output = CommandBuilder::new("webhooks status")
+    .core(core)
+    .temp_dir(tempdir.clone())
+    .command()
+    .output()
+    .unwrap();
+
+  assert!(output.status.success());
+
+  serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn server_delivers_signed_event_batches() {
+  let core = mockcore::spawn();
+
+  let (url, requests) = spawn_receiver();
+
+  let mut server = CommandBuilder::new(format!(
+    "server --address 127.0.0.1 --http-port {}",
+    free_port()
+  ))
+  .core(&core)
+  .write(
+    "bitomc.yaml",
+    format!("webhooks:\n- url: {url}\n  secret: secret\n  types: [block_committed]\n"),
+  )
+  .spawn();
+
+  let batches = |count: usize| {
+    for attempt in 0.. {
+      let requests = requests.lock().unwrap();
+
+      if requests.len() >= count {
+        return requests
+          .iter()
+          .map(|(headers, body)| {
+            assert_eq!(
+              headers["x-bitomc-signature"],
+              format!("sha256={}", signature("secret", body)),
+            );
+            serde_json::from_slice::<api::WebhookBatch>(body).unwrap()
+          })
+          .collect::<Vec<api::WebhookBatch>>();
+      }
+
+      drop(requests);
+
+      if attempt == 200 {
+        panic!("Webhook did not receive {count} batches");
+      }
+
+      thread::sleep(Duration::from_millis(50));
+    }
+
+    unreachable!()
+  };
+
+  assert_eq!(
+    serde_json::to_value(&batches(1)[0]).unwrap(),
+    serde_json::json!({
+      "id": 0,
+      "events": [{
+        "block_committed": {
+          "block_hash": core.state().hashes[0],
+          "block_height": 0,
+        }
+      }],
+    }),
+  );
+
+  core.mine_blocks(1);
+
+  let hash = core.state().hashes[1];
+
+  assert_eq!(
+    serde_json::to_value(&batches(2)[1]).unwrap(),
+    serde_json::json!({
+      "id": 1,
+      "events": [{
+        "block_committed": {
+          "block_hash": hash,
+          "block_height": 1,
+        }
+      }],
+    }),
+  );
+
+  server.child.kill().unwrap();
+  server.child.wait().unwrap();
+}
+
+#[test]
+fn status_lists_pending_and_dead_lettered_deliveries() {
+  let core = mockcore::spawn();
+
+  let tempdir = Arc::new(TempDir::new().unwrap());
+
+  fs::write(
+    tempdir.path().join("bitomc.yaml"),
+    format!(
+      "webhooks:\n- url: http://127.0.0.1:{}/hook\n  secret: secret\n  max_attempts: 1\n",
+      free_port()
+    ),
+  )
+  .unwrap();
+
+  assert!(CommandBuilder::new("index update")
+    .core(&core)
+    .temp_dir(tempdir.clone())
+    .command()
+    .status()
+    .unwrap()
+    .success());
+
+  let output = status(&core, &tempdir);
+
+  assert_eq!(output.pending.len(), 1);
+  assert_eq!(output.pending[0].id, 0);
+  assert_eq!(output.pending[0].events, 1);
+  assert_eq!(output.pending[0].attempts, 0);
+  assert!(output.dead_letters.is_empty());
+
+  let mut server = CommandBuilder::new(format!(
+    "server --address 127.0.0.1 --http-port {}",
+    free_port()
+  ))
+  .core(&core)
+  .temp_dir(tempdir.clone())
+  .spawn();
+
+  for attempt in 0.. {
+    let output = status(&core, &tempdir);
+
+    if let Some(dead_letter) = output.dead_letters.first() {
+      assert_eq!(dead_letter.id, 0);
+      assert_eq!(dead_letter.attempts, 1);
+      assert_eq!(dead_letter.next_attempt, None);
+      assert!(dead_letter.error.is_some());
+      break;
+    }
+
+    if attempt == 100 {
+      panic!("Delivery was not dead-lettered");
+    }
+
+    thread::sleep(Duration::from_millis(100));
+  }
+
+  server.child.kill().unwrap();
+  server.child.wait().unwrap();
+}
+
+#[test]
+fn status_requires_outbox() {
+  let core = mockcore::spawn();
+
+  CommandBuilder::new("webhooks status")
+    .core(&core)
+    .stderr_regex("error: no webhook outbox at `.*index.webhooks.redb`.*")
+    .expected_exit_code(1)
+    .run_and_extract_stdout();
+}